@@ -9,11 +9,111 @@ export class ExternalObject<T> {
     [K: symbol]: T
   }
 }
+export interface NativeScannedNote {
+  /** Index of the transaction in the batch */
+  transactionIndex: number
+  /** Index of the note among the transaction's notes */
+  noteIndex: number
+  /** The serialized note */
+  note: Buffer
+  /** "received", "change" or "selfSend" */
+  origin: string
+}
 export interface NativeSpendProof {
   treeSize: number
   rootHash: Buffer
   nullifier: Buffer
 }
+export interface NativeStoreEntry {
+  key: Buffer
+  value: Buffer
+}
+/** A change to make in Store.write. Omitting the value deletes the key. */
+export interface NativeBatchOperation {
+  key: Buffer
+  value?: Buffer | undefined | null
+}
+/**
+ * Consensus rules to enforce when verifying a transaction. Omitted fields
+ * apply no restriction.
+ */
+export interface NativeVerificationContext {
+  sequence?: number | undefined | null
+  maxSpends?: number | undefined | null
+  maxReceipts?: number | undefined | null
+  allowNegativeFee?: boolean | undefined | null
+  maxMinersFee?: number | undefined | null
+  /**
+   * Skip spend proofs that were already verified by this process, and
+   * remember the ones verified now. Useful when reconnecting blocks
+   * during a reorg.
+   */
+  useSpendProofCache?: boolean | undefined | null
+  /**
+   * Likewise for receipt proofs, so transactions verified in the mempool
+   * aren't proven again when they arrive in a block.
+   */
+  useReceiptProofCache?: boolean | undefined | null
+}
+export interface NativeProofCacheStats {
+  hits: number
+  misses: number
+  evictions: number
+  entries: number
+}
+export interface NativeMovedNoteCommitment {
+  noteCommitment: Buffer
+  oldPosition: number
+  newPosition: number
+}
+export interface NativeTransactionDiff {
+  addedNullifiers: Array<Buffer>
+  removedNullifiers: Array<Buffer>
+  addedNoteCommitments: Array<Buffer>
+  removedNoteCommitments: Array<Buffer>
+  /**
+   * Notes in both blocks whose position in the note tree changed, so
+   * their nullifiers must be recomputed
+   */
+  movedNoteCommitments: Array<NativeMovedNoteCommitment>
+}
+export interface NativeChainEvent {
+  /** "noteCommitmentAdded" or "nullifierSpent" */
+  kind: string
+  /** Index of the transaction in the list the events were extracted from */
+  transactionIndex: number
+  transactionHash: Buffer
+  /** Index of the receipt or spend in the transaction */
+  index: number
+  /** Position of an added note among the notes added by the transactions */
+  position?: number | undefined | null
+  /** The note commitment or nullifier */
+  value: Buffer
+}
+export interface NativeTransactionVerification {
+  valid: boolean
+  /** Why the transaction was rejected, if it was */
+  error?: string | undefined | null
+}
+export interface NativeRecipient {
+  publicAddress: string
+  value: bigint
+  memo: string
+}
+export interface NativeSentNote {
+  /** Index of the output the note was recovered from */
+  index: number
+  /** The serialized note */
+  note: Buffer
+}
+export interface NativeDeposit {
+  /** Index of the output the deposit was found in */
+  index: number
+  /** The user the deposit tag was issued to */
+  userId: bigint
+  /** The serialized note */
+  note: Buffer
+}
 export interface Key {
   spending_key: string
   incoming_view_key: string
@@ -22,7 +122,160 @@ export interface Key {
 }
 export function generateKey(): Key
 export function generateNewPublicAddress(privateKey: string): Key
+/**
+ * Derive the Iron Fish account for a bech32 encoded Zcash Sapling extended
+ * spending key. The same Zcash key always derives the same account.
+ */
+export function generateKeyFromZcash(zcashKey: string): Key
+/**
+ * Sign a message with the spending key of one of the key's addresses,
+ * without creating a transaction, to prove ownership of the address. The
+ * signature reveals the address's incoming view key.
+ */
+export function signMessage(spendingKey: string, publicAddress: string, message: Buffer): Buffer
+/** Check a signature made with signMessage. */
+export function verifyMessage(publicAddress: string, message: Buffer, signature: Buffer): boolean
 export function initializeSapling(): void
+export interface ParamsFingerprint {
+  spend_params?: Buffer | undefined | null
+  output_params?: Buffer | undefined | null
+  spend_verifying_key: Buffer
+  output_verifying_key: Buffer
+}
+/**
+ * Hashes of the loaded Sapling parameters and verifying keys, to compare
+ * against the fingerprints the network expects.
+ */
+export function paramsFingerprint(): ParamsFingerprint
+/** The sequence of the genesis block starts at 1 */
+export const GENESIS_BLOCK_SEQUENCE: number
+/** The amount of coins in the genesis block */
+export const GENESIS_SUPPLY_IN_IRON: number
+/** The number of ore in one iron */
+export const ORE_PER_IRON: number
+/** How many seconds into the future a block's timestamp may be */
+export const ALLOWED_BLOCK_FUTURE_SECONDS: number
+/** The maximum allowed requested blocks by the network */
+export const MAX_REQUESTED_BLOCKS: number
+/** Max size for a message, for instance when requesting batches of blocks */
+export const MAX_MESSAGE_SIZE: number
+/** The average time that all blocks should be mined */
+export const TARGET_BLOCK_TIME_IN_SECONDS: number
+/** The time range when difficulty and target not change */
+export const TARGET_BUCKET_TIME_IN_SECONDS: number
+/** Graffiti sizes in bytes */
+export const GRAFFITI_SIZE: number
+/** An approximation of how many blocks make up a year */
+export const IRON_FISH_YEAR_IN_BLOCKS: number
+/** The most transactions a miner puts in a block template */
+export const MAX_TRANSACTIONS_PER_BLOCK: number
+/** The reward, in ore, for mining the block at the given sequence */
+export function blockReward(sequence: number): bigint
+export function spendProofCacheStats(): NativeProofCacheStats
+/**
+ * Forget the cached spend proofs for a nullifier, such as when the block
+ * containing the spend is disconnected for good.
+ */
+export function invalidateSpendProofCache(nullifier: Buffer): void
+export function clearSpendProofCache(): void
+export function receiptProofCacheStats(): NativeProofCacheStats
+export function clearReceiptProofCache(): void
+/**
+ * Compare the serialized transactions of a block being disconnected with
+ * those of the block replacing it, returning the nullifiers and note
+ * commitments that were added or removed, and the notes that moved. The
+ * tree sizes are the size of the note tree before each block.
+ */
+export function diffTransactions(oldTransactions: Array<Buffer>, oldTreeSize: number, newTransactions: Array<Buffer>, newTreeSize: number): NativeTransactionDiff
+/**
+ * List the note commitments added and nullifiers spent by serialized
+ * transactions, such as those of a verified block, in the order they are
+ * applied.
+ */
+export function extractEvents(transactions: Array<Buffer>): Array<NativeChainEvent>
+/**
+ * Verify serialized transactions together on a worker thread, returning
+ * whether each one is valid.
+ *
+ * The buffers are read in place rather than copied, and the counts of
+ * descriptions are checked against the context before they are read.
+ */
+export function batchVerifyTransactions(transactions: Array<Buffer>, context?: NativeVerificationContext | undefined | null): Promise<Array<NativeTransactionVerification>>
+/**
+ * Build, prove and sign a transaction paying the recipients from the given
+ * serialized notes on a worker thread, returning the serialized posted
+ * transaction.
+ *
+ * Notes are selected in the order given until they cover the payments and
+ * the fee, and change is returned to the spender. Each note needs the
+ * witness at the same index.
+ *
+ * Throws if a recipient's memo is longer than MEMO_SIZE bytes, rather than
+ * truncating it.
+ */
+export function sendTransaction(spenderHexKey: string, notes: Array<Buffer>, witnesses: Array<object>, recipients: Array<NativeRecipient>, transactionFee: bigint, expirationSequence: number): Promise<Buffer>
+/**
+ * The serialized size of a transaction with the given number of spends and
+ * outputs, including any change output, without building it.
+ */
+export function estimateTransactionSize(spendCount: number, outputCount: number): number
+/**
+ * The fee for a transaction with the given number of spends and outputs,
+ * at a fee rate in ore per serialized byte.
+ */
+export function estimateFee(spendCount: number, outputCount: number, feeRate: bigint): bigint
+/**
+ * Recover the notes sent by the account with the given outgoing view key
+ * from a list of serialized encrypted notes. Notes that can't be decrypted
+ * with the key are skipped.
+ */
+export function scanOutputsForSpender(outputs: Array<Buffer>, outgoingHexKey: string): Array<NativeSentNote>
+/**
+ * Derive the deposit tag to issue to one of an exchange's users, so that
+ * deposits to the exchange's address can be credited to them.
+ */
+export function depositTag(incomingHexKey: string, userId: bigint): Buffer
+/**
+ * Find the deposits carrying a valid deposit tag in a list of serialized
+ * encrypted notes for the exchange with the given incoming view key.
+ */
+export function extractDeposits(outputs: Array<Buffer>, incomingHexKey: string): Array<NativeDeposit>
+export interface NativeWitnessNode {
+  /** "Left" or "Right", matching Side in the TypeScript merkle tree */
+  side: string
+  hashOfSibling: Buffer
+}
+export interface NativeWitness {
+  treeSize: number
+  rootHash: Buffer
+  authPath: Array<NativeWitnessNode>
+}
+/**
+ * Calculate witnesses for the notes at the given positions from a
+ * serialized tree snapshot, hashing the shared parts of the tree once.
+ */
+export function witnessesFor(snapshot: Buffer, positions: Array<number>): Array<NativeWitness>
+/** Number of bytes in the memo of a note. Longer memos are truncated. */
+export const MEMO_SIZE: number
+/** Throw if the memo would be truncated when it's added to a note. */
+export function validateMemo(memo: string): void
+/**
+ * Finds an account's notes in batches of transactions on the libuv
+ * thread pool, so a rescan doesn't cross into Rust once per note.
+ *
+ * At most maxPendingBatches batches may be in flight at once. Further
+ * calls to scanBatch throw until one completes, so a rescan that reads
+ * blocks faster than they can be scanned has to wait rather than queue
+ * up the whole chain in memory.
+ */
+export type NativeAccountScanner = AccountScanner
+export class AccountScanner {
+  constructor(incomingHexKey: string, outgoingHexKey: string, maxPendingBatches: number)
+  /** Scan serialized transactions for notes owned by the account. */
+  scanBatch(transactions: Array<Buffer>): Promise<Array<NativeScannedNote>>
+  /** Number of batches passed to scanBatch that haven't completed yet. */
+  pendingBatches(): number
+}
 export type NativeNoteEncrypted = NoteEncrypted
 export class NoteEncrypted {
   constructor(bytes: Buffer)
@@ -42,6 +295,11 @@ export class NoteEncrypted {
 export type NativeNote = Note
 export class Note {
   constructor(owner: string, value: bigint, memo: string)
+  /**
+   * Create a deposit to an exchange, with the deposit tag the exchange
+   * issued to the depositing user as the memo.
+   */
+  static forDeposit(owner: string, value: bigint, depositTag: Buffer): NativeNote
   static deserialize(bytes: Buffer): NativeNote
   serialize(): Buffer
   /** Value this note represents. */
@@ -62,11 +320,77 @@ export class Note {
    */
   nullifier(ownerPrivateKey: string, position: bigint): Buffer
 }
+/**
+ * A nullifier that is known to be 32 bytes, so malformed nullifiers are
+ * rejected when they're created rather than deep inside verification.
+ */
+export type NativeNullifier = Nullifier
+export class Nullifier {
+  constructor(bytes: Buffer)
+  static fromHex(hex: string): NativeNullifier
+  toHex(): string
+  serialize(): Buffer
+  equals(other: Nullifier): boolean
+}
+export type NativeReceiptDescription = ReceiptDescription
+/**
+ * A new note built on its own, to be added to a Transaction later with
+ * addReceiptDescription.
+ */
+export class ReceiptDescription {
+  /**
+   * Prove the creation of the note, which the owner of
+   * outgoing_hex_key can recover, without creating a transaction.
+   */
+  static create(outgoingHexKey: string, note: Note): NativeReceiptDescription
+  /** Value of the note being created. */
+  value(): bigint
+  /**
+   * The value and randomness the note's value commitment was calculated
+   * from, so other parties can check the value without decrypting it.
+   */
+  valueCommitmentOpening(): Buffer
+}
+export type NativeSpendDescription = SpendDescription
+/**
+ * A spend built on its own, to be added to a Transaction later with
+ * addSpendDescription.
+ */
+export class SpendDescription {
+  /**
+   * Prove that spender_hex_key owns the note at the given witness
+   * location, without creating a transaction.
+   */
+  static create(spenderHexKey: string, note: Note, witness: object): NativeSpendDescription
+  /** Value of the note being spent. */
+  value(): bigint
+}
+export type NativeStore = Store
+/**
+ * A handle to a key-value store kept on the Rust side. Handles can be
+ * passed to other native functions that persist their state in it.
+ */
+export class Store {
+  /** A store that is kept in memory and lost when the handle is dropped. */
+  static memory(): NativeStore
+  /**
+   * Open or create an LMDB store in the given directory, which can grow
+   * to map_size bytes.
+   */
+  static openLmdb(path: string, mapSize: number): NativeStore
+  get(key: Buffer): Buffer | null
+  put(key: Buffer, value: Buffer): void
+  delete(key: Buffer): void
+  /** Apply all of the operations together, or none of them. */
+  write(operations: Array<NativeBatchOperation>): void
+  /** All of the entries whose keys start with the prefix, in key order. */
+  iterate(prefix: Buffer): Array<NativeStoreEntry>
+}
 export type NativeTransactionPosted = TransactionPosted
 export class TransactionPosted {
   constructor(bytes: Buffer)
   serialize(): Buffer
-  verify(): boolean
+  verify(context?: NativeVerificationContext | undefined | null): boolean
   notesLength(): number
   getNote(index: number): Buffer
   spendsLength(): number
@@ -83,6 +407,10 @@ export class Transaction {
   receive(spenderHexKey: string, note: Note): string
   /** Spend the note owned by spender_hex_key at the given witness location. */
   spend(spenderHexKey: string, note: Note, witness: object): string
+  /** Add a spend that was built separately with SpendDescription.create. */
+  addSpendDescription(description: SpendDescription): void
+  /** Add a note that was built separately with ReceiptDescription.create. */
+  addReceiptDescription(description: ReceiptDescription): void
   /**
    * Special case for posting a miners fee transaction. Miner fee transactions
    * are unique in that they generate currency. They do not have any spends
@@ -117,5 +445,7 @@ export class ThreadPoolHandler {
   stop(): void
   pause(): void
   getFoundBlock(): FoundBlockResult | undefined | null
+  keepRequestValid(miningRequestId: number): void
+  expireRequest(miningRequestId: number): void
   getHashRateSubmission(): number
 }
@@ -236,14 +236,52 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { NoteEncrypted, Note, TransactionPosted, Transaction, generateKey, generateNewPublicAddress, initializeSapling, FoundBlockResult, ThreadPoolHandler } = nativeBinding
+const { AccountScanner, NoteEncrypted, Note, Nullifier, ReceiptDescription, SpendDescription, Store, TransactionPosted, Transaction, generateKey, generateNewPublicAddress, generateKeyFromZcash, signMessage, verifyMessage, initializeSapling, paramsFingerprint, GENESIS_BLOCK_SEQUENCE, GENESIS_SUPPLY_IN_IRON, ORE_PER_IRON, ALLOWED_BLOCK_FUTURE_SECONDS, MAX_REQUESTED_BLOCKS, MAX_MESSAGE_SIZE, TARGET_BLOCK_TIME_IN_SECONDS, TARGET_BUCKET_TIME_IN_SECONDS, GRAFFITI_SIZE, IRON_FISH_YEAR_IN_BLOCKS, MAX_TRANSACTIONS_PER_BLOCK, blockReward, spendProofCacheStats, invalidateSpendProofCache, clearSpendProofCache, receiptProofCacheStats, clearReceiptProofCache, diffTransactions, extractEvents, batchVerifyTransactions, sendTransaction, estimateTransactionSize, estimateFee, scanOutputsForSpender, depositTag, extractDeposits, witnessesFor, MEMO_SIZE, validateMemo, FoundBlockResult, ThreadPoolHandler } = nativeBinding
 
+module.exports.AccountScanner = AccountScanner
 module.exports.NoteEncrypted = NoteEncrypted
 module.exports.Note = Note
+module.exports.Nullifier = Nullifier
+module.exports.ReceiptDescription = ReceiptDescription
+module.exports.SpendDescription = SpendDescription
+module.exports.Store = Store
 module.exports.TransactionPosted = TransactionPosted
 module.exports.Transaction = Transaction
 module.exports.generateKey = generateKey
 module.exports.generateNewPublicAddress = generateNewPublicAddress
+module.exports.generateKeyFromZcash = generateKeyFromZcash
+module.exports.signMessage = signMessage
+module.exports.verifyMessage = verifyMessage
 module.exports.initializeSapling = initializeSapling
+module.exports.paramsFingerprint = paramsFingerprint
+module.exports.GENESIS_BLOCK_SEQUENCE = GENESIS_BLOCK_SEQUENCE
+module.exports.GENESIS_SUPPLY_IN_IRON = GENESIS_SUPPLY_IN_IRON
+module.exports.ORE_PER_IRON = ORE_PER_IRON
+module.exports.ALLOWED_BLOCK_FUTURE_SECONDS = ALLOWED_BLOCK_FUTURE_SECONDS
+module.exports.MAX_REQUESTED_BLOCKS = MAX_REQUESTED_BLOCKS
+module.exports.MAX_MESSAGE_SIZE = MAX_MESSAGE_SIZE
+module.exports.TARGET_BLOCK_TIME_IN_SECONDS = TARGET_BLOCK_TIME_IN_SECONDS
+module.exports.TARGET_BUCKET_TIME_IN_SECONDS = TARGET_BUCKET_TIME_IN_SECONDS
+module.exports.GRAFFITI_SIZE = GRAFFITI_SIZE
+module.exports.IRON_FISH_YEAR_IN_BLOCKS = IRON_FISH_YEAR_IN_BLOCKS
+module.exports.MAX_TRANSACTIONS_PER_BLOCK = MAX_TRANSACTIONS_PER_BLOCK
+module.exports.blockReward = blockReward
+module.exports.spendProofCacheStats = spendProofCacheStats
+module.exports.invalidateSpendProofCache = invalidateSpendProofCache
+module.exports.clearSpendProofCache = clearSpendProofCache
+module.exports.receiptProofCacheStats = receiptProofCacheStats
+module.exports.clearReceiptProofCache = clearReceiptProofCache
+module.exports.diffTransactions = diffTransactions
+module.exports.extractEvents = extractEvents
+module.exports.batchVerifyTransactions = batchVerifyTransactions
+module.exports.sendTransaction = sendTransaction
+module.exports.estimateTransactionSize = estimateTransactionSize
+module.exports.estimateFee = estimateFee
+module.exports.scanOutputsForSpender = scanOutputsForSpender
+module.exports.depositTag = depositTag
+module.exports.extractDeposits = extractDeposits
+module.exports.witnessesFor = witnessesFor
+module.exports.MEMO_SIZE = MEMO_SIZE
+module.exports.validateMemo = validateMemo
 module.exports.FoundBlockResult = FoundBlockResult
 module.exports.ThreadPoolHandler = ThreadPoolHandler
@@ -0,0 +1,18 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use napi::bindgen_prelude::*;
+
+/// Convert a BigInt from JavaScript to a u64, rejecting negative values and
+/// values that don't fit.
+pub(crate) fn bigint_to_u64(value: &BigInt, name: &str) -> Result<u64> {
+    let (signed, value, lossless) = value.get_u64();
+    if signed || !lossless {
+        return Err(Error::from_reason(format!(
+            "{} must be a non-negative 64-bit integer",
+            name
+        )));
+    }
+    Ok(value)
+}
@@ -2,14 +2,20 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use ironfish_rust::SaplingKey;
+#[macro_use]
+extern crate lazy_static;
+
+use ironfish_rust::keys::AddressSignature;
+use ironfish_rust::{PublicAddress, SaplingKey};
 use napi::bindgen_prelude::*;
 use napi::Error;
 use napi_derive::napi;
 
+use ironfish_rust::consensus::constants;
 use ironfish_rust::mining;
 use ironfish_rust::sapling_bls12;
 
+mod helpers;
 pub mod structs;
 
 #[napi(object)]
@@ -49,11 +55,142 @@ pub fn generate_new_public_address(private_key: String) -> Result<Key> {
     })
 }
 
+/// Derive the Iron Fish account for a bech32 encoded Zcash Sapling extended
+/// spending key. The same Zcash key always derives the same account.
+#[napi]
+pub fn generate_key_from_zcash(zcash_key: String) -> Result<Key> {
+    let sapling_key = SaplingKey::from_zcash_encoded(&zcash_key)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    Ok(Key {
+        spending_key: sapling_key.hex_spending_key(),
+        incoming_view_key: sapling_key.incoming_view_key().hex_key(),
+        outgoing_view_key: sapling_key.outgoing_view_key().hex_key(),
+        public_address: sapling_key.generate_public_address().hex_public_address(),
+    })
+}
+
+/// Sign a message with the spending key of one of the key's addresses,
+/// without creating a transaction, to prove ownership of the address. The
+/// signature reveals the address's incoming view key.
+#[napi]
+pub fn sign_message(
+    spending_key: String,
+    public_address: String,
+    message: Buffer,
+) -> Result<Buffer> {
+    let key =
+        SaplingKey::from_hex(&spending_key).map_err(|err| Error::from_reason(err.to_string()))?;
+    let address = PublicAddress::from_hex(&public_address)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    let signature = key
+        .sign_message(&address, message.as_ref())
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+    let mut vec: Vec<u8> = vec![];
+    signature
+        .write(&mut vec)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    Ok(Buffer::from(vec))
+}
+
+/// Check a signature made with signMessage.
+#[napi]
+pub fn verify_message(public_address: String, message: Buffer, signature: Buffer) -> Result<bool> {
+    let address = PublicAddress::from_hex(&public_address)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+    let signature = AddressSignature::read(signature.as_ref())
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    Ok(address.verify_message(message.as_ref(), &signature))
+}
+
 #[napi]
 pub fn initialize_sapling() {
     let _ = sapling_bls12::SAPLING.clone();
 }
 
+#[napi(object)]
+pub struct ParamsFingerprint {
+    #[napi(js_name = "spend_params")]
+    pub spend_params: Option<Buffer>,
+    #[napi(js_name = "output_params")]
+    pub output_params: Option<Buffer>,
+    #[napi(js_name = "spend_verifying_key")]
+    pub spend_verifying_key: Buffer,
+    #[napi(js_name = "output_verifying_key")]
+    pub output_verifying_key: Buffer,
+}
+
+/// Hashes of the loaded Sapling parameters and verifying keys, to compare
+/// against the fingerprints the network expects.
+#[napi]
+pub fn params_fingerprint() -> ParamsFingerprint {
+    let fingerprint = sapling_bls12::params_fingerprint();
+
+    ParamsFingerprint {
+        spend_params: fingerprint
+            .spend_params
+            .map(|hash| Buffer::from(hash.to_vec())),
+        output_params: fingerprint
+            .receipt_params
+            .map(|hash| Buffer::from(hash.to_vec())),
+        spend_verifying_key: Buffer::from(fingerprint.spend_verifying_key.to_vec()),
+        output_verifying_key: Buffer::from(fingerprint.receipt_verifying_key.to_vec()),
+    }
+}
+
+/// The sequence of the genesis block starts at 1
+#[napi]
+pub const GENESIS_BLOCK_SEQUENCE: u32 = constants::GENESIS_BLOCK_SEQUENCE;
+
+/// The amount of coins in the genesis block
+#[napi]
+pub const GENESIS_SUPPLY_IN_IRON: u32 = constants::GENESIS_SUPPLY_IN_IRON as u32;
+
+/// The number of ore in one iron
+#[napi]
+pub const ORE_PER_IRON: u32 = constants::ORE_PER_IRON as u32;
+
+/// How many seconds into the future a block's timestamp may be
+#[napi]
+pub const ALLOWED_BLOCK_FUTURE_SECONDS: u32 = constants::ALLOWED_BLOCK_FUTURE_SECONDS;
+
+/// The maximum allowed requested blocks by the network
+#[napi]
+pub const MAX_REQUESTED_BLOCKS: u32 = constants::MAX_REQUESTED_BLOCKS;
+
+/// Max size for a message, for instance when requesting batches of blocks
+#[napi]
+pub const MAX_MESSAGE_SIZE: u32 = constants::MAX_MESSAGE_SIZE;
+
+/// The average time that all blocks should be mined
+#[napi]
+pub const TARGET_BLOCK_TIME_IN_SECONDS: u32 = constants::TARGET_BLOCK_TIME_IN_SECONDS;
+
+/// The time range when difficulty and target not change
+#[napi]
+pub const TARGET_BUCKET_TIME_IN_SECONDS: u32 = constants::TARGET_BUCKET_TIME_IN_SECONDS;
+
+/// Graffiti sizes in bytes
+#[napi]
+pub const GRAFFITI_SIZE: u32 = constants::GRAFFITI_SIZE as u32;
+
+/// An approximation of how many blocks make up a year
+#[napi]
+pub const IRON_FISH_YEAR_IN_BLOCKS: u32 = constants::IRON_FISH_YEAR_IN_BLOCKS;
+
+/// The most transactions a miner puts in a block template
+#[napi]
+pub const MAX_TRANSACTIONS_PER_BLOCK: u32 = constants::MAX_TRANSACTIONS_PER_BLOCK;
+
+/// The reward, in ore, for mining the block at the given sequence
+#[napi]
+pub fn block_reward(sequence: u32) -> u64 {
+    ironfish_rust::consensus::reward::block_reward(sequence)
+}
+
 #[napi(constructor)]
 pub struct FoundBlockResult {
     pub randomness: String,
@@ -90,13 +227,13 @@ impl ThreadPoolHandler {
 
     #[napi]
     #[allow(dead_code)]
-    pub fn pause(&self) {
+    pub fn pause(&mut self) {
         self.threadpool.pause()
     }
 
     #[napi]
     #[allow(dead_code)]
-    pub fn get_found_block(&self) -> Option<FoundBlockResult> {
+    pub fn get_found_block(&mut self) -> Option<FoundBlockResult> {
         if let Some(result) = self.threadpool.get_found_block() {
             return Some(FoundBlockResult {
                 randomness: format!("{:016x}", result.0),
@@ -106,6 +243,18 @@ impl ThreadPoolHandler {
         None
     }
 
+    #[napi]
+    #[allow(dead_code)]
+    pub fn keep_request_valid(&mut self, mining_request_id: u32) {
+        self.threadpool.keep_request_valid(mining_request_id)
+    }
+
+    #[napi]
+    #[allow(dead_code)]
+    pub fn expire_request(&mut self, mining_request_id: u32) {
+        self.threadpool.expire_request(mining_request_id)
+    }
+
     #[napi]
     #[allow(dead_code)]
     pub fn get_hash_rate_submission(&self) -> u32 {
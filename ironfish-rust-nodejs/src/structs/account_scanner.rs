@@ -0,0 +1,147 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use ironfish_rust::keys::ViewKeys;
+use ironfish_rust::sapling_bls12::SAPLING;
+use ironfish_rust::scanning::{scan_transaction_for_account, NoteOrigin};
+use ironfish_rust::{IncomingViewKey, OutgoingViewKey, Transaction};
+use napi::bindgen_prelude::*;
+use napi::{Env, Task};
+use napi_derive::napi;
+
+#[napi(object)]
+pub struct NativeScannedNote {
+    /// Index of the transaction in the batch
+    pub transaction_index: u32,
+    /// Index of the note among the transaction's notes
+    pub note_index: u32,
+    /// The serialized note
+    pub note: Buffer,
+    /// "received", "change" or "selfSend"
+    pub origin: String,
+}
+
+pub struct ScanBatchTask {
+    view_keys: ViewKeys,
+    transactions: Vec<Buffer>,
+    pending_batches: Arc<AtomicU32>,
+}
+
+impl Task for ScanBatchTask {
+    type Output = Vec<(u32, u32, Vec<u8>, NoteOrigin)>;
+    type JsValue = Vec<NativeScannedNote>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let mut found = vec![];
+        for (transaction_index, bytes) in self.transactions.iter().enumerate() {
+            let transaction = Transaction::read(SAPLING.clone(), bytes.as_ref())
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+
+            for owned in scan_transaction_for_account(&transaction, &self.view_keys) {
+                let mut note = vec![];
+                owned
+                    .note
+                    .write(&mut note)
+                    .map_err(|err| Error::from_reason(err.to_string()))?;
+                found.push((
+                    transaction_index as u32,
+                    owned.index as u32,
+                    note,
+                    owned.origin,
+                ));
+            }
+        }
+
+        Ok(found)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output
+            .into_iter()
+            .map(
+                |(transaction_index, note_index, note, origin)| NativeScannedNote {
+                    transaction_index,
+                    note_index,
+                    note: Buffer::from(note),
+                    origin: match origin {
+                        NoteOrigin::Received => "received",
+                        NoteOrigin::Change => "change",
+                        NoteOrigin::SelfSend => "selfSend",
+                    }
+                    .to_string(),
+                },
+            )
+            .collect())
+    }
+
+    fn finally(&mut self, _env: Env) -> Result<()> {
+        self.pending_batches.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Finds an account's notes in batches of transactions on the libuv
+/// thread pool, so a rescan doesn't cross into Rust once per note.
+///
+/// At most maxPendingBatches batches may be in flight at once. Further
+/// calls to scanBatch throw until one completes, so a rescan that reads
+/// blocks faster than they can be scanned has to wait rather than queue
+/// up the whole chain in memory.
+#[napi(js_name = "AccountScanner")]
+pub struct NativeAccountScanner {
+    view_keys: ViewKeys,
+    max_pending_batches: u32,
+    pending_batches: Arc<AtomicU32>,
+}
+
+#[napi]
+impl NativeAccountScanner {
+    #[napi(constructor)]
+    pub fn new(
+        incoming_hex_key: String,
+        outgoing_hex_key: String,
+        max_pending_batches: u32,
+    ) -> Result<Self> {
+        let incoming = IncomingViewKey::from_hex(&incoming_hex_key)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+        let outgoing = OutgoingViewKey::from_hex(&outgoing_hex_key)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        Ok(NativeAccountScanner {
+            view_keys: ViewKeys { incoming, outgoing },
+            max_pending_batches,
+            pending_batches: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    /// Scan serialized transactions for notes owned by the account.
+    #[napi]
+    pub fn scan_batch(&self, transactions: Vec<Buffer>) -> Result<AsyncTask<ScanBatchTask>> {
+        let max_pending_batches = self.max_pending_batches;
+        self.pending_batches
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                if pending < max_pending_batches {
+                    Some(pending + 1)
+                } else {
+                    None
+                }
+            })
+            .map_err(|_| Error::from_reason("Too many pending batches".to_string()))?;
+
+        Ok(AsyncTask::new(ScanBatchTask {
+            view_keys: self.view_keys.clone(),
+            transactions,
+            pending_batches: self.pending_batches.clone(),
+        }))
+    }
+
+    /// Number of batches passed to scanBatch that haven't completed yet.
+    #[napi]
+    pub fn pending_batches(&self) -> u32 {
+        self.pending_batches.load(Ordering::SeqCst)
+    }
+}
@@ -2,15 +2,30 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+mod account_scanner;
+pub use account_scanner::*;
+
 mod note_encrypted;
 pub use note_encrypted::*;
 
 mod note;
 pub use note::*;
 
+mod nullifier;
+pub use nullifier::*;
+
+mod receipt_description;
+pub use receipt_description::*;
+
+mod spend_description;
+pub use spend_description::*;
+
 mod spend_proof;
 pub use spend_proof::*;
 
+mod store;
+pub use store::*;
+
 mod transaction;
 pub use transaction::*;
 
@@ -5,8 +5,22 @@
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+use crate::helpers::bigint_to_u64;
+
+use ironfish_rust::keys::{DepositTag, DEPOSIT_TAG_SIZE};
 use ironfish_rust::{note::Memo, Note, SaplingKey};
 
+/// Number of bytes in the memo of a note. Longer memos are truncated.
+#[napi]
+pub const MEMO_SIZE: u32 = ironfish_rust::note::MEMO_SIZE as u32;
+
+/// Throw if the memo would be truncated when it's added to a note.
+#[napi]
+pub fn validate_memo(memo: String) -> Result<()> {
+    ironfish_rust::note::validate_memo_bytes(memo.as_bytes())
+        .map_err(|err| Error::from_reason(err.to_string()))
+}
+
 #[napi(js_name = "Note")]
 pub struct NativeNote {
     pub(crate) note: Note,
@@ -16,7 +30,7 @@ pub struct NativeNote {
 impl NativeNote {
     #[napi(constructor)]
     pub fn new(owner: String, value: BigInt, memo: String) -> Result<Self> {
-        let value_u64 = value.get_u64().1;
+        let value_u64 = bigint_to_u64(&value, "value")?;
 
         let owner_address = ironfish_rust::PublicAddress::from_hex(&owner)
             .map_err(|err| Error::from_reason(err.to_string()))?;
@@ -25,6 +39,25 @@ impl NativeNote {
         })
     }
 
+    /// Create a deposit to an exchange, with the deposit tag the exchange
+    /// issued to the depositing user as the memo.
+    #[napi(factory)]
+    pub fn for_deposit(owner: String, value: BigInt, deposit_tag: Buffer) -> Result<Self> {
+        let value_u64 = bigint_to_u64(&value, "value")?;
+
+        let owner_address = ironfish_rust::PublicAddress::from_hex(&owner)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+        let tag_bytes: &[u8; DEPOSIT_TAG_SIZE] = deposit_tag
+            .as_ref()
+            .try_into()
+            .map_err(|_| Error::from_reason("Invalid deposit tag".to_string()))?;
+        let tag = DepositTag::from_bytes(tag_bytes);
+
+        Ok(NativeNote {
+            note: Note::new(owner_address, value_u64, tag.memo()),
+        })
+    }
+
     #[napi(factory)]
     pub fn deserialize(bytes: Buffer) -> Result<Self> {
         let note = Note::read(bytes.as_ref()).map_err(|err| Error::from_reason(err.to_string()))?;
@@ -64,7 +97,7 @@ impl NativeNote {
     /// 'nullifier set', preventing double-spend.
     #[napi]
     pub fn nullifier(&self, owner_private_key: String, position: BigInt) -> Result<Buffer> {
-        let position_u64 = position.get_u64().1;
+        let position_u64 = bigint_to_u64(&position, "position")?;
 
         let private_key = SaplingKey::from_hex(&owner_private_key)
             .map_err(|err| Error::from_reason(err.to_string()))?;
@@ -8,8 +8,12 @@ use ironfish_rust::OutgoingViewKey;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
+use ironfish_rust::scanning::extract_deposits as extract;
+use ironfish_rust::scanning::scan_outputs_for_spender as scan_outputs;
 use ironfish_rust::MerkleNote;
 
+use crate::helpers::bigint_to_u64;
+
 #[napi(js_name = "NoteEncrypted")]
 pub struct NativeNoteEncrypted {
     pub(crate) note: MerkleNote,
@@ -113,3 +117,99 @@ impl NativeNoteEncrypted {
         )
     }
 }
+
+#[napi(object)]
+pub struct NativeSentNote {
+    /// Index of the output the note was recovered from
+    pub index: u32,
+    /// The serialized note
+    pub note: Buffer,
+}
+
+/// Recover the notes sent by the account with the given outgoing view key
+/// from a list of serialized encrypted notes. Notes that can't be decrypted
+/// with the key are skipped.
+#[napi]
+pub fn scan_outputs_for_spender(
+    outputs: Vec<Buffer>,
+    outgoing_hex_key: String,
+) -> Result<Vec<NativeSentNote>> {
+    let outgoing_view_key = OutgoingViewKey::from_hex(&outgoing_hex_key)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    let notes = outputs
+        .iter()
+        .map(|bytes| MerkleNote::read(bytes.as_ref()))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    scan_outputs(&notes, &outgoing_view_key)
+        .into_iter()
+        .map(|sent| {
+            let mut vec = vec![];
+            sent.note
+                .write(&mut vec)
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+
+            Ok(NativeSentNote {
+                index: sent.index as u32,
+                note: Buffer::from(vec),
+            })
+        })
+        .collect()
+}
+
+#[napi(object)]
+pub struct NativeDeposit {
+    /// Index of the output the deposit was found in
+    pub index: u32,
+    /// The user the deposit tag was issued to
+    pub user_id: BigInt,
+    /// The serialized note
+    pub note: Buffer,
+}
+
+/// Derive the deposit tag to issue to one of an exchange's users, so that
+/// deposits to the exchange's address can be credited to them.
+#[napi]
+pub fn deposit_tag(incoming_hex_key: String, user_id: BigInt) -> Result<Buffer> {
+    let incoming_view_key = IncomingViewKey::from_hex(&incoming_hex_key)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    let tag = incoming_view_key.deposit_tag(bigint_to_u64(&user_id, "userId")?);
+    Ok(Buffer::from(tag.to_bytes().to_vec()))
+}
+
+/// Find the deposits carrying a valid deposit tag in a list of serialized
+/// encrypted notes for the exchange with the given incoming view key.
+#[napi]
+pub fn extract_deposits(
+    outputs: Vec<Buffer>,
+    incoming_hex_key: String,
+) -> Result<Vec<NativeDeposit>> {
+    let incoming_view_key = IncomingViewKey::from_hex(&incoming_hex_key)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    let notes = outputs
+        .iter()
+        .map(|bytes| MerkleNote::read(bytes.as_ref()))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    extract(&notes, &incoming_view_key)
+        .into_iter()
+        .map(|deposit| {
+            let mut vec = vec![];
+            deposit
+                .note
+                .write(&mut vec)
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+
+            Ok(NativeDeposit {
+                index: deposit.index as u32,
+                user_id: BigInt::from(deposit.user_id),
+                note: Buffer::from(vec),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,64 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::convert::TryInto;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+/// A nullifier that is known to be 32 bytes, so malformed nullifiers are
+/// rejected when they're created rather than deep inside verification.
+#[napi(js_name = "Nullifier")]
+pub struct NativeNullifier {
+    pub(crate) nullifier: [u8; 32],
+}
+
+#[napi]
+impl NativeNullifier {
+    #[napi(constructor)]
+    pub fn new(bytes: Buffer) -> Result<Self> {
+        let nullifier = bytes
+            .as_ref()
+            .try_into()
+            .map_err(|_| Error::from_reason("Nullifier must be 32 bytes".to_string()))?;
+
+        Ok(NativeNullifier { nullifier })
+    }
+
+    #[napi(factory)]
+    pub fn from_hex(hex: String) -> Result<Self> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(Error::from_reason(
+                "Nullifier must be 64 hexadecimal characters".to_string(),
+            ));
+        }
+
+        let mut nullifier = [0; 32];
+        for (byte, pair) in nullifier.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).expect("hex is ascii");
+            *byte = u8::from_str_radix(pair, 16)
+                .map_err(|_| Error::from_reason("Invalid hexadecimal character".to_string()))?;
+        }
+
+        Ok(NativeNullifier { nullifier })
+    }
+
+    #[napi]
+    pub fn to_hex(&self) -> String {
+        self.nullifier
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    #[napi]
+    pub fn serialize(&self) -> Buffer {
+        Buffer::from(&self.nullifier[..])
+    }
+
+    #[napi]
+    pub fn equals(&self, other: &NativeNullifier) -> bool {
+        self.nullifier == other.nullifier
+    }
+}
@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use ironfish_rust::{OutgoingViewKey, ReceiptParams};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use ironfish_rust::sapling_bls12::SAPLING;
+
+use super::note::NativeNote;
+
+/// A new note built on its own, to be added to a Transaction later with
+/// addReceiptDescription.
+#[napi(js_name = "ReceiptDescription")]
+pub struct NativeReceiptDescription {
+    receipt: Option<ReceiptParams>,
+    value: u64,
+    value_commitment_opening: Vec<u8>,
+}
+
+#[napi]
+impl NativeReceiptDescription {
+    /// Prove the creation of the note, which the owner of
+    /// outgoing_hex_key can recover, without creating a transaction.
+    #[napi(factory)]
+    pub fn create(outgoing_hex_key: String, note: &NativeNote) -> Result<Self> {
+        let outgoing_view_key = OutgoingViewKey::from_hex(&outgoing_hex_key)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+        let receipt =
+            ReceiptParams::for_outgoing_view_key(SAPLING.clone(), &outgoing_view_key, &note.note)
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        let mut value_commitment_opening = vec![];
+        receipt
+            .value_commitment_opening()
+            .write(&mut value_commitment_opening)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        Ok(NativeReceiptDescription {
+            receipt: Some(receipt),
+            value: note.note.value(),
+            value_commitment_opening,
+        })
+    }
+
+    /// Value of the note being created.
+    #[napi]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The value and randomness the note's value commitment was calculated
+    /// from, so other parties can check the value without decrypting it.
+    #[napi]
+    pub fn value_commitment_opening(&self) -> Buffer {
+        Buffer::from(self.value_commitment_opening.clone())
+    }
+}
+
+impl NativeReceiptDescription {
+    /// Take the receipt out of the description so it can be added to a
+    /// transaction. A description can only be added once.
+    pub(crate) fn take(&mut self) -> Result<ReceiptParams> {
+        self.receipt
+            .take()
+            .ok_or_else(|| Error::from_reason("Receipt description was already used".to_string()))
+    }
+}
@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::cell::RefCell;
+
+use ironfish_rust::{SaplingKey, SpendParams};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use ironfish_rust::sapling_bls12::SAPLING;
+
+use super::note::NativeNote;
+use super::witness::JsWitness;
+
+/// A spend built on its own, to be added to a Transaction later with
+/// addSpendDescription.
+#[napi(js_name = "SpendDescription")]
+pub struct NativeSpendDescription {
+    spend: Option<SpendParams>,
+    value: u64,
+}
+
+#[napi]
+impl NativeSpendDescription {
+    /// Prove that spender_hex_key owns the note at the given witness
+    /// location, without creating a transaction.
+    #[napi(factory)]
+    pub fn create(
+        env: Env,
+        spender_hex_key: String,
+        note: &NativeNote,
+        witness: Object,
+    ) -> Result<Self> {
+        let w = JsWitness {
+            cx: RefCell::new(env),
+            obj: witness,
+        };
+
+        let spender_key = SaplingKey::from_hex(&spender_hex_key)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+        let spend = SpendParams::new(SAPLING.clone(), spender_key, &note.note, &w)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        Ok(NativeSpendDescription {
+            spend: Some(spend),
+            value: note.note.value(),
+        })
+    }
+
+    /// Value of the note being spent.
+    #[napi]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+impl NativeSpendDescription {
+    /// Take the spend out of the description so it can be added to a
+    /// transaction. A description can only be added once.
+    pub(crate) fn take(&mut self) -> Result<SpendParams> {
+        self.spend
+            .take()
+            .ok_or_else(|| Error::from_reason("Spend description was already used".to_string()))
+    }
+}
@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::convert::TryInto;
+use std::path::Path;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use ironfish_rust::storage::{LmdbStore, MemoryStore, Store, WriteBatch};
+
+#[napi(object)]
+pub struct NativeStoreEntry {
+    pub key: Buffer,
+    pub value: Buffer,
+}
+
+/// A change to make in Store.write. Omitting the value deletes the key.
+#[napi(object)]
+pub struct NativeBatchOperation {
+    pub key: Buffer,
+    pub value: Option<Buffer>,
+}
+
+/// A handle to a key-value store kept on the Rust side. Handles can be
+/// passed to other native functions that persist their state in it.
+#[napi(js_name = "Store")]
+pub struct NativeStore {
+    pub(crate) store: Arc<dyn Store>,
+}
+
+#[napi]
+impl NativeStore {
+    /// A store that is kept in memory and lost when the handle is dropped.
+    #[napi(factory)]
+    pub fn memory() -> Self {
+        NativeStore {
+            store: Arc::new(MemoryStore::new()),
+        }
+    }
+
+    /// Open or create an LMDB store in the given directory, which can grow
+    /// to map_size bytes.
+    #[napi(factory)]
+    pub fn open_lmdb(path: String, map_size: i64) -> Result<Self> {
+        let map_size: usize = map_size
+            .try_into()
+            .map_err(|_| Error::from_reason("Invalid map size".to_string()))?;
+        let store = LmdbStore::open(Path::new(&path), map_size)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        Ok(NativeStore {
+            store: Arc::new(store),
+        })
+    }
+
+    #[napi]
+    pub fn get(&self, key: Buffer) -> Result<Option<Buffer>> {
+        let value = self
+            .store
+            .get(key.as_ref())
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        Ok(value.map(Buffer::from))
+    }
+
+    #[napi]
+    pub fn put(&self, key: Buffer, value: Buffer) -> Result<()> {
+        self.store
+            .put(key.as_ref(), value.as_ref())
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    #[napi]
+    pub fn delete(&self, key: Buffer) -> Result<()> {
+        self.store
+            .delete(key.as_ref())
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    /// Apply all of the operations together, or none of them.
+    #[napi]
+    pub fn write(&self, operations: Vec<NativeBatchOperation>) -> Result<()> {
+        let mut batch = WriteBatch::new();
+        for operation in operations.iter() {
+            match &operation.value {
+                Some(value) => batch.put(operation.key.as_ref(), value.as_ref()),
+                None => batch.delete(operation.key.as_ref()),
+            };
+        }
+
+        self.store
+            .write(batch)
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    /// All of the entries whose keys start with the prefix, in key order.
+    #[napi]
+    pub fn iterate(&self, prefix: Buffer) -> Result<Vec<NativeStoreEntry>> {
+        let entries = self
+            .store
+            .iterate(prefix.as_ref())
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(key, value)| NativeStoreEntry {
+                key: Buffer::from(key),
+                value: Buffer::from(value),
+            })
+            .collect())
+    }
+}
@@ -4,16 +4,482 @@
 
 use std::cell::RefCell;
 use std::convert::TryInto;
-
-use ironfish_rust::{MerkleNoteHash, ProposedTransaction, PublicAddress, SaplingKey, Transaction};
+use std::sync::Arc;
+
+use ironfish_rust::note::Memo;
+use ironfish_rust::transaction::{
+    diff, estimate_fee as estimate_fee_for, estimate_transaction_size as estimate_size,
+    extract_events as extract, ChainEvent, ReceiptProofCache, SpendProofCache, VerificationContext,
+};
+use ironfish_rust::wallet::{send, Recipient};
+use ironfish_rust::witness::{Witness, WitnessTrait};
+use ironfish_rust::{
+    MerkleNoteHash, Note, ProposedTransaction, PublicAddress, SaplingKey, Transaction,
+};
 use napi::bindgen_prelude::*;
+use napi::{Env, Task};
 use napi_derive::napi;
 
 use ironfish_rust::sapling_bls12::SAPLING;
 
 use super::note::NativeNote;
+use super::receipt_description::NativeReceiptDescription;
+use super::spend_description::NativeSpendDescription;
 use super::spend_proof::NativeSpendProof;
 use super::witness::JsWitness;
+use crate::helpers::bigint_to_u64;
+
+/// Number of verified spend proofs to remember across calls to verify
+const SPEND_PROOF_CACHE_CAPACITY: usize = 50_000;
+
+/// Number of verified receipt proofs to remember across calls to verify
+const RECEIPT_PROOF_CACHE_CAPACITY: usize = 50_000;
+
+lazy_static! {
+    static ref SPEND_PROOF_CACHE: Arc<SpendProofCache> =
+        Arc::new(SpendProofCache::new(SPEND_PROOF_CACHE_CAPACITY));
+    static ref RECEIPT_PROOF_CACHE: Arc<ReceiptProofCache> =
+        Arc::new(ReceiptProofCache::new(RECEIPT_PROOF_CACHE_CAPACITY));
+}
+
+/// Consensus rules to enforce when verifying a transaction. Omitted fields
+/// apply no restriction.
+#[napi(object)]
+pub struct NativeVerificationContext {
+    pub sequence: Option<u32>,
+    pub max_spends: Option<u32>,
+    pub max_receipts: Option<u32>,
+    pub allow_negative_fee: Option<bool>,
+    pub max_miners_fee: Option<i64>,
+    /// Skip spend proofs that were already verified by this process, and
+    /// remember the ones verified now. Useful when reconnecting blocks
+    /// during a reorg.
+    pub use_spend_proof_cache: Option<bool>,
+    /// Likewise for receipt proofs, so transactions verified in the mempool
+    /// aren't proven again when they arrive in a block.
+    pub use_receipt_proof_cache: Option<bool>,
+}
+
+impl TryFrom<NativeVerificationContext> for VerificationContext {
+    type Error = Error;
+
+    fn try_from(context: NativeVerificationContext) -> Result<Self> {
+        let default = VerificationContext::default();
+
+        let max_miners_fee = match context.max_miners_fee {
+            Some(max) => Some(max.try_into().map_err(|_| {
+                Error::from_reason("maxMinersFee must not be negative".to_string())
+            })?),
+            None => None,
+        };
+
+        Ok(VerificationContext {
+            sequence: context.sequence,
+            max_spends: context.max_spends.map(|max| max as usize),
+            max_receipts: context.max_receipts.map(|max| max as usize),
+            allow_negative_fee: context
+                .allow_negative_fee
+                .unwrap_or(default.allow_negative_fee),
+            max_miners_fee,
+            spend_proof_cache: match context.use_spend_proof_cache {
+                Some(true) => Some(SPEND_PROOF_CACHE.clone()),
+                _ => default.spend_proof_cache,
+            },
+            receipt_proof_cache: match context.use_receipt_proof_cache {
+                Some(true) => Some(RECEIPT_PROOF_CACHE.clone()),
+                _ => default.receipt_proof_cache,
+            },
+        })
+    }
+}
+
+#[napi(object)]
+pub struct NativeProofCacheStats {
+    pub hits: i64,
+    pub misses: i64,
+    pub evictions: i64,
+    pub entries: u32,
+}
+
+#[napi]
+pub fn spend_proof_cache_stats() -> NativeProofCacheStats {
+    let stats = SPEND_PROOF_CACHE.stats();
+
+    NativeProofCacheStats {
+        hits: stats.hits as i64,
+        misses: stats.misses as i64,
+        evictions: stats.evictions as i64,
+        entries: stats.entries as u32,
+    }
+}
+
+/// Forget the cached spend proofs for a nullifier, such as when the block
+/// containing the spend is disconnected for good.
+#[napi]
+pub fn invalidate_spend_proof_cache(nullifier: Buffer) -> Result<()> {
+    let nullifier: [u8; 32] = nullifier
+        .as_ref()
+        .try_into()
+        .map_err(|_| Error::from_reason("Nullifier must be 32 bytes".to_string()))?;
+    SPEND_PROOF_CACHE.invalidate(&nullifier);
+    Ok(())
+}
+
+#[napi]
+pub fn clear_spend_proof_cache() {
+    SPEND_PROOF_CACHE.clear();
+}
+
+#[napi]
+pub fn receipt_proof_cache_stats() -> NativeProofCacheStats {
+    let stats = RECEIPT_PROOF_CACHE.stats();
+
+    NativeProofCacheStats {
+        hits: stats.hits as i64,
+        misses: stats.misses as i64,
+        evictions: stats.evictions as i64,
+        entries: stats.entries as u32,
+    }
+}
+
+#[napi]
+pub fn clear_receipt_proof_cache() {
+    RECEIPT_PROOF_CACHE.clear();
+}
+
+#[napi(object)]
+pub struct NativeMovedNoteCommitment {
+    pub note_commitment: Buffer,
+    pub old_position: u32,
+    pub new_position: u32,
+}
+
+#[napi(object)]
+pub struct NativeTransactionDiff {
+    pub added_nullifiers: Vec<Buffer>,
+    pub removed_nullifiers: Vec<Buffer>,
+    pub added_note_commitments: Vec<Buffer>,
+    pub removed_note_commitments: Vec<Buffer>,
+    /// Notes in both blocks whose position in the note tree changed, so
+    /// their nullifiers must be recomputed
+    pub moved_note_commitments: Vec<NativeMovedNoteCommitment>,
+}
+
+/// Compare the serialized transactions of a block being disconnected with
+/// those of the block replacing it, returning the nullifiers and note
+/// commitments that were added or removed, and the notes that moved. The
+/// tree sizes are the size of the note tree before each block.
+#[napi]
+pub fn diff_transactions(
+    old_transactions: Vec<Buffer>,
+    old_tree_size: u32,
+    new_transactions: Vec<Buffer>,
+    new_tree_size: u32,
+) -> Result<NativeTransactionDiff> {
+    let read_transactions = |buffers: Vec<Buffer>| -> Result<Vec<Transaction>> {
+        buffers
+            .iter()
+            .map(|bytes| {
+                Transaction::read(SAPLING.clone(), bytes.as_ref())
+                    .map_err(|err| Error::from_reason(err.to_string()))
+            })
+            .collect()
+    };
+    let to_buffers = |items: Vec<[u8; 32]>| -> Vec<Buffer> {
+        items.iter().map(|item| Buffer::from(&item[..])).collect()
+    };
+
+    let transaction_diff = diff(
+        &read_transactions(old_transactions)?,
+        u64::from(old_tree_size),
+        &read_transactions(new_transactions)?,
+        u64::from(new_tree_size),
+    );
+
+    Ok(NativeTransactionDiff {
+        added_nullifiers: to_buffers(transaction_diff.added_nullifiers),
+        removed_nullifiers: to_buffers(transaction_diff.removed_nullifiers),
+        added_note_commitments: to_buffers(transaction_diff.added_note_commitments),
+        removed_note_commitments: to_buffers(transaction_diff.removed_note_commitments),
+        moved_note_commitments: transaction_diff
+            .moved_note_commitments
+            .iter()
+            .map(|moved| NativeMovedNoteCommitment {
+                note_commitment: Buffer::from(&moved.note_commitment[..]),
+                old_position: moved.old_position as u32,
+                new_position: moved.new_position as u32,
+            })
+            .collect(),
+    })
+}
+
+#[napi(object)]
+pub struct NativeChainEvent {
+    /// "noteCommitmentAdded" or "nullifierSpent"
+    pub kind: String,
+    /// Index of the transaction in the list the events were extracted from
+    pub transaction_index: u32,
+    pub transaction_hash: Buffer,
+    /// Index of the receipt or spend in the transaction
+    pub index: u32,
+    /// Position of an added note among the notes added by the transactions
+    pub position: Option<u32>,
+    /// The note commitment or nullifier
+    pub value: Buffer,
+}
+
+/// List the note commitments added and nullifiers spent by serialized
+/// transactions, such as those of a verified block, in the order they are
+/// applied.
+#[napi]
+pub fn extract_events(transactions: Vec<Buffer>) -> Result<Vec<NativeChainEvent>> {
+    let transactions = transactions
+        .iter()
+        .map(|bytes| Transaction::read(SAPLING.clone(), bytes.as_ref()))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    Ok(extract(&transactions)
+        .into_iter()
+        .map(|event| match event {
+            ChainEvent::NoteCommitmentAdded {
+                transaction_index,
+                transaction_hash,
+                receipt_index,
+                position,
+                note_commitment,
+            } => NativeChainEvent {
+                kind: "noteCommitmentAdded".to_string(),
+                transaction_index: transaction_index as u32,
+                transaction_hash: Buffer::from(&transaction_hash[..]),
+                index: receipt_index as u32,
+                position: Some(position as u32),
+                value: Buffer::from(&note_commitment[..]),
+            },
+            ChainEvent::NullifierSpent {
+                transaction_index,
+                transaction_hash,
+                spend_index,
+                nullifier,
+            } => NativeChainEvent {
+                kind: "nullifierSpent".to_string(),
+                transaction_index: transaction_index as u32,
+                transaction_hash: Buffer::from(&transaction_hash[..]),
+                index: spend_index as u32,
+                position: None,
+                value: Buffer::from(&nullifier[..]),
+            },
+        })
+        .collect())
+}
+
+#[napi(object)]
+pub struct NativeTransactionVerification {
+    pub valid: bool,
+    /// Why the transaction was rejected, if it was
+    pub error: Option<String>,
+}
+
+pub struct BatchVerifyTask {
+    transactions: Vec<Buffer>,
+    context: VerificationContext,
+}
+
+impl Task for BatchVerifyTask {
+    type Output = Vec<Option<String>>;
+    type JsValue = Vec<NativeTransactionVerification>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let mut errors = vec![None; self.transactions.len()];
+        let mut transactions = Vec::with_capacity(self.transactions.len());
+        for (index, bytes) in self.transactions.iter().enumerate() {
+            match Transaction::read_with_context(SAPLING.clone(), bytes.as_ref(), &self.context) {
+                Ok(transaction) => transactions.push((index, transaction)),
+                Err(err) => errors[index] = Some(err.to_string()),
+            }
+        }
+
+        let batch = transactions.iter().map(|(_, transaction)| transaction);
+        if ironfish_rust::transaction::batch_verify_transactions(batch, &self.context).is_err() {
+            // The batch doesn't say which transaction was invalid, so find
+            // out by checking them one at a time
+            for (index, transaction) in transactions.iter() {
+                if let Err(err) = transaction.verify_with_context(&self.context) {
+                    errors[*index] = Some(err.to_string());
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output
+            .into_iter()
+            .map(|error| NativeTransactionVerification {
+                valid: error.is_none(),
+                error,
+            })
+            .collect())
+    }
+}
+
+/// Verify serialized transactions together on a worker thread, returning
+/// whether each one is valid.
+///
+/// The buffers are read in place rather than copied, and the counts of
+/// descriptions are checked against the context before they are read.
+#[napi]
+pub fn batch_verify_transactions(
+    transactions: Vec<Buffer>,
+    context: Option<NativeVerificationContext>,
+) -> Result<AsyncTask<BatchVerifyTask>> {
+    let context = match context {
+        Some(context) => context.try_into()?,
+        None => VerificationContext::default(),
+    };
+
+    Ok(AsyncTask::new(BatchVerifyTask {
+        transactions,
+        context,
+    }))
+}
+
+#[napi(object)]
+pub struct NativeRecipient {
+    pub public_address: String,
+    pub value: BigInt,
+    pub memo: String,
+}
+
+pub struct SendTask {
+    spender_key: SaplingKey,
+    notes: Vec<(Note, Witness)>,
+    recipients: Vec<Recipient>,
+    transaction_fee: u64,
+    expiration_sequence: u32,
+}
+
+impl Task for SendTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let transaction = send(
+            SAPLING.clone(),
+            &self.spender_key,
+            &self.notes,
+            &self.recipients,
+            self.transaction_fee,
+            self.expiration_sequence,
+        )
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        let mut vec: Vec<u8> = vec![];
+        transaction
+            .write(&mut vec)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+        Ok(vec)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(Buffer::from(output))
+    }
+}
+
+/// Build, prove and sign a transaction paying the recipients from the given
+/// serialized notes on a worker thread, returning the serialized posted
+/// transaction.
+///
+/// Notes are selected in the order given until they cover the payments and
+/// the fee, and change is returned to the spender. Each note needs the
+/// witness at the same index.
+///
+/// Throws if a recipient's memo is longer than MEMO_SIZE bytes, rather than
+/// truncating it.
+#[napi]
+pub fn send_transaction(
+    env: Env,
+    spender_hex_key: String,
+    notes: Vec<Buffer>,
+    witnesses: Vec<Object>,
+    recipients: Vec<NativeRecipient>,
+    transaction_fee: BigInt,
+    expiration_sequence: u32,
+) -> Result<AsyncTask<SendTask>> {
+    if notes.len() != witnesses.len() {
+        return Err(Error::from_reason(
+            "Each note must have a witness".to_string(),
+        ));
+    }
+
+    let spender_key = SaplingKey::from_hex(&spender_hex_key)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    // The witnesses call back into JavaScript, so they're read here rather
+    // than on the worker thread
+    let notes = notes
+        .iter()
+        .zip(witnesses.into_iter())
+        .map(|(bytes, witness)| {
+            let note =
+                Note::read(bytes.as_ref()).map_err(|err| Error::from_reason(err.to_string()))?;
+            let witness = JsWitness {
+                cx: RefCell::new(env),
+                obj: witness,
+            };
+            let witness = Witness {
+                tree_size: witness.tree_size() as usize,
+                root_hash: witness.root_hash(),
+                auth_path: witness.get_auth_path(),
+            };
+            Ok((note, witness))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let recipients = recipients
+        .into_iter()
+        .map(|recipient| {
+            let address = PublicAddress::from_hex(&recipient.public_address)
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+            Ok(Recipient {
+                address,
+                value: bigint_to_u64(&recipient.value, "value")?,
+                memo: Memo::from_bytes(recipient.memo.as_bytes())
+                    .map_err(|err| Error::from_reason(err.to_string()))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let transaction_fee = bigint_to_u64(&transaction_fee, "transactionFee")?;
+
+    Ok(AsyncTask::new(SendTask {
+        spender_key,
+        notes,
+        recipients,
+        transaction_fee,
+        expiration_sequence,
+    }))
+}
+
+/// The serialized size of a transaction with the given number of spends and
+/// outputs, including any change output, without building it.
+#[napi]
+pub fn estimate_transaction_size(spend_count: u32, output_count: u32) -> Result<u32> {
+    estimate_size(spend_count as usize, output_count as usize)
+        .try_into()
+        .map_err(|_| Error::from_reason("Transaction size overflows a u32".to_string()))
+}
+
+/// The fee for a transaction with the given number of spends and outputs,
+/// at a fee rate in ore per serialized byte.
+#[napi]
+pub fn estimate_fee(spend_count: u32, output_count: u32, fee_rate: BigInt) -> Result<BigInt> {
+    Ok(BigInt::from(estimate_fee_for(
+        spend_count as usize,
+        output_count as usize,
+        bigint_to_u64(&fee_rate, "feeRate")?,
+    )))
+}
 
 #[napi(js_name = "TransactionPosted")]
 pub struct NativeTransactionPosted {
@@ -43,10 +509,15 @@ impl NativeTransactionPosted {
     }
 
     #[napi]
-    pub fn verify(&self) -> bool {
-        match self.transaction.verify() {
-            Ok(_) => true,
-            Err(_e) => false,
+    pub fn verify(&self, context: Option<NativeVerificationContext>) -> Result<bool> {
+        let context = match context {
+            Some(context) => context.try_into()?,
+            None => VerificationContext::default(),
+        };
+
+        match self.transaction.verify_with_context(&context) {
+            Ok(_) => Ok(true),
+            Err(_e) => Ok(false),
         }
     }
 
@@ -197,6 +668,32 @@ impl NativeTransaction {
         Ok("".to_string())
     }
 
+    /// Add a spend that was built separately with SpendDescription.create.
+    #[napi]
+    pub fn add_spend_description(
+        &mut self,
+        description: &mut NativeSpendDescription,
+    ) -> Result<()> {
+        let value = description.value;
+        let spend = description.take()?;
+        self.transaction.add_spend_proof(spend, value);
+
+        Ok(())
+    }
+
+    /// Add a note that was built separately with ReceiptDescription.create.
+    #[napi]
+    pub fn add_receipt_description(
+        &mut self,
+        description: &mut NativeReceiptDescription,
+    ) -> Result<()> {
+        let value = description.value;
+        let receipt = description.take()?;
+        self.transaction.add_receipt_proof(receipt, value);
+
+        Ok(())
+    }
+
     /// Special case for posting a miners fee transaction. Miner fee transactions
     /// are unique in that they generate currency. They do not have any spends
     /// or change and therefore have a negative transaction fee. In normal use,
@@ -233,7 +730,8 @@ impl NativeTransaction {
         change_goes_to: Option<String>,
         intended_transaction_fee: BigInt,
     ) -> Result<Buffer> {
-        let intended_transaction_fee_u64 = intended_transaction_fee.get_u64().1;
+        let intended_transaction_fee_u64 =
+            bigint_to_u64(&intended_transaction_fee, "intendedTransactionFee")?;
 
         let spender_key = SaplingKey::from_hex(&spender_hex_key)
             .map_err(|err| Error::from_reason(err.to_string()))?;
@@ -10,7 +10,9 @@ use ironfish_rust::MerkleNoteHash;
 use napi::bindgen_prelude::*;
 use napi::Env;
 use napi::JsObject;
+use napi_derive::napi;
 
+use ironfish_rust::tree_snapshot::TreeSnapshot;
 use ironfish_rust::witness::{WitnessNode, WitnessTrait};
 
 pub struct JsWitness {
@@ -116,3 +118,66 @@ impl WitnessTrait for JsWitness {
             .unwrap()
     }
 }
+
+#[napi(object)]
+pub struct NativeWitnessNode {
+    /// "Left" or "Right", matching Side in the TypeScript merkle tree
+    pub side: String,
+    pub hash_of_sibling: Buffer,
+}
+
+#[napi(object)]
+pub struct NativeWitness {
+    pub tree_size: u32,
+    pub root_hash: Buffer,
+    pub auth_path: Vec<NativeWitnessNode>,
+}
+
+/// Calculate witnesses for the notes at the given positions from a
+/// serialized tree snapshot, hashing the shared parts of the tree once.
+#[napi]
+pub fn witnesses_for(snapshot: Buffer, positions: Vec<u32>) -> Result<Vec<NativeWitness>> {
+    let snapshot =
+        TreeSnapshot::read(snapshot.as_ref()).map_err(|err| Error::from_reason(err.to_string()))?;
+    let positions: Vec<u64> = positions.into_iter().map(u64::from).collect();
+
+    let witnesses = snapshot
+        .witnesses_for(&positions)
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    witnesses
+        .iter()
+        .map(|witness| {
+            let auth_path = witness
+                .get_auth_path()
+                .into_iter()
+                .map(|node| {
+                    let (side, hash) = match node {
+                        WitnessNode::Left(hash) => ("Left", hash),
+                        WitnessNode::Right(hash) => ("Right", hash),
+                    };
+                    let mut hash_of_sibling = vec![];
+                    MerkleNoteHash::new(hash)
+                        .write(&mut hash_of_sibling)
+                        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+                    Ok(NativeWitnessNode {
+                        side: side.to_string(),
+                        hash_of_sibling: Buffer::from(hash_of_sibling),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut root_hash = vec![];
+            MerkleNoteHash::new(witness.root_hash())
+                .write(&mut root_hash)
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+
+            Ok(NativeWitness {
+                tree_size: witness.tree_size(),
+                root_hash: Buffer::from(root_hash),
+                auth_path,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Describe the shape of the circuits used for proving, so tooling that
+//! regenerates parameters can confirm it is building the expected circuits.
+//!
+//! The circuits are synthesized into a test constraint system with
+//! placeholder witness values. Neither the number of constraints nor the
+//! hash depends on the witness values, only on the structure of the circuit.
+
+use super::{errors, keys::SaplingKey};
+use bellman::{gadgets::test::TestConstraintSystem, Circuit};
+use bls12_381::Scalar;
+use byteorder::{LittleEndian, WriteBytesExt};
+use zcash_primitives::primitives::ValueCommitment;
+use zcash_proofs::circuit::sapling::{Output, Spend, TREE_DEPTH};
+
+use std::io;
+
+/// The hash of the spend circuit that the published parameters were
+/// generated for. The same as zcash's Sapling spend circuit.
+pub const SPEND_CIRCUIT_HASH: &str =
+    "d37c738e83df5d9b0bb6495ac96abf21bcb2697477e2c15c2c7916ff7a3b6a89";
+
+/// The hash of the output circuit that the published parameters were
+/// generated for. The same as zcash's Sapling output circuit.
+pub const OUTPUT_CIRCUIT_HASH: &str =
+    "c26d5cdfe6ccd65c03390902c02e11393ea6bb96aae32a7f2ecb12eb9103faee";
+
+/// The number of constraints and structural hash of a single circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitMetadata {
+    /// Name of the circuit, eg: `spend`
+    pub name: &'static str,
+
+    /// Number of constraints the circuit synthesizes to
+    pub num_constraints: usize,
+
+    /// Hex encoded hash of the constraint system, as calculated by bellman's
+    /// TestConstraintSystem
+    pub hash: String,
+}
+
+impl CircuitMetadata {
+    /// Store the metadata in the given writer. The format is the length
+    /// prefixed name, the number of constraints, then the length prefixed
+    /// hash, and is stable across releases.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u8(self.name.len() as u8)?;
+        writer.write_all(self.name.as_bytes())?;
+        writer.write_u64::<LittleEndian>(self.num_constraints as u64)?;
+        writer.write_u8(self.hash.len() as u8)?;
+        writer.write_all(self.hash.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Calculate the metadata for each of the circuits used in transactions.
+///
+/// This synthesizes every circuit, so it takes a moment. It's intended for
+/// tooling and tests, not for use on a hot path.
+pub fn circuit_metadata() -> Result<Vec<CircuitMetadata>, errors::SaplingProofError> {
+    Ok(vec![spend_metadata()?, receipt_metadata()?])
+}
+
+fn spend_metadata() -> Result<CircuitMetadata, errors::SaplingProofError> {
+    let key = SaplingKey::new([0; 32])?;
+    let address = key.generate_public_address();
+
+    let circuit = Spend {
+        value_commitment: Some(placeholder_value_commitment()),
+        proof_generation_key: Some(key.sapling_proof_generation_key()),
+        payment_address: Some(address.sapling_payment_address()),
+        commitment_randomness: Some(jubjub::Fr::zero()),
+        ar: Some(jubjub::Fr::zero()),
+        auth_path: vec![Some((Scalar::zero(), false)); TREE_DEPTH],
+        anchor: Some(Scalar::zero()),
+    };
+
+    synthesize("spend", circuit)
+}
+
+fn receipt_metadata() -> Result<CircuitMetadata, errors::SaplingProofError> {
+    let key = SaplingKey::new([0; 32])?;
+    let address = key.generate_public_address();
+
+    let circuit = Output {
+        value_commitment: Some(placeholder_value_commitment()),
+        payment_address: Some(address.sapling_payment_address()),
+        commitment_randomness: Some(jubjub::Fr::zero()),
+        esk: Some(jubjub::Fr::zero()),
+    };
+
+    synthesize("output", circuit)
+}
+
+fn placeholder_value_commitment() -> ValueCommitment {
+    ValueCommitment {
+        value: 0,
+        randomness: jubjub::Fr::zero(),
+    }
+}
+
+fn synthesize<C: Circuit<Scalar>>(
+    name: &'static str,
+    circuit: C,
+) -> Result<CircuitMetadata, errors::SaplingProofError> {
+    let mut cs = TestConstraintSystem::<Scalar>::new();
+    circuit.synthesize(&mut cs)?;
+
+    Ok(CircuitMetadata {
+        name,
+        num_constraints: cs.num_constraints(),
+        hash: cs.hash(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{circuit_metadata, OUTPUT_CIRCUIT_HASH, SPEND_CIRCUIT_HASH};
+
+    #[test]
+    fn test_circuit_metadata() {
+        let metadata = circuit_metadata().expect("circuits should synthesize");
+        assert_eq!(metadata.len(), 2);
+
+        assert_eq!(metadata[0].name, "spend");
+        assert_eq!(metadata[0].num_constraints, 98777);
+        assert_eq!(metadata[0].hash, SPEND_CIRCUIT_HASH);
+
+        assert_eq!(metadata[1].name, "output");
+        assert_eq!(metadata[1].num_constraints, 7827);
+        assert_eq!(metadata[1].hash, OUTPUT_CIRCUIT_HASH);
+
+        let mut serialized = vec![];
+        metadata[0]
+            .write(&mut serialized)
+            .expect("should serialize metadata");
+        assert_eq!(serialized.len(), 1 + 5 + 8 + 1 + 64);
+    }
+}
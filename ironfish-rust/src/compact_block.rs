@@ -0,0 +1,325 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A smaller encoding of blocks for light clients.
+//!
+//! A compact block has just enough data to find the notes an account owns
+//! and notice when they're spent: the nullifiers of each spend, and the
+//! commitment, ephemeral key, and start of the ciphertext of each output.
+//! Proofs, signatures, and memos are left out.
+
+use super::{
+    errors,
+    keys::IncomingViewKey,
+    note::{Note, COMPACT_NOTE_SIZE},
+    serializing::read_scalar,
+    transaction::Transaction,
+    MerkleNote, MerkleNoteHash,
+};
+use bls12_381::Scalar;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ff::PrimeField;
+use group::GroupEncoding;
+use jubjub::SubgroupPoint;
+use zcash_primitives::primitives::Nullifier;
+
+use std::io::{self, Read};
+
+/// The parts of a transaction output needed to detect and decrypt notes
+#[derive(Clone)]
+pub struct CompactOutput {
+    /// Commitment to the note, as stored in the note commitment tree
+    pub note_commitment: Scalar,
+
+    /// Public part of the ephemeral diffie-hellman key-pair for the note
+    pub ephemeral_public_key: SubgroupPoint,
+
+    /// Start of the encrypted note, omitting the memo and MAC
+    pub ciphertext_prefix: [u8; COMPACT_NOTE_SIZE],
+}
+
+impl CompactOutput {
+    pub fn from_merkle_note(merkle_note: &MerkleNote) -> Self {
+        let mut ciphertext_prefix = [0; COMPACT_NOTE_SIZE];
+        ciphertext_prefix.copy_from_slice(&merkle_note.encrypted_note[..COMPACT_NOTE_SIZE]);
+
+        CompactOutput {
+            note_commitment: merkle_note.note_commitment,
+            ephemeral_public_key: merkle_note.ephemeral_public_key,
+            ciphertext_prefix,
+        }
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let note_commitment = read_scalar(&mut reader).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unable to convert note commitment",
+            )
+        })?;
+
+        let mut bytes = [0; 32];
+        reader.read_exact(&mut bytes)?;
+        let ephemeral_public_key = SubgroupPoint::from_bytes(&bytes);
+        if ephemeral_public_key.is_none().into() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unable to convert ephemeral public key",
+            ));
+        }
+
+        let mut ciphertext_prefix = [0; COMPACT_NOTE_SIZE];
+        reader.read_exact(&mut ciphertext_prefix)?;
+
+        Ok(CompactOutput {
+            note_commitment,
+            ephemeral_public_key: ephemeral_public_key.unwrap(),
+            ciphertext_prefix,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.note_commitment.to_repr().as_ref())?;
+        writer.write_all(&self.ephemeral_public_key.to_bytes())?;
+        writer.write_all(&self.ciphertext_prefix)?;
+        Ok(())
+    }
+
+    /// Hash of the note as stored in the leaf of the note commitment tree
+    pub fn merkle_hash(&self) -> MerkleNoteHash {
+        MerkleNoteHash::new(self.note_commitment)
+    }
+
+    /// Decrypt the note for its owner. The memo isn't available in compact
+    /// form, so the note's memo is empty.
+    pub fn decrypt_note_for_owner(
+        &self,
+        owner_view_key: &IncomingViewKey,
+    ) -> Result<Note, errors::NoteError> {
+        let shared_secret = owner_view_key.shared_secret(&self.ephemeral_public_key);
+        let note =
+            Note::from_owner_compact(owner_view_key, &shared_secret, &self.ciphertext_prefix)?;
+        note.verify_commitment(self.note_commitment)?;
+        Ok(note)
+    }
+}
+
+/// The parts of a transaction needed by light clients
+#[derive(Clone)]
+pub struct CompactTransaction {
+    /// Hash of the full transaction, so clients can request it if needed
+    pub hash: [u8; 32],
+
+    /// Nullifiers of the notes spent by the transaction
+    pub nullifiers: Vec<Nullifier>,
+
+    /// The outputs of the transaction, in the same order as its receipts
+    pub outputs: Vec<CompactOutput>,
+}
+
+impl CompactTransaction {
+    pub fn from_transaction(transaction: &Transaction) -> Self {
+        CompactTransaction {
+            hash: transaction.hash(),
+            nullifiers: transaction
+                .iter_spends()
+                .map(|spend| spend.nullifier())
+                .collect(),
+            outputs: transaction
+                .iter_receipts()
+                .map(|receipt| CompactOutput::from_merkle_note(&receipt.merkle_note()))
+                .collect(),
+        }
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let mut hash = [0; 32];
+        reader.read_exact(&mut hash)?;
+
+        // Counts aren't trusted to size allocations, since compact blocks
+        // are read from the network
+        let num_nullifiers = reader.read_u32::<LittleEndian>()?;
+        let mut nullifiers = vec![];
+        for _ in 0..num_nullifiers {
+            let mut nullifier = [0; 32];
+            reader.read_exact(&mut nullifier)?;
+            nullifiers.push(Nullifier(nullifier));
+        }
+
+        let num_outputs = reader.read_u32::<LittleEndian>()?;
+        let mut outputs = vec![];
+        for _ in 0..num_outputs {
+            outputs.push(CompactOutput::read(&mut reader)?);
+        }
+
+        Ok(CompactTransaction {
+            hash,
+            nullifiers,
+            outputs,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.hash)?;
+        writer.write_u32::<LittleEndian>(self.nullifiers.len() as u32)?;
+        for nullifier in self.nullifiers.iter() {
+            writer.write_all(&nullifier.0)?;
+        }
+        writer.write_u32::<LittleEndian>(self.outputs.len() as u32)?;
+        for output in self.outputs.iter() {
+            output.write(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// A block header along with the compact form of each of its transactions
+#[derive(Clone)]
+pub struct CompactBlock {
+    /// The serialized block header. Headers are defined outside of this
+    /// crate, so they're carried as opaque bytes.
+    pub header: Vec<u8>,
+
+    pub transactions: Vec<CompactTransaction>,
+}
+
+impl CompactBlock {
+    pub fn new(header: Vec<u8>, transactions: &[Transaction]) -> Self {
+        CompactBlock {
+            header,
+            transactions: transactions
+                .iter()
+                .map(CompactTransaction::from_transaction)
+                .collect(),
+        }
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        // Lengths aren't trusted to size allocations, since compact blocks
+        // are read from the network
+        let header_length = reader.read_u32::<LittleEndian>()?;
+        let mut header = vec![];
+        reader
+            .by_ref()
+            .take(header_length as u64)
+            .read_to_end(&mut header)?;
+        if header.len() != header_length as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Block header is truncated",
+            ));
+        }
+
+        let num_transactions = reader.read_u32::<LittleEndian>()?;
+        let mut transactions = vec![];
+        for _ in 0..num_transactions {
+            transactions.push(CompactTransaction::read(&mut reader)?);
+        }
+
+        Ok(CompactBlock {
+            header,
+            transactions,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(self.header.len() as u32)?;
+        writer.write_all(&self.header)?;
+        writer.write_u32::<LittleEndian>(self.transactions.len() as u32)?;
+        for transaction in self.transactions.iter() {
+            transaction.write(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CompactBlock, CompactOutput};
+    use crate::{
+        keys::SaplingKey,
+        note::{Memo, Note},
+        sapling_bls12,
+        test_util::make_fake_witness,
+        transaction::ProposedTransaction,
+        MerkleNote,
+    };
+
+    use rand::{thread_rng, Rng};
+    use zcash_primitives::primitives::ValueCommitment;
+
+    #[test]
+    fn test_compact_output_decryption() {
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let note = Note::new(
+            receiver_key.generate_public_address(),
+            42,
+            Memo::from("not in compact form"),
+        );
+
+        let mut buffer = [0u8; 64];
+        thread_rng().fill(&mut buffer[..]);
+        let value_commitment = ValueCommitment {
+            value: note.value(),
+            randomness: jubjub::Fr::from_bytes_wide(&buffer),
+        };
+        let diffie_hellman_keys = note.owner().generate_diffie_hellman_keys();
+        let merkle_note =
+            MerkleNote::new(&spender_key, &note, &value_commitment, &diffie_hellman_keys);
+
+        let output = CompactOutput::from_merkle_note(&merkle_note);
+        assert_eq!(output.merkle_hash(), merkle_note.merkle_hash());
+
+        let decrypted = output
+            .decrypt_note_for_owner(receiver_key.incoming_view_key())
+            .expect("should be able to decrypt compact output");
+        assert_eq!(decrypted.value(), 42);
+        assert_eq!(decrypted.memo(), Memo::default());
+        assert_eq!(decrypted.commitment(), note.commitment());
+
+        assert!(output
+            .decrypt_note_for_owner(spender_key.incoming_view_key())
+            .is_err());
+    }
+
+    #[test]
+    fn test_compact_block_serialization() {
+        let sapling = sapling_bls12::SAPLING.clone();
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+
+        let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+        let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+        let witness = make_fake_witness(&in_note);
+
+        let mut transaction = ProposedTransaction::new(sapling);
+        transaction
+            .spend(spender_key.clone(), &in_note, &witness)
+            .expect("should be able to spend note");
+        transaction
+            .receive(&spender_key, &out_note)
+            .expect("should be able to receive note");
+        let transaction = transaction
+            .post(&spender_key, None, 1)
+            .expect("should be able to post transaction");
+
+        let block = CompactBlock::new(vec![1, 2, 3], &[transaction]);
+        let mut serialized = vec![];
+        block.write(&mut serialized).expect("should serialize");
+        let read_back = CompactBlock::read(&serialized[..]).expect("should deserialize");
+
+        assert_eq!(read_back.header, vec![1, 2, 3]);
+        assert_eq!(read_back.transactions.len(), 1);
+        let compact_transaction = &read_back.transactions[0];
+        assert_eq!(compact_transaction.hash, block.transactions[0].hash);
+        assert_eq!(compact_transaction.nullifiers.len(), 1);
+        assert_eq!(compact_transaction.outputs.len(), 2);
+
+        let note = compact_transaction.outputs[0]
+            .decrypt_note_for_owner(receiver_key.incoming_view_key())
+            .expect("should decrypt the receiver's note");
+        assert_eq!(note.value(), 40);
+    }
+}
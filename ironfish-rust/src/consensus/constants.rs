@@ -0,0 +1,47 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Numbers the network has to agree on. The node reads the ones it uses
+//! through the napi bindings, in ironfish/src/consensus/consensus.ts, rather
+//! than keeping its own copies, so changing one here changes it everywhere.
+
+/// The sequence of the genesis block starts at 1
+pub const GENESIS_BLOCK_SEQUENCE: u32 = 1;
+
+/// The amount of coins in the genesis block
+pub const GENESIS_SUPPLY_IN_IRON: u64 = 42_000_000;
+
+/// The number of ore, the smallest unit of value in a note, in one iron
+pub const ORE_PER_IRON: u64 = 100_000_000;
+
+/// An upper bound on the iron that will ever exist: the genesis supply plus
+/// every block reward, rounded up to the next million
+pub const MAX_SUPPLY_IN_IRON: u64 = 257_000_000;
+
+/// When adding a block, the block can be this amount of seconds into the
+/// future without rejecting it
+pub const ALLOWED_BLOCK_FUTURE_SECONDS: u32 = 15;
+
+/// The maximum allowed requested blocks by the network
+pub const MAX_REQUESTED_BLOCKS: u32 = 50;
+
+/// Max size for a message, for instance when requesting batches of blocks
+pub const MAX_MESSAGE_SIZE: u32 = 256 * 1024 * 1024;
+
+/// The average time that all blocks should be mined
+pub const TARGET_BLOCK_TIME_IN_SECONDS: u32 = 60;
+
+/// The time range when difficulty and target not change
+pub const TARGET_BUCKET_TIME_IN_SECONDS: u32 = 10;
+
+/// Graffiti sizes in bytes
+pub const GRAFFITI_SIZE: usize = 32;
+
+/// An approximation of how many blocks make up a year, used in calculating
+/// how much a miner should get in rewards.
+pub const IRON_FISH_YEAR_IN_BLOCKS: u32 = (365 * 24 * 60 * 60) / TARGET_BLOCK_TIME_IN_SECONDS;
+
+/// The most transactions a miner puts in a block template, including the
+/// miners fee
+pub const MAX_TRANSACTIONS_PER_BLOCK: u32 = 300;
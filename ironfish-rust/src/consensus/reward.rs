@@ -0,0 +1,77 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::constants::{GENESIS_SUPPLY_IN_IRON, IRON_FISH_YEAR_IN_BLOCKS, ORE_PER_IRON};
+
+/// Rewards are rounded to the nearest eighth of an iron
+const REWARD_GRANULARITY_IN_IRON: f64 = 0.125;
+
+/// The reward, in ore, for mining the block at the given sequence.
+///
+/// A quarter of the genesis supply is emitted in the first year, decaying
+/// by a factor of e^-0.05 each year after that, and split evenly over the
+/// blocks in the year. This is the same calculation as
+/// Strategy.miningReward in the node, floating point operations included,
+/// so that both agree on every reward.
+pub fn block_reward(sequence: u32) -> u64 {
+    let years_after_launch = sequence / IRON_FISH_YEAR_IN_BLOCKS;
+
+    let annual_reward = (GENESIS_SUPPLY_IN_IRON as f64 / 4.0)
+        * std::f64::consts::E.powf(-0.05 * years_after_launch as f64);
+    let block_reward = annual_reward / IRON_FISH_YEAR_IN_BLOCKS as f64;
+    let rounded = REWARD_GRANULARITY_IN_IRON * (block_reward / REWARD_GRANULARITY_IN_IRON).round();
+
+    (rounded * ORE_PER_IRON as f64).round() as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::block_reward;
+    use crate::consensus::constants::{
+        GENESIS_SUPPLY_IN_IRON, IRON_FISH_YEAR_IN_BLOCKS, MAX_SUPPLY_IN_IRON, ORE_PER_IRON,
+    };
+
+    #[test]
+    fn test_block_reward() {
+        assert_eq!(block_reward(1), 2_000_000_000);
+        assert_eq!(block_reward(IRON_FISH_YEAR_IN_BLOCKS - 1), 2_000_000_000);
+        assert_eq!(block_reward(IRON_FISH_YEAR_IN_BLOCKS), 1_900_000_000);
+        assert_eq!(block_reward(IRON_FISH_YEAR_IN_BLOCKS + 1), 1_900_000_000);
+        assert_eq!(block_reward(2 * IRON_FISH_YEAR_IN_BLOCKS), 1_812_500_000);
+        assert_eq!(block_reward(10 * IRON_FISH_YEAR_IN_BLOCKS), 1_212_500_000);
+        assert_eq!(block_reward(50 * IRON_FISH_YEAR_IN_BLOCKS), 162_500_000);
+        assert_eq!(block_reward(100 * IRON_FISH_YEAR_IN_BLOCKS), 12_500_000);
+
+        // Rewards only change from one year to the next, and never increase
+        let mut previous = block_reward(0);
+        for year in 1..200 {
+            let reward = block_reward(year * IRON_FISH_YEAR_IN_BLOCKS);
+            assert_eq!(
+                reward,
+                block_reward((year + 1) * IRON_FISH_YEAR_IN_BLOCKS - 1)
+            );
+            assert!(reward <= previous);
+            previous = reward;
+        }
+    }
+
+    #[test]
+    fn test_max_supply() {
+        // Rewards are the same for every block in a year and reach zero
+        // eventually, so the total can be summed a year at a time
+        let mut supply = GENESIS_SUPPLY_IN_IRON * ORE_PER_IRON;
+        let mut year = 0;
+        loop {
+            let reward = block_reward(year * IRON_FISH_YEAR_IN_BLOCKS);
+            if reward == 0 {
+                break;
+            }
+            supply += reward * IRON_FISH_YEAR_IN_BLOCKS as u64;
+            year += 1;
+        }
+
+        assert!(supply <= MAX_SUPPLY_IN_IRON * ORE_PER_IRON);
+        assert!(supply > (MAX_SUPPLY_IN_IRON - 1_000_000) * ORE_PER_IRON);
+    }
+}
@@ -19,6 +19,15 @@ pub enum SaplingKeyError {
     DiversificationError,
     InvalidLanguageEncoding,
     InvalidWord,
+    /// The key isn't valid bech32m, or doesn't hold 32 bytes
+    InvalidEncoding,
+    /// The bech32m checksum doesn't match. Holds the position of the
+    /// character that was probably mistyped, if one could be found.
+    InvalidChecksum(Option<usize>),
+    /// The character at this position can't appear in a bech32m key
+    InvalidCharacter(usize),
+    /// The key is a different kind of key than expected; holds its prefix
+    WrongKeyPrefix(String),
 }
 
 impl fmt::Display for SaplingKeyError {
@@ -35,6 +44,32 @@ impl From<io::Error> for SaplingKeyError {
     }
 }
 
+/// Error raised when reading a point, signature or proof that has to be in
+/// its canonical encoding
+#[derive(Debug)]
+pub enum EncodingError {
+    /// The bytes don't encode a valid value
+    InvalidEncoding,
+    /// The value could be read, but the bytes aren't the encoding it
+    /// serializes to
+    NonCanonicalEncoding,
+    IoError(io::Error),
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for EncodingError {}
+
+impl From<io::Error> for EncodingError {
+    fn from(e: io::Error) -> EncodingError {
+        EncodingError::IoError(e)
+    }
+}
+
 /// Error raised if proving fails for some reason
 #[derive(Debug)]
 pub enum SaplingProofError {
@@ -45,6 +80,16 @@ pub enum SaplingProofError {
     SigningError,
     VerificationFailed,
     InconsistentWitness,
+    InconsistentDiffieHellmanKeys,
+    MissingProvingParameters,
+    DuplicateSpend,
+    DuplicateReceipt,
+    /// A point, scalar, signature or proof could be read, but had more than
+    /// one valid encoding and wasn't in the canonical one
+    NonCanonicalEncoding,
+    /// A spend's root hash isn't the root of the note tree at the size it
+    /// claims
+    InvalidAnchor,
 }
 
 impl fmt::Display for SaplingProofError {
@@ -73,6 +118,15 @@ impl From<io::Error> for SaplingProofError {
     }
 }
 
+impl From<EncodingError> for SaplingProofError {
+    fn from(e: EncodingError) -> SaplingProofError {
+        match e {
+            EncodingError::NonCanonicalEncoding => SaplingProofError::NonCanonicalEncoding,
+            _ => SaplingProofError::IOError,
+        }
+    }
+}
+
 /// Errors raised when constructing a transaction
 #[derive(Debug)]
 pub enum TransactionError {
@@ -82,6 +136,14 @@ pub enum TransactionError {
     ProvingError,
     IoError(io::Error),
     VerificationFailed,
+    ExpiredError,
+    TooManySpendsError,
+    TooManyReceiptsError,
+    InvalidFeeError,
+    DuplicateSpendError,
+    DuplicateReceiptError,
+    NonCanonicalEncoding,
+    InvalidAnchor,
 }
 
 impl fmt::Display for TransactionError {
@@ -97,6 +159,10 @@ impl From<SaplingProofError> for TransactionError {
         match e {
             SaplingProofError::SigningError => TransactionError::SigningError,
             SaplingProofError::VerificationFailed => TransactionError::VerificationFailed,
+            SaplingProofError::DuplicateSpend => TransactionError::DuplicateSpendError,
+            SaplingProofError::DuplicateReceipt => TransactionError::DuplicateReceiptError,
+            SaplingProofError::NonCanonicalEncoding => TransactionError::NonCanonicalEncoding,
+            SaplingProofError::InvalidAnchor => TransactionError::InvalidAnchor,
             _ => TransactionError::ProvingError,
         }
     }
@@ -115,6 +181,9 @@ pub enum NoteError {
     RandomnessError,
     KeyError,
     InvalidCommitment,
+    InvalidMemo,
+    /// The memo was longer than MEMO_SIZE; holds the length it had
+    MemoTooLong(usize),
 }
 
 impl fmt::Display for NoteError {
@@ -136,3 +205,159 @@ impl From<SaplingKeyError> for NoteError {
         NoteError::KeyError
     }
 }
+
+/// Errors raised when decoding a peer to peer message
+#[derive(Debug)]
+pub enum NetworkMessageError {
+    UnknownMessageType,
+    UnexpectedEndOfMessage,
+    NonCanonicalVarint,
+    TrailingBytes,
+    InvalidValue,
+}
+
+impl fmt::Display for NetworkMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for NetworkMessageError {}
+
+impl From<io::Error> for NetworkMessageError {
+    fn from(e: io::Error) -> NetworkMessageError {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => NetworkMessageError::UnexpectedEndOfMessage,
+            _ => NetworkMessageError::InvalidValue,
+        }
+    }
+}
+
+/// Errors raised by a storage backend
+#[derive(Debug)]
+pub enum StorageError {
+    IoError(io::Error),
+    LockError,
+    BackendError(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> StorageError {
+        StorageError::IoError(e)
+    }
+}
+
+/// Errors raised when updating or spending from a wallet
+#[derive(Debug)]
+pub enum WalletError {
+    StorageError,
+    InsufficientFundsError,
+    MissingWitness,
+    InvalidExport,
+    NoRecipients,
+    TransactionError(TransactionError),
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for WalletError {}
+
+impl From<io::Error> for WalletError {
+    fn from(_e: io::Error) -> WalletError {
+        WalletError::StorageError
+    }
+}
+
+impl From<StorageError> for WalletError {
+    fn from(_e: StorageError) -> WalletError {
+        WalletError::StorageError
+    }
+}
+
+impl From<TransactionError> for WalletError {
+    fn from(e: TransactionError) -> WalletError {
+        WalletError::TransactionError(e)
+    }
+}
+
+impl From<SaplingProofError> for WalletError {
+    fn from(e: SaplingProofError) -> WalletError {
+        WalletError::TransactionError(e.into())
+    }
+}
+
+/// Errors raised when building the transactions of a genesis block
+#[derive(Debug)]
+pub enum GenesisError {
+    NoAllocations,
+    SupplyOverflow,
+    TransactionError(TransactionError),
+    TreeSnapshotError(TreeSnapshotError),
+}
+
+impl fmt::Display for GenesisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for GenesisError {}
+
+impl From<TransactionError> for GenesisError {
+    fn from(e: TransactionError) -> GenesisError {
+        GenesisError::TransactionError(e)
+    }
+}
+
+impl From<SaplingProofError> for GenesisError {
+    fn from(e: SaplingProofError) -> GenesisError {
+        GenesisError::TransactionError(e.into())
+    }
+}
+
+impl From<TreeSnapshotError> for GenesisError {
+    fn from(e: TreeSnapshotError) -> GenesisError {
+        GenesisError::TreeSnapshotError(e)
+    }
+}
+
+/// Errors raised when calculating witnesses from a tree snapshot
+#[derive(Debug)]
+pub enum TreeSnapshotError {
+    IOError,
+    InvalidLeaf,
+    EmptyTree,
+    PositionOutOfRange(u64),
+}
+
+impl fmt::Display for TreeSnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for TreeSnapshotError {}
+
+impl From<io::Error> for TreeSnapshotError {
+    fn from(_e: io::Error) -> TreeSnapshotError {
+        TreeSnapshotError::IOError
+    }
+}
+
+impl From<SaplingKeyError> for TreeSnapshotError {
+    fn from(_e: SaplingKeyError) -> TreeSnapshotError {
+        TreeSnapshotError::InvalidLeaf
+    }
+}
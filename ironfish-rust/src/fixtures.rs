@@ -0,0 +1,208 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Read and write the test fixtures of the NodeJS package.
+//!
+//! The TypeScript tests record the transactions, blocks and accounts they
+//! create in `__fixtures__/*.fixture` files, which are JSON objects mapping
+//! test names to lists of values. Buffers are stored as `{ "type": "Buffer",
+//! "data": "base64:..." }`. Loading them here lets the Rust tests check that
+//! both languages agree on serialization without running the NodeJS tests.
+
+use serde_json::{json, Map, Value};
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const BUFFER_PREFIX: &str = "base64:";
+
+/// The values recorded by each test in a fixture file.
+pub type Fixtures = BTreeMap<String, Vec<Value>>;
+
+/// The source directory of the NodeJS package, which contains the
+/// `__fixtures__` directories.
+pub fn fixtures_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../ironfish/src")
+}
+
+/// Load every value from a fixture file, grouped by the name of the test
+/// that recorded them.
+pub fn load_fixture<P: AsRef<Path>>(path: P) -> io::Result<Fixtures> {
+    let contents = fs::read_to_string(path)?;
+    parse_fixture(&contents)
+}
+
+/// Parse the contents of a fixture file.
+pub fn parse_fixture(contents: &str) -> io::Result<Fixtures> {
+    Ok(serde_json::from_str(contents)?)
+}
+
+/// Load every buffer from a fixture file, grouped by the name of the test
+/// that recorded them. Buffers nested in other values, such as accounts, are
+/// included.
+pub fn load_fixture_buffers<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, Vec<Vec<u8>>>> {
+    let contents = fs::read_to_string(path)?;
+    parse_fixture_buffers(&contents)
+}
+
+/// Parse the buffers out of the contents of a fixture file.
+pub fn parse_fixture_buffers(contents: &str) -> io::Result<BTreeMap<String, Vec<Vec<u8>>>> {
+    let mut fixtures = BTreeMap::new();
+    for (name, values) in parse_fixture(contents)? {
+        let mut buffers = vec![];
+        for value in values.iter() {
+            collect_buffers(value, &mut buffers)?;
+        }
+        fixtures.insert(name, buffers);
+    }
+    Ok(fixtures)
+}
+
+/// Write fixtures in the same format as the TypeScript tests, so fixtures
+/// loaded with [`load_fixture`] or generated from Rust can be loaded by the
+/// NodeJS package. Use [`buffer_value`] to store buffers.
+pub fn write_fixture<W: io::Write>(mut writer: W, fixtures: &Fixtures) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut writer, fixtures)?;
+    Ok(())
+}
+
+/// The value the TypeScript tests store a buffer as.
+pub fn buffer_value(bytes: &[u8]) -> Value {
+    json!({
+        "type": "Buffer",
+        "data": format!("{}{}", BUFFER_PREFIX, base64::encode(bytes)),
+    })
+}
+
+/// Read a buffer stored by [`buffer_value`], or `None` if the value isn't
+/// one.
+pub fn read_buffer_value(value: &Value) -> io::Result<Option<Vec<u8>>> {
+    let entries = match value.as_object() {
+        Some(entries) if is_buffer(entries) => entries,
+        _ => return Ok(None),
+    };
+
+    match entries.get("data").and_then(Value::as_str) {
+        Some(data) if data.starts_with(BUFFER_PREFIX) => {
+            base64::decode(&data[BUFFER_PREFIX.len()..])
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn is_buffer(entries: &Map<String, Value>) -> bool {
+    entries.get("type").and_then(Value::as_str) == Some("Buffer")
+}
+
+fn collect_buffers(value: &Value, buffers: &mut Vec<Vec<u8>>) -> io::Result<()> {
+    if let Some(buffer) = read_buffer_value(value)? {
+        buffers.push(buffer);
+        return Ok(());
+    }
+
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                collect_buffers(value, buffers)?;
+            }
+        }
+        Value::Object(entries) => {
+            for value in entries.values() {
+                collect_buffers(value, buffers)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        buffer_value, fixtures_root, load_fixture, load_fixture_buffers, parse_fixture,
+        parse_fixture_buffers, read_buffer_value, write_fixture, Fixtures,
+    };
+    use crate::{sapling_bls12, transaction::Transaction};
+
+    use serde_json::json;
+
+    #[test]
+    fn test_buffer_value() {
+        let value = buffer_value(b"foob");
+        assert_eq!(
+            value,
+            json!({ "type": "Buffer", "data": "base64:Zm9vYg==" })
+        );
+        assert_eq!(read_buffer_value(&value).unwrap(), Some(b"foob".to_vec()));
+
+        assert_eq!(read_buffer_value(&json!({ "name": "x" })).unwrap(), None);
+        assert!(read_buffer_value(&json!({ "type": "Buffer", "data": "base64:Zm9v!" })).is_err());
+    }
+
+    #[test]
+    fn test_fixture_round_trip() {
+        let mut fixtures = Fixtures::new();
+        fixtures.insert(
+            "a \"quoted\" test".to_string(),
+            vec![buffer_value(&[1, 2, 3]), buffer_value(&[])],
+        );
+        fixtures.insert(
+            "account".to_string(),
+            vec![json!({ "name": "x", "rescan": null, "spendingKey": buffer_value(&[4]) })],
+        );
+        fixtures.insert("empty".to_string(), vec![]);
+
+        let mut written = vec![];
+        write_fixture(&mut written, &fixtures).unwrap();
+        let written = std::str::from_utf8(&written).unwrap();
+        assert_eq!(parse_fixture(written).unwrap(), fixtures);
+
+        let buffers = parse_fixture_buffers(written).unwrap();
+        assert_eq!(buffers["a \"quoted\" test"], vec![vec![1, 2, 3], vec![]]);
+        assert_eq!(buffers["account"], vec![vec![4]]);
+        assert!(buffers["empty"].is_empty());
+    }
+
+    #[test]
+    fn test_typescript_account_fixtures() {
+        let path = fixtures_root().join("account/__fixtures__/accounts.test.ts.fixture");
+        let fixtures = load_fixture(path).expect("should load fixture");
+        assert!(!fixtures.is_empty());
+
+        let mut written = vec![];
+        write_fixture(&mut written, &fixtures).unwrap();
+        assert_eq!(
+            parse_fixture(std::str::from_utf8(&written).unwrap()).unwrap(),
+            fixtures
+        );
+    }
+
+    #[test]
+    fn test_typescript_transaction_fixtures() {
+        let path =
+            fixtures_root().join("workerPool/tasks/__fixtures__/verifyTransaction.test.ts.fixture");
+        let fixtures = load_fixture_buffers(path).expect("should load fixture");
+        assert!(!fixtures.is_empty());
+
+        for (name, buffers) in fixtures.iter() {
+            assert!(!buffers.is_empty(), "{} has no buffers", name);
+            for buffer in buffers {
+                let transaction = Transaction::read(sapling_bls12::SAPLING.clone(), &buffer[..])
+                    .unwrap_or_else(|_| panic!("{} should deserialize", name));
+                transaction
+                    .verify()
+                    .unwrap_or_else(|_| panic!("{} should verify", name));
+
+                let mut serialized = vec![];
+                transaction.write(&mut serialized).unwrap();
+                assert_eq!(&serialized, buffer);
+            }
+        }
+    }
+}
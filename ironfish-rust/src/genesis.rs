@@ -0,0 +1,206 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Build the transactions of a genesis block from a list of allocations.
+//!
+//! This follows makeGenesisBlock in the node: a throwaway key is granted the
+//! whole supply out of thin air, then spends it to each allocation. The
+//! block also has a zero-value miners fee, since every block needs one.
+
+use super::{
+    errors::{GenesisError, TransactionError},
+    keys::{PublicAddress, SaplingKey},
+    note::{Memo, Note},
+    transaction::{ProposedTransaction, Transaction},
+    tree_snapshot::TreeSnapshot,
+    Sapling,
+};
+use bls12_381::Scalar;
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use std::{io, sync::Arc};
+
+/// Coins granted to an address in the genesis block
+#[derive(Clone)]
+pub struct GenesisAllocation {
+    pub address: PublicAddress,
+    pub amount: u64,
+    pub memo: Memo,
+}
+
+/// The transactions of a genesis block
+pub struct GenesisTransactions {
+    /// The zero-value miners fee, owned by the account creating the block
+    pub miners_fee: Transaction,
+
+    /// The transaction creating the supply, followed by the one that
+    /// distributes it to the allocations
+    pub transactions: Vec<Transaction>,
+
+    /// Root of the note commitment tree once the block is added
+    pub note_tree_root: Scalar,
+
+    /// Size of the note commitment tree once the block is added
+    pub note_tree_size: u32,
+}
+
+impl GenesisTransactions {
+    /// Every transaction in the order they appear in the block, starting
+    /// with the miners fee.
+    pub fn block_transactions(&self) -> impl Iterator<Item = &Transaction> {
+        std::iter::once(&self.miners_fee).chain(self.transactions.iter())
+    }
+
+    /// Write the transactions of the block: the number of transactions as a
+    /// u32, then each serialized transaction prefixed with its length as a
+    /// u32, in block order.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(self.transactions.len() as u32 + 1)?;
+        for transaction in self.block_transactions() {
+            let mut serialized = vec![];
+            transaction.write(&mut serialized)?;
+            writer.write_u32::<LittleEndian>(serialized.len() as u32)?;
+            writer.write_all(&serialized)?;
+        }
+        Ok(())
+    }
+}
+
+/// Build the transactions for a genesis block granting each allocation its
+/// amount. The miners fee is paid to miner_key, and the note creating the
+/// supply carries the given memo.
+pub fn build_genesis_transactions(
+    sapling: Arc<Sapling>,
+    miner_key: &SaplingKey,
+    allocations: &[GenesisAllocation],
+    memo: Memo,
+) -> Result<GenesisTransactions, GenesisError> {
+    if allocations.is_empty() {
+        return Err(GenesisError::NoAllocations);
+    }
+    let supply = allocations
+        .iter()
+        .try_fold(0u64, |sum, allocation| sum.checked_add(allocation.amount))
+        .ok_or(GenesisError::SupplyOverflow)?;
+
+    let mut miners_fee = ProposedTransaction::new(sapling.clone());
+    miners_fee.receive(
+        miner_key,
+        &Note::new(miner_key.generate_public_address(), 0, Memo::default()),
+    )?;
+    let miners_fee = miners_fee.post_miners_fee()?;
+
+    // The key holding the supply between the two transactions. It ends up
+    // with nothing and is discarded.
+    let genesis_key = SaplingKey::generate_key();
+    let genesis_note = Note::new(genesis_key.generate_public_address(), supply, memo);
+
+    let mut initial = ProposedTransaction::new(sapling.clone());
+    initial.receive(&genesis_key, &genesis_note)?;
+    let initial = initial.post_miners_fee()?;
+
+    // The genesis note is the second in the tree, after the miners fee
+    let mut tree = TreeSnapshot::default();
+    for transaction in [&miners_fee, &initial].iter() {
+        for receipt in transaction.iter_receipts() {
+            tree.push(receipt.merkle_note().merkle_hash());
+        }
+    }
+    let witness = tree
+        .witnesses_for(&[1])?
+        .pop()
+        .expect("one witness was requested");
+
+    let mut distribution = ProposedTransaction::new(sapling);
+    distribution.spend(genesis_key.clone(), &genesis_note, &witness)?;
+    for allocation in allocations {
+        distribution.receive(
+            &genesis_key,
+            &Note::new(
+                allocation.address.clone(),
+                allocation.amount,
+                allocation.memo,
+            ),
+        )?;
+    }
+    let distribution = distribution.post(&genesis_key, None, 0)?;
+
+    for receipt in distribution.iter_receipts() {
+        tree.push(receipt.merkle_note().merkle_hash());
+    }
+
+    Ok(GenesisTransactions {
+        miners_fee,
+        transactions: vec![initial, distribution],
+        note_tree_root: tree.root_hash()?,
+        note_tree_size: tree.size() as u32,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_genesis_transactions, GenesisAllocation};
+    use crate::{keys::SaplingKey, note::Memo, sapling_bls12, tree_snapshot::TreeSnapshot};
+
+    #[test]
+    fn test_build_genesis_transactions() {
+        let miner_key = SaplingKey::generate_key();
+        let first_key = SaplingKey::generate_key();
+        let second_key = SaplingKey::generate_key();
+        let allocations = [
+            GenesisAllocation {
+                address: first_key.generate_public_address(),
+                amount: 100,
+                memo: Memo::from("first"),
+            },
+            GenesisAllocation {
+                address: second_key.generate_public_address(),
+                amount: 200,
+                memo: Memo::from("second"),
+            },
+        ];
+
+        let genesis = build_genesis_transactions(
+            sapling_bls12::SAPLING.clone(),
+            &miner_key,
+            &allocations,
+            Memo::from("genesis"),
+        )
+        .expect("should be able to build genesis transactions");
+
+        assert_eq!(genesis.transactions.len(), 2);
+        assert_eq!(genesis.miners_fee.transaction_fee(), 0);
+        assert_eq!(genesis.transactions[0].transaction_fee(), -300);
+        let distribution = &genesis.transactions[1];
+        distribution.verify().expect("distribution should verify");
+        assert_eq!(distribution.transaction_fee(), 0);
+
+        // Each allocation can find its note
+        let notes: Vec<_> = distribution
+            .iter_receipts()
+            .filter_map(|receipt| {
+                receipt
+                    .merkle_note()
+                    .decrypt_note_for_owner(first_key.incoming_view_key())
+                    .ok()
+            })
+            .collect();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].value(), 100);
+        assert_eq!(notes[0].memo(), Memo::from("first"));
+
+        let mut tree = TreeSnapshot::default();
+        for transaction in genesis.block_transactions() {
+            for receipt in transaction.iter_receipts() {
+                tree.push(receipt.merkle_note().merkle_hash());
+            }
+        }
+        assert_eq!(genesis.note_tree_size, 4);
+        assert_eq!(genesis.note_tree_root, tree.root_hash().unwrap());
+
+        let mut serialized = vec![];
+        genesis.write(&mut serialized).unwrap();
+        assert_eq!(serialized[..4], 3u32.to_le_bytes());
+    }
+}
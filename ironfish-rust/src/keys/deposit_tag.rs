@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::IncomingViewKey;
+use crate::{
+    note::{Memo, MEMO_SIZE},
+    serializing::scalar_to_bytes,
+};
+use blake2b_simd::Params as Blake2b;
+use subtle::ConstantTimeEq;
+
+use std::convert::TryInto;
+
+const DEPOSIT_TAG_PERSONALIZATION: &[u8; 16] = b"Bndeposit_tag___";
+
+/// Marks a memo as holding a deposit tag rather than free text.
+const DEPOSIT_TAG_MEMO_PREFIX: &[u8; 4] = b"ifdt";
+
+const DEPOSIT_TAG_MAC_SIZE: usize = 16;
+
+/// Number of bytes in a serialized deposit tag: the user id followed by
+/// the MAC.
+pub const DEPOSIT_TAG_SIZE: usize = 8 + DEPOSIT_TAG_MAC_SIZE;
+
+/// Identifies which of an exchange's users a deposit belongs to, so an
+/// exchange can take deposits for all of its users at one address.
+///
+/// The exchange derives a tag for each user from its incoming view key with
+/// IncomingViewKey::deposit_tag and hands it to the user, whose wallet puts
+/// it in the encrypted memo of the deposit (see
+/// ProposedTransaction::receive_deposit). Only the exchange can derive valid
+/// tags, so users can't claim each other's deposits by guessing user ids,
+/// and since the tag is encrypted with the note, nobody else can link
+/// deposits from the same user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepositTag {
+    user_id: u64,
+    mac: [u8; DEPOSIT_TAG_MAC_SIZE],
+}
+
+impl DepositTag {
+    pub fn from_bytes(bytes: &[u8; DEPOSIT_TAG_SIZE]) -> DepositTag {
+        DepositTag {
+            user_id: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            mac: bytes[8..].try_into().unwrap(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; DEPOSIT_TAG_SIZE] {
+        let mut bytes = [0; DEPOSIT_TAG_SIZE];
+        bytes[..8].copy_from_slice(&self.user_id.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.mac);
+        bytes
+    }
+
+    /// The user the exchange derived this tag for. Only meaningful once the
+    /// tag has been checked with IncomingViewKey::verify_deposit_tag.
+    pub fn user_id(&self) -> u64 {
+        self.user_id
+    }
+
+    /// The memo to attach to a deposit carrying this tag
+    pub fn memo(&self) -> Memo {
+        let mut memo = [0; MEMO_SIZE];
+        memo[..4].copy_from_slice(DEPOSIT_TAG_MEMO_PREFIX);
+        memo[4..4 + DEPOSIT_TAG_SIZE].copy_from_slice(&self.to_bytes());
+        Memo(memo)
+    }
+
+    /// Read the tag from the memo of a deposit, if it has one
+    pub fn from_memo(memo: &Memo) -> Option<DepositTag> {
+        if &memo.0[..4] != DEPOSIT_TAG_MEMO_PREFIX {
+            return None;
+        }
+        let bytes = memo.0[4..4 + DEPOSIT_TAG_SIZE].try_into().unwrap();
+        Some(DepositTag::from_bytes(bytes))
+    }
+}
+
+impl IncomingViewKey {
+    /// Derive the deposit tag for one of the exchange's users
+    pub fn deposit_tag(&self, user_id: u64) -> DepositTag {
+        DepositTag {
+            user_id,
+            mac: self.deposit_tag_mac(user_id),
+        }
+    }
+
+    /// Whether the tag was derived from this key
+    pub fn verify_deposit_tag(&self, tag: &DepositTag) -> bool {
+        self.deposit_tag_mac(tag.user_id).ct_eq(&tag.mac).into()
+    }
+
+    fn deposit_tag_mac(&self, user_id: u64) -> [u8; DEPOSIT_TAG_MAC_SIZE] {
+        let hash = Blake2b::new()
+            .hash_length(DEPOSIT_TAG_MAC_SIZE)
+            .key(&scalar_to_bytes(&self.view_key))
+            .personal(DEPOSIT_TAG_PERSONALIZATION)
+            .hash(&user_id.to_le_bytes());
+
+        hash.as_bytes().try_into().unwrap()
+    }
+}
@@ -0,0 +1,142 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Checksummed bech32m encoding of keys.
+//!
+//! Hex keys give no feedback when they are mistyped: a wrong character
+//! just produces a different, valid looking key. The bech32m checksum
+//! catches typos, and the prefix catches pasting the wrong kind of key.
+
+use super::errors::SaplingKeyError;
+use bech32::{FromBase32, ToBase32, Variant};
+
+/// Prefix of bech32m encoded spending keys
+pub const SPENDING_KEY_PREFIX: &str = "ifsecret";
+
+/// Prefix of bech32m encoded incoming view keys
+pub const INCOMING_VIEW_KEY_PREFIX: &str = "ifincoming";
+
+/// Prefix of bech32m encoded outgoing view keys
+pub const OUTGOING_VIEW_KEY_PREFIX: &str = "ifoutgoing";
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+pub(crate) fn encode(prefix: &str, bytes: &[u8; 32]) -> String {
+    bech32::encode(prefix, bytes.to_base32(), Variant::Bech32m)
+        .expect("prefix is a valid human readable part")
+}
+
+/// Decode a key with the given prefix.
+///
+/// A checksum failure is reported with the position of the character that
+/// was most likely mistyped, if changing a single character fixes it.
+pub(crate) fn decode(prefix: &str, value: &str) -> Result<[u8; 32], SaplingKeyError> {
+    let data = match bech32::decode(value) {
+        Ok((found_prefix, _, _)) if found_prefix != prefix => {
+            return Err(SaplingKeyError::WrongKeyPrefix(found_prefix))
+        }
+        Ok((_, _, Variant::Bech32)) => return Err(SaplingKeyError::InvalidEncoding),
+        Ok((_, data, Variant::Bech32m)) => data,
+        Err(bech32::Error::InvalidChecksum) => {
+            return Err(SaplingKeyError::InvalidChecksum(find_typo(prefix, value)))
+        }
+        Err(bech32::Error::InvalidChar(c)) => {
+            // Only the data after the separator is checked character by
+            // character, and the prefix may contain characters it can't
+            let data_start = value.rfind('1').map_or(0, |separator| separator + 1);
+            let position = value[data_start..]
+                .chars()
+                .position(|v| v == c)
+                .map_or(0, |position| data_start + position);
+            return Err(SaplingKeyError::InvalidCharacter(position));
+        }
+        Err(_) => return Err(SaplingKeyError::InvalidEncoding),
+    };
+
+    let bytes = Vec::<u8>::from_base32(&data).map_err(|_| SaplingKeyError::InvalidEncoding)?;
+    if bytes.len() != 32 {
+        return Err(SaplingKeyError::InvalidEncoding);
+    }
+
+    let mut key = [0; 32];
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+/// Find the single character that, when replaced, makes the checksum valid.
+///
+/// Returns None if no single replacement does, or if more than one
+/// position could be at fault, since the hint would be misleading.
+fn find_typo(prefix: &str, value: &str) -> Option<usize> {
+    let lowercase = value.to_lowercase();
+    let data_start = lowercase.rfind('1')? + 1;
+    let mut candidate = lowercase.clone().into_bytes();
+    let mut found = None;
+
+    for position in data_start..candidate.len() {
+        let original = candidate[position];
+        for &replacement in CHARSET.iter().filter(|&&c| c != original) {
+            candidate[position] = replacement;
+            let fixed = match std::str::from_utf8(&candidate) {
+                Ok(fixed) => fixed,
+                Err(_) => continue,
+            };
+            if let Ok((found_prefix, _, Variant::Bech32m)) = bech32::decode(fixed) {
+                if found_prefix == prefix {
+                    if found.map_or(false, |found| found != position) {
+                        return None;
+                    }
+                    found = Some(position);
+                }
+            }
+        }
+        candidate[position] = original;
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode, SPENDING_KEY_PREFIX};
+    use crate::errors::SaplingKeyError;
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let key = [7; 32];
+        let encoded = encode(SPENDING_KEY_PREFIX, &key);
+        assert!(encoded.starts_with("ifsecret1"));
+        assert_eq!(decode(SPENDING_KEY_PREFIX, &encoded).unwrap(), key);
+        assert_eq!(
+            decode(SPENDING_KEY_PREFIX, &encoded.to_uppercase()).unwrap(),
+            key
+        );
+    }
+
+    #[test]
+    fn test_typo_hints() {
+        let encoded = encode(SPENDING_KEY_PREFIX, &[7; 32]);
+        let position = encoded.len() - 10;
+
+        let mut typo = encoded.clone().into_bytes();
+        typo[position] = if typo[position] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert!(matches!(
+            decode(SPENDING_KEY_PREFIX, &typo),
+            Err(SaplingKeyError::InvalidChecksum(Some(p))) if p == position
+        ));
+
+        let mut invalid = encoded.clone();
+        invalid.replace_range(position..position + 1, "b");
+        assert!(matches!(
+            decode(SPENDING_KEY_PREFIX, &invalid),
+            Err(SaplingKeyError::InvalidCharacter(p)) if p == position
+        ));
+
+        assert!(matches!(
+            decode("ifincoming", &encoded),
+            Err(SaplingKeyError::WrongKeyPrefix(prefix)) if prefix == SPENDING_KEY_PREFIX
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Derive Iron Fish accounts from existing Zcash Sapling keys.
+//!
+//! The Zcash key isn't used directly: its serialized extended spending key
+//! is hashed into a new Iron Fish spending key, so the two accounts share
+//! no key material and holding the Iron Fish key reveals nothing about the
+//! Zcash one. The same Zcash key always derives the same account.
+
+use super::{errors::SaplingKeyError, SaplingKey};
+use bech32::{FromBase32, Variant};
+use blake2b_simd::Params as Blake2b;
+use zcash_primitives::zip32::ExtendedSpendingKey;
+
+const ZCASH_MIGRATION_PERSONALIZATION: &[u8; 16] = b"Bnzcash_migrate_";
+
+/// Prefix of bech32 encoded Zcash mainnet extended spending keys
+pub const ZCASH_EXTENDED_SPENDING_KEY_PREFIX: &str = "secret-extended-key-main";
+
+impl SaplingKey {
+    /// Derive the Iron Fish key for a Zcash extended spending key
+    pub fn from_zcash_extended_spending_key(
+        zcash_key: &ExtendedSpendingKey,
+    ) -> Result<Self, SaplingKeyError> {
+        let mut serialized = vec![];
+        zcash_key.write(&mut serialized)?;
+
+        let hash = Blake2b::new()
+            .hash_length(32)
+            .personal(ZCASH_MIGRATION_PERSONALIZATION)
+            .hash(&serialized);
+
+        let mut spending_key = [0; 32];
+        spending_key.copy_from_slice(hash.as_bytes());
+        Self::new(spending_key)
+    }
+
+    /// Derive the Iron Fish key for a bech32 encoded Zcash mainnet extended
+    /// spending key, as exported by zcashd with z_exportkey.
+    pub fn from_zcash_encoded(value: &str) -> Result<Self, SaplingKeyError> {
+        let data = match bech32::decode(value) {
+            Ok((prefix, _, _)) if prefix != ZCASH_EXTENDED_SPENDING_KEY_PREFIX => {
+                return Err(SaplingKeyError::WrongKeyPrefix(prefix))
+            }
+            Ok((_, data, Variant::Bech32)) => data,
+            Ok((_, _, Variant::Bech32m)) => return Err(SaplingKeyError::InvalidEncoding),
+            Err(bech32::Error::InvalidChecksum) => {
+                return Err(SaplingKeyError::InvalidChecksum(None))
+            }
+            Err(_) => return Err(SaplingKeyError::InvalidEncoding),
+        };
+
+        let bytes = Vec::<u8>::from_base32(&data).map_err(|_| SaplingKeyError::InvalidEncoding)?;
+        let zcash_key =
+            ExtendedSpendingKey::read(&bytes[..]).map_err(|_| SaplingKeyError::InvalidEncoding)?;
+        Self::from_zcash_extended_spending_key(&zcash_key)
+    }
+}
@@ -4,7 +4,7 @@
 
 use super::errors;
 use super::serializing::{
-    bytes_to_hex, hex_to_bytes, point_to_bytes, read_scalar, scalar_to_bytes,
+    hex_to_secret_bytes, point_to_bytes, read_scalar, scalar_to_bytes, secret_bytes_to_hex,
 };
 use bip39::{Language, Mnemonic};
 use blake2b_simd::Params as Blake2b;
@@ -20,8 +20,16 @@ use zcash_primitives::primitives::{ProofGenerationKey, ViewingKey};
 
 use std::io;
 
+mod deposit_tag;
+pub use deposit_tag::*;
+mod encoding;
+pub use encoding::*;
+mod migrate;
+pub use migrate::*;
 mod public_address;
 pub use public_address::*;
+mod signing;
+pub use signing::*;
 mod view_keys;
 pub use view_keys::*;
 
@@ -115,20 +123,17 @@ impl<'a> SaplingKey {
 
     /// Load a key from a string of hexadecimal digits
     pub fn from_hex(value: &str) -> Result<Self, errors::SaplingKeyError> {
-        match hex_to_bytes(value) {
+        match hex_to_secret_bytes(value) {
             Err(()) => Err(errors::SaplingKeyError::InvalidPaymentAddress),
-            Ok(bytes) => {
-                if bytes.len() != 32 {
-                    Err(errors::SaplingKeyError::InvalidPaymentAddress)
-                } else {
-                    let mut byte_arr = [0; 32];
-                    byte_arr.clone_from_slice(&bytes[0..32]);
-                    Self::new(byte_arr)
-                }
-            }
+            Ok(bytes) => Self::new(bytes),
         }
     }
 
+    /// Load a key encoded with bech32m_spending_key
+    pub fn from_bech32m(value: &str) -> Result<Self, errors::SaplingKeyError> {
+        Self::new(decode(SPENDING_KEY_PREFIX, value)?)
+    }
+
     /// Load a key from a string of words to be decoded into bytes.
     pub fn from_words(language_code: &str, value: String) -> Result<Self, errors::SaplingKeyError> {
         let language = Language::from_language_code(language_code)
@@ -136,8 +141,11 @@ impl<'a> SaplingKey {
         let mnemonic = Mnemonic::from_phrase(&value, language)
             .map_err(|_| errors::SaplingKeyError::InvalidPaymentAddress)?;
         let bytes = mnemonic.entropy();
+        if bytes.len() != 32 {
+            return Err(errors::SaplingKeyError::InvalidWord);
+        }
         let mut byte_arr = [0; 32];
-        byte_arr.clone_from_slice(&bytes[0..32]);
+        byte_arr.clone_from_slice(bytes);
         Self::new(byte_arr)
     }
 
@@ -200,7 +208,13 @@ impl<'a> SaplingKey {
     /// Private spending key as hexadecimal. This is slightly
     /// more human readable.
     pub fn hex_spending_key(&self) -> String {
-        bytes_to_hex(&self.spending_key)
+        secret_bytes_to_hex(&self.spending_key)
+    }
+
+    /// Private spending key as bech32m, which has a checksum to catch
+    /// typos when the key is entered by hand.
+    pub fn bech32m_spending_key(&self) -> String {
+        encode(SPENDING_KEY_PREFIX, &self.spending_key)
     }
 
     /// Private spending key as words. This is even more human readable.
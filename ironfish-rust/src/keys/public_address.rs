@@ -66,6 +66,18 @@ impl PublicAddress {
         Self::from_view_key(sapling_key.incoming_view_key(), diversifier)
     }
 
+    /// Initialize a public address from the authorizing key (`ak`) and
+    /// nullifier deriving key (`nk`) of an account, without building its
+    /// view keys first. See IncomingViewKey::from_parts.
+    pub fn from_components(
+        authorizing_key: &[u8; 32],
+        nullifier_deriving_key: &[u8; 32],
+        diversifier: &[u8; 11],
+    ) -> Result<PublicAddress, errors::SaplingKeyError> {
+        let view_key = IncomingViewKey::from_parts(authorizing_key, nullifier_deriving_key)?;
+        Self::from_view_key(&view_key, diversifier)
+    }
+
     pub fn from_view_key(
         view_key: &IncomingViewKey,
         diversifier: &[u8; 11],
@@ -167,6 +179,20 @@ impl PublicAddress {
         (secret_key, public_key)
     }
 
+    /// Key exchange as in generate_diffie_hellman_keys, with the given
+    /// ephemeral secret key instead of a random one.
+    ///
+    /// This is only for known answer tests of note encryption; an ephemeral
+    /// secret key must never be used for more than one note.
+    #[cfg(any(test, feature = "deterministic-encryption"))]
+    pub fn diffie_hellman_keys_from_secret(
+        &self,
+        secret_key: jubjub::Fr,
+    ) -> (jubjub::Fr, SubgroupPoint) {
+        let public_key = self.diversifier_point * secret_key;
+        (secret_key, public_key)
+    }
+
     /// Convert this key to a payment address for use in the zcash_primitives
     /// crate. This is essentially just an adapter from one struct name to
     /// another because `pk_d` is not a name I want to expose in a public
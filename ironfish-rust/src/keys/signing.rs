@@ -0,0 +1,161 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{errors, PublicAddress, SaplingKey};
+use crate::serializing::{read_canonical_point, read_scalar};
+use blake2b_simd::Params as Blake2b;
+use group::GroupEncoding;
+use jubjub::ExtendedPoint;
+use rand::{thread_rng, Rng};
+use zcash_primitives::constants::SPENDING_KEY_GENERATOR;
+use zcash_primitives::redjubjub::{PrivateKey, PublicKey, Signature};
+
+use std::io;
+
+/// Keeps message signatures from being valid for anything else signed with
+/// the same keys.
+const MESSAGE_SIGNATURE_PERSONALIZATION: &[u8; 16] = b"Bnaddress_signed";
+
+/// A signature over an arbitrary message made with the spend authorizing key
+/// of a public address, without creating a transaction.
+///
+/// As in a spend, the message is signed with a randomized spend authorizing
+/// key. The signature carries the randomizer and the nullifier deriving key,
+/// so a verifier can recover the authorizing key and check that it and the
+/// nullifier deriving key derive the address. Only the holder of the
+/// spending key can produce one.
+///
+/// The authorizing and nullifier deriving keys make up the address's
+/// incoming view key, so anyone with a signature can read the notes sent to
+/// the address and see when they're spent.
+#[derive(Clone, Copy)]
+pub struct AddressSignature {
+    randomized_public_key: [u8; 32],
+    public_key_randomness: [u8; 32],
+    nullifier_deriving_key: [u8; 32],
+    signature: Signature,
+}
+
+impl AddressSignature {
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let mut randomized_public_key = [0; 32];
+        reader.read_exact(&mut randomized_public_key)?;
+        let mut public_key_randomness = [0; 32];
+        reader.read_exact(&mut public_key_randomness)?;
+        let mut nullifier_deriving_key = [0; 32];
+        reader.read_exact(&mut nullifier_deriving_key)?;
+        let signature = Signature::read(reader)?;
+
+        Ok(AddressSignature {
+            randomized_public_key,
+            public_key_randomness,
+            nullifier_deriving_key,
+            signature,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.randomized_public_key)?;
+        writer.write_all(&self.public_key_randomness)?;
+        writer.write_all(&self.nullifier_deriving_key)?;
+        self.signature.write(writer)
+    }
+}
+
+impl SaplingKey {
+    /// Sign the message to prove ownership of an address generated from this
+    /// key. Fails with InvalidPublicAddress if the address is from another
+    /// key.
+    pub fn sign_message(
+        &self,
+        address: &PublicAddress,
+        message: &[u8],
+    ) -> Result<AddressSignature, errors::SaplingKeyError> {
+        if address.diversifier_point * self.incoming_viewing_key.view_key
+            != address.transmission_key
+        {
+            return Err(errors::SaplingKeyError::InvalidPublicAddress);
+        }
+
+        let mut buffer = [0u8; 64];
+        thread_rng().fill(&mut buffer[..]);
+        let public_key_randomness = jubjub::Fr::from_bytes_wide(&buffer);
+
+        let private_key = PrivateKey(self.spend_authorizing_key).randomize(public_key_randomness);
+        let randomized_public_key = PublicKey::from_private(&private_key, SPENDING_KEY_GENERATOR);
+        let signature = private_key.sign(
+            &signature_data(&randomized_public_key.0, address, message),
+            &mut thread_rng(),
+            SPENDING_KEY_GENERATOR,
+        );
+
+        Ok(AddressSignature {
+            randomized_public_key: randomized_public_key.0.to_bytes(),
+            public_key_randomness: public_key_randomness.to_bytes(),
+            nullifier_deriving_key: self.nullifier_deriving_key.to_bytes(),
+            signature,
+        })
+    }
+}
+
+impl PublicAddress {
+    /// Check a signature made with SaplingKey::sign_message for this address.
+    pub fn verify_message(&self, message: &[u8], signature: &AddressSignature) -> bool {
+        self.check_message_signature(message, signature).is_ok()
+    }
+
+    fn check_message_signature(
+        &self,
+        message: &[u8],
+        signature: &AddressSignature,
+    ) -> Result<(), errors::SaplingKeyError> {
+        let randomized_public_key: ExtendedPoint =
+            read_canonical_point(&signature.randomized_public_key[..])
+                .map_err(|_| errors::SaplingKeyError::InvalidPublicAddress)?;
+        let public_key_randomness: jubjub::Fr = read_scalar(&signature.public_key_randomness[..])?;
+
+        // Undo the randomization to find the authorizing key the signer
+        // claims, which has to derive this address
+        let authorizing_key = randomized_public_key
+            - ExtendedPoint::from(SPENDING_KEY_GENERATOR * public_key_randomness);
+        let address = PublicAddress::from_components(
+            &authorizing_key.to_bytes(),
+            &signature.nullifier_deriving_key,
+            &self.diversifier.0,
+        )?;
+        if address != *self {
+            return Err(errors::SaplingKeyError::InvalidPublicAddress);
+        }
+
+        if !PublicKey(randomized_public_key).verify(
+            &signature_data(&randomized_public_key, self, message),
+            &signature.signature,
+            SPENDING_KEY_GENERATOR,
+        ) {
+            return Err(errors::SaplingKeyError::InvalidPublicAddress);
+        }
+        Ok(())
+    }
+}
+
+/// The data that is signed: the randomized public key followed by a hash
+/// binding the message to the address, the same layout spends sign.
+fn signature_data(
+    randomized_public_key: &ExtendedPoint,
+    address: &PublicAddress,
+    message: &[u8],
+) -> [u8; 64] {
+    let hash = Blake2b::new()
+        .hash_length(32)
+        .personal(MESSAGE_SIGNATURE_PERSONALIZATION)
+        .to_state()
+        .update(&address.public_address())
+        .update(message)
+        .finalize();
+
+    let mut data = [0; 64];
+    data[..32].copy_from_slice(&randomized_public_key.to_bytes());
+    data[32..].copy_from_slice(hash.as_bytes());
+    data
+}
@@ -2,7 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use super::{shared_secret, PublicAddress, SaplingKey};
+use super::{
+    errors::SaplingKeyError, shared_secret, AddressSignature, DepositTag, IncomingViewKey,
+    OutgoingViewKey, PublicAddress, SaplingKey, ZCASH_EXTENDED_SPENDING_KEY_PREFIX,
+};
+use crate::note::Memo;
 use group::Curve;
 use jubjub::ExtendedPoint;
 
@@ -88,3 +92,157 @@ fn test_hex_conversion() {
 
     assert!(PublicAddress::from_hex("invalid").is_err());
 }
+
+#[test]
+fn test_bech32m_conversion() {
+    let key: SaplingKey = SaplingKey::generate_key();
+
+    let encoded = key.bech32m_spending_key();
+    let second_key = SaplingKey::from_bech32m(&encoded).unwrap();
+    assert_eq!(second_key.spending_key, key.spending_key);
+
+    let incoming = key.incoming_view_key().bech32m_key();
+    let second_incoming = IncomingViewKey::from_bech32m(&incoming).unwrap();
+    assert_eq!(second_incoming.view_key, key.incoming_view_key().view_key);
+
+    let outgoing = key.outgoing_view_key().bech32m_key();
+    let second_outgoing = OutgoingViewKey::from_bech32m(&outgoing).unwrap();
+    assert_eq!(second_outgoing.view_key, key.outgoing_view_key().view_key);
+
+    // Each kind of key only loads from its own encoding
+    assert!(SaplingKey::from_bech32m(&incoming).is_err());
+    assert!(IncomingViewKey::from_bech32m(&outgoing).is_err());
+    assert!(OutgoingViewKey::from_bech32m(&encoded).is_err());
+}
+
+#[test]
+fn test_message_signing() {
+    let key: SaplingKey = SaplingKey::generate_key();
+    let address = key.generate_public_address();
+    let message = b"I own this address";
+
+    let signature = key
+        .sign_message(&address, message)
+        .expect("should sign for own address");
+    assert!(address.verify_message(message, &signature));
+    assert!(!address.verify_message(b"something else", &signature));
+
+    let mut serialized = vec![];
+    signature.write(&mut serialized).unwrap();
+    assert_eq!(serialized.len(), 160);
+    let read_back = AddressSignature::read(&serialized[..]).unwrap();
+    assert!(address.verify_message(message, &read_back));
+
+    // Signatures are tied to the address they were made for
+    let other_address = key.generate_public_address();
+    assert!(!other_address.verify_message(message, &signature));
+
+    let other_key = SaplingKey::generate_key();
+    assert!(other_key.sign_message(&address, message).is_err());
+
+    // The keys in the signature have to derive the address
+    let mut forged = serialized.clone();
+    forged[64..96].copy_from_slice(&other_key.nullifier_deriving_key());
+    let forged = AddressSignature::read(&forged[..]).unwrap();
+    assert!(!address.verify_message(message, &forged));
+
+    // A signature by another key can't be passed off with this key's parts
+    let other_address = other_key.generate_public_address();
+    let mut forged = vec![];
+    other_key
+        .sign_message(&other_address, message)
+        .unwrap()
+        .write(&mut forged)
+        .unwrap();
+    forged[64..96].copy_from_slice(&serialized[64..96]);
+    let forged = AddressSignature::read(&forged[..]).unwrap();
+    assert!(!address.verify_message(message, &forged));
+}
+
+#[test]
+fn test_deposit_tag() {
+    let exchange_key: SaplingKey = SaplingKey::generate_key();
+    let incoming = exchange_key.incoming_view_key();
+
+    let tag = incoming.deposit_tag(42);
+    assert_eq!(tag.user_id(), 42);
+    assert!(incoming.verify_deposit_tag(&tag));
+    assert_eq!(DepositTag::from_bytes(&tag.to_bytes()), tag);
+    assert_eq!(DepositTag::from_memo(&tag.memo()), Some(tag));
+    assert_eq!(DepositTag::from_memo(&Memo::from("not a tag")), None);
+
+    // Tags can't be made for other users, or by other keys
+    let mut forged = tag.to_bytes();
+    forged[0] = 43;
+    assert!(!incoming.verify_deposit_tag(&DepositTag::from_bytes(&forged)));
+
+    let other_key = SaplingKey::generate_key();
+    assert!(!other_key.incoming_view_key().verify_deposit_tag(&tag));
+}
+
+#[test]
+fn test_zcash_migration() {
+    use bech32::{ToBase32, Variant};
+    use zcash_primitives::zip32::ExtendedSpendingKey;
+
+    // The master key for the first ZIP 32 test vector seed
+    let seed: Vec<u8> = (0..32).collect();
+    let zcash_key = ExtendedSpendingKey::master(&seed);
+
+    let key = SaplingKey::from_zcash_extended_spending_key(&zcash_key).unwrap();
+    assert_eq!(
+        key.hex_spending_key(),
+        "ca9853e88afc7b26c76c223f8f01e6830a77ee6db37a2c8f461231d81183ef7c"
+    );
+
+    let zero_seed_key =
+        SaplingKey::from_zcash_extended_spending_key(&ExtendedSpendingKey::master(&[0; 32]))
+            .unwrap();
+    assert_eq!(
+        zero_seed_key.hex_spending_key(),
+        "466a899d8d925bead63c9927c9e4c03b3d60c1d5aac20c15ea2f612171cd549a"
+    );
+
+    let mut serialized = vec![];
+    zcash_key.write(&mut serialized).unwrap();
+    let encoded = bech32::encode(
+        ZCASH_EXTENDED_SPENDING_KEY_PREFIX,
+        serialized.to_base32(),
+        Variant::Bech32,
+    )
+    .unwrap();
+    let decoded = SaplingKey::from_zcash_encoded(&encoded).unwrap();
+    assert_eq!(decoded.hex_spending_key(), key.hex_spending_key());
+
+    assert!(matches!(
+        SaplingKey::from_zcash_encoded(&key.bech32m_spending_key()),
+        Err(SaplingKeyError::WrongKeyPrefix(_))
+    ));
+}
+
+#[test]
+fn test_view_key_from_parts() {
+    use group::GroupEncoding;
+
+    let key: SaplingKey = SaplingKey::generate_key();
+    let authorizing_key = key.authorizing_key.to_bytes();
+    let nullifier_deriving_key = key.nullifier_deriving_key.to_bytes();
+
+    let incoming = IncomingViewKey::from_parts(&authorizing_key, &nullifier_deriving_key).unwrap();
+    assert_eq!(incoming.hex_key(), key.incoming_view_key().hex_key());
+
+    let address = key.generate_public_address();
+    let from_components = PublicAddress::from_components(
+        &authorizing_key,
+        &nullifier_deriving_key,
+        &address.diversifier.0,
+    )
+    .unwrap();
+    assert_eq!(from_components.public_address(), address.public_address());
+
+    // The identity and points that aren't on the curve are rejected
+    let mut identity = [0; 32];
+    identity[0] = 1;
+    assert!(IncomingViewKey::from_parts(&identity, &nullifier_deriving_key).is_err());
+    assert!(IncomingViewKey::from_parts(&authorizing_key, &[0xff; 32]).is_err());
+}
@@ -12,12 +12,17 @@
 //! that you have spent.
 //!
 
-use super::{errors, PublicAddress};
+use super::{
+    decode, encode, errors, PublicAddress, SaplingKey, INCOMING_VIEW_KEY_PREFIX,
+    OUTGOING_VIEW_KEY_PREFIX,
+};
 use crate::serializing::{
-    bytes_to_hex, hex_to_bytes, point_to_bytes, read_scalar, scalar_to_bytes,
+    hex_to_secret_bytes, point_to_bytes, read_canonical_point, read_scalar, scalar_to_bytes,
+    secret_bytes_to_hex,
 };
 use bip39::{Language, Mnemonic};
 use blake2b_simd::Params as Blake2b;
+use group::Group;
 use jubjub::SubgroupPoint;
 use rand::{thread_rng, Rng};
 
@@ -25,6 +30,8 @@ use std::io;
 
 const DIFFIE_HELLMAN_PERSONALIZATION: &[u8; 16] = b"Beanstalk shared";
 
+const OUTGOING_VIEW_KEY_EPOCH_PERSONALIZATION: &[u8; 16] = b"Bnovk_epoch_key_";
+
 /// Key that allows someone to view a transaction that you have received.
 ///
 /// Referred to as `ivk` in the literature.
@@ -42,18 +49,34 @@ impl IncomingViewKey {
 
     /// Load a key from a string of hexadecimal digits
     pub fn from_hex(value: &str) -> Result<Self, errors::SaplingKeyError> {
-        match hex_to_bytes(value) {
+        match hex_to_secret_bytes(value) {
             Err(()) => Err(errors::SaplingKeyError::InvalidViewingKey),
-            Ok(bytes) => {
-                if bytes.len() != 32 {
-                    Err(errors::SaplingKeyError::InvalidViewingKey)
-                } else {
-                    Self::read(&mut bytes[..].as_ref())
-                }
-            }
+            Ok(bytes) => Self::read(&mut bytes[..].as_ref()),
         }
     }
 
+    /// Load a key encoded with bech32m_key
+    pub fn from_bech32m(value: &str) -> Result<Self, errors::SaplingKeyError> {
+        let bytes = decode(INCOMING_VIEW_KEY_PREFIX, value)?;
+        Self::read(&mut bytes[..].as_ref())
+    }
+
+    /// Derive the key from the authorizing key (`ak`) and nullifier deriving
+    /// key (`nk`), such as those produced by a key generation ceremony held
+    /// outside of this library.
+    ///
+    /// Both must be canonical encodings of points in the prime order
+    /// subgroup, other than the identity.
+    pub fn from_parts(
+        authorizing_key: &[u8; 32],
+        nullifier_deriving_key: &[u8; 32],
+    ) -> Result<Self, errors::SaplingKeyError> {
+        let authorizing_key = read_key_part(authorizing_key)?;
+        let nullifier_deriving_key = read_key_part(nullifier_deriving_key)?;
+        let view_key = SaplingKey::hash_viewing_key(&authorizing_key, &nullifier_deriving_key)?;
+        Ok(IncomingViewKey { view_key })
+    }
+
     /// Load a key from a string of words to be decoded into bytes.
     ///
     /// See https://github.com/BeanstalkNetwork/word-encoding
@@ -63,14 +86,22 @@ impl IncomingViewKey {
         let mnemonic = Mnemonic::from_phrase(&value, language)
             .map_err(|_| errors::SaplingKeyError::InvalidPaymentAddress)?;
         let bytes = mnemonic.entropy();
+        if bytes.len() != 32 {
+            return Err(errors::SaplingKeyError::InvalidWord);
+        }
         let mut byte_arr = [0; 32];
-        byte_arr.clone_from_slice(&bytes[0..32]);
+        byte_arr.clone_from_slice(bytes);
         Self::read(&mut byte_arr[..].as_ref())
     }
 
     /// Viewing key as hexadecimal, for readability.
     pub fn hex_key(&self) -> String {
-        bytes_to_hex(&scalar_to_bytes(&self.view_key))
+        secret_bytes_to_hex(&scalar_to_bytes(&self.view_key))
+    }
+
+    /// Viewing key as bech32m, with a checksum to catch typos.
+    pub fn bech32m_key(&self) -> String {
+        encode(INCOMING_VIEW_KEY_PREFIX, &scalar_to_bytes(&self.view_key))
     }
 
     /// Even more readable
@@ -121,6 +152,16 @@ impl IncomingViewKey {
     }
 }
 
+/// Read a point that a view key is derived from, rejecting the identity
+fn read_key_part(bytes: &[u8; 32]) -> Result<SubgroupPoint, errors::SaplingKeyError> {
+    let point: SubgroupPoint =
+        read_canonical_point(&bytes[..]).map_err(|_| errors::SaplingKeyError::InvalidViewingKey)?;
+    if point.is_identity().into() {
+        return Err(errors::SaplingKeyError::InvalidViewingKey);
+    }
+    Ok(point)
+}
+
 /// Key that allows someone to view a transaction that you have spent.
 ///
 /// Referred to as `ovk` in the literature.
@@ -132,20 +173,18 @@ pub struct OutgoingViewKey {
 impl OutgoingViewKey {
     /// Load a key from a string of hexadecimal digits
     pub fn from_hex(value: &str) -> Result<Self, errors::SaplingKeyError> {
-        match hex_to_bytes(value) {
+        match hex_to_secret_bytes(value) {
             Err(()) => Err(errors::SaplingKeyError::InvalidViewingKey),
-            Ok(bytes) => {
-                if bytes.len() != 32 {
-                    Err(errors::SaplingKeyError::InvalidViewingKey)
-                } else {
-                    let mut view_key = [0; 32];
-                    view_key.clone_from_slice(&bytes[0..32]);
-                    Ok(Self { view_key })
-                }
-            }
+            Ok(view_key) => Ok(Self { view_key }),
         }
     }
 
+    /// Load a key encoded with bech32m_key
+    pub fn from_bech32m(value: &str) -> Result<Self, errors::SaplingKeyError> {
+        let view_key = decode(OUTGOING_VIEW_KEY_PREFIX, value)?;
+        Ok(Self { view_key })
+    }
+
     /// Load a key from a string of words to be decoded into bytes.
     ///
     /// See https://github.com/BeanstalkNetwork/word-encoding
@@ -155,14 +194,42 @@ impl OutgoingViewKey {
         let mnemonic = Mnemonic::from_phrase(&value, language)
             .map_err(|_| errors::SaplingKeyError::InvalidPaymentAddress)?;
         let bytes = mnemonic.entropy();
+        if bytes.len() != 32 {
+            return Err(errors::SaplingKeyError::InvalidWord);
+        }
         let mut view_key = [0; 32];
-        view_key.clone_from_slice(&bytes[0..32]);
+        view_key.clone_from_slice(bytes);
         Ok(Self { view_key })
     }
 
     /// Viewing key as hexadecimal, for readability.
     pub fn hex_key(&self) -> String {
-        bytes_to_hex(&self.view_key)
+        secret_bytes_to_hex(&self.view_key)
+    }
+
+    /// Viewing key as bech32m, with a checksum to catch typos.
+    pub fn bech32m_key(&self) -> String {
+        encode(OUTGOING_VIEW_KEY_PREFIX, &self.view_key)
+    }
+
+    /// Derive the outgoing view key for an epoch.
+    ///
+    /// Notes encrypted with an epoch key can only be recovered with that
+    /// epoch key, which this key can derive, so handing out or leaking an
+    /// epoch key only exposes the notes sent during that epoch. Epoch keys
+    /// can't be used to derive each other.
+    pub fn for_epoch(&self, epoch: u32) -> OutgoingViewKey {
+        let hash = Blake2b::new()
+            .hash_length(32)
+            .personal(OUTGOING_VIEW_KEY_EPOCH_PERSONALIZATION)
+            .to_state()
+            .update(&self.view_key)
+            .update(&epoch.to_le_bytes())
+            .finalize();
+
+        let mut view_key = [0; 32];
+        view_key.copy_from_slice(hash.as_bytes());
+        OutgoingViewKey { view_key }
     }
 
     /// Even more readable
@@ -10,27 +10,42 @@ use bls12_381::Bls12;
 
 mod serializing;
 
+pub mod circuit_metadata;
+pub mod compact_block;
+pub mod consensus;
 pub mod errors;
+pub mod genesis;
 pub mod keys;
 pub mod merkle_note;
 pub mod merkle_note_hash;
 pub mod mining;
+pub mod network;
 pub mod note;
+pub mod positioned_note;
 pub mod receiving;
+pub mod scanning;
 pub mod spending;
+pub mod storage;
 pub mod transaction;
+pub mod tree_snapshot;
+pub mod value_commitment_opening;
+pub mod wallet;
 pub mod witness;
 pub use {
     keys::{IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey, ViewKeys},
     merkle_note::MerkleNote,
     merkle_note_hash::MerkleNoteHash,
     note::Note,
+    positioned_note::PositionedNote,
     receiving::{ReceiptParams, ReceiptProof},
     spending::{SpendParams, SpendProof},
     transaction::{ProposedTransaction, Transaction},
 };
 pub mod sapling_bls12;
 
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+
 #[cfg(test)]
 pub(crate) mod test_util; // I'm not sure if this is the right way to publish the utility library.
 
@@ -39,16 +54,31 @@ pub(crate) mod test_util; // I'm not sure if this is the right way to publish th
 //
 // spend and output are two arithmetic circuits for use in zksnark calculations provided by Bellman.
 // Though the *_params have a verifying key on them, they are not the prepared verifying keys,
-// so we store the prepared keys separately at the time of loading the params.
+// so we store the prepared keys separately at the time of loading the params. The unprepared
+// keys are kept too, since batch verification needs their individual elements.
 //
 // The values are all loaded from a file in serialized form.
+//
+// A Sapling loaded with load_verifier only has the verifying keys, and fails to create proofs.
 pub struct Sapling {
-    spend_params: groth16::Parameters<Bls12>,
-    receipt_params: groth16::Parameters<Bls12>,
+    spend_params: Option<groth16::Parameters<Bls12>>,
+    receipt_params: Option<groth16::Parameters<Bls12>>,
     spend_verifying_key: groth16::PreparedVerifyingKey<Bls12>,
     receipt_verifying_key: groth16::PreparedVerifyingKey<Bls12>,
+    spend_vk: groth16::VerifyingKey<Bls12>,
+    receipt_vk: groth16::VerifyingKey<Bls12>,
 }
 
+/// The serialized verifying key for the spend circuit, as found at the start of
+/// sapling-spend.params.
+#[cfg(feature = "embedded-verifying-keys")]
+pub const SPEND_VERIFYING_KEY_BYTES: &[u8] = include_bytes!("sapling_params/sapling-spend.vk");
+
+/// The serialized verifying key for the output circuit, as found at the start of
+/// sapling-output.params.
+#[cfg(feature = "embedded-verifying-keys")]
+pub const RECEIPT_VERIFYING_KEY_BYTES: &[u8] = include_bytes!("sapling_params/sapling-output.vk");
+
 impl Sapling {
     /// Initialize a Sapling instance and prepare for proving. Load the parameters from a config file
     /// at a known location (`./sapling_params`, for now).
@@ -61,17 +91,63 @@ impl Sapling {
         let spend_params = Sapling::load_params(&spend_bytes[..]);
         let receipt_params = Sapling::load_params(&receipt_bytes[..]);
 
-        let spend_vk = groth16::prepare_verifying_key(&spend_params.vk);
-        let receipt_vk = groth16::prepare_verifying_key(&receipt_params.vk);
+        #[cfg(feature = "embedded-verifying-keys")]
+        let (spend_vk, receipt_vk) = Sapling::load_embedded_verifying_keys();
+
+        #[cfg(not(feature = "embedded-verifying-keys"))]
+        let (spend_vk, receipt_vk) = (spend_params.vk.clone(), receipt_params.vk.clone());
 
         Sapling {
-            spend_verifying_key: spend_vk,
-            receipt_verifying_key: receipt_vk,
-            spend_params,
-            receipt_params,
+            spend_verifying_key: groth16::prepare_verifying_key(&spend_vk),
+            receipt_verifying_key: groth16::prepare_verifying_key(&receipt_vk),
+            spend_vk,
+            receipt_vk,
+            spend_params: Some(spend_params),
+            receipt_params: Some(receipt_params),
         }
     }
 
+    /// Initialize a Sapling instance that can only verify proofs, using the verifying keys
+    /// compiled into the binary. This is fast and doesn't touch the proving parameters, so
+    /// verification-only consumers like explorers don't pay for loading or shipping them.
+    ///
+    /// Creating spend or receipt proofs with the returned instance fails with
+    /// SaplingProofError::MissingProvingParameters.
+    #[cfg(feature = "embedded-verifying-keys")]
+    pub fn load_verifier() -> Self {
+        let (spend_vk, receipt_vk) = Sapling::load_embedded_verifying_keys();
+
+        Sapling {
+            spend_verifying_key: groth16::prepare_verifying_key(&spend_vk),
+            receipt_verifying_key: groth16::prepare_verifying_key(&receipt_vk),
+            spend_vk,
+            receipt_vk,
+            spend_params: None,
+            receipt_params: None,
+        }
+    }
+
+    /// Whether this instance can create proofs, or only verify them.
+    pub fn can_prove(&self) -> bool {
+        self.spend_params.is_some() && self.receipt_params.is_some()
+    }
+
+    pub(crate) fn spend_params(
+        &self,
+    ) -> Result<&groth16::Parameters<Bls12>, errors::SaplingProofError> {
+        self.spend_params
+            .as_ref()
+            .ok_or(errors::SaplingProofError::MissingProvingParameters)
+    }
+
+    pub(crate) fn receipt_params(
+        &self,
+    ) -> Result<&groth16::Parameters<Bls12>, errors::SaplingProofError> {
+        self.receipt_params
+            .as_ref()
+            .ok_or(errors::SaplingProofError::MissingProvingParameters)
+    }
+
     /// Load sapling parameters from a provided filename. The parameters are huge and take a
     /// couple seconds to load. They primarily contain the "toxic waste" for a specific sapling
     /// curve.
@@ -80,4 +156,13 @@ impl Sapling {
     fn load_params(bytes: &[u8]) -> groth16::Parameters<Bls12> {
         groth16::Parameters::read(bytes, false).unwrap()
     }
+
+    #[cfg(feature = "embedded-verifying-keys")]
+    fn load_embedded_verifying_keys() -> (groth16::VerifyingKey<Bls12>, groth16::VerifyingKey<Bls12>)
+    {
+        (
+            groth16::VerifyingKey::read(SPEND_VERIFYING_KEY_BYTES).unwrap(),
+            groth16::VerifyingKey::read(RECEIPT_VERIFYING_KEY_BYTES).unwrap(),
+        )
+    }
 }
@@ -8,7 +8,8 @@ use super::{
     errors,
     keys::{shared_secret, IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey},
     note::{Note, ENCRYPTED_NOTE_SIZE},
-    serializing::{aead, read_scalar},
+    positioned_note::PositionedNote,
+    serializing::{aead, read_canonical_point, read_scalar},
     witness::{WitnessNode, WitnessTrait},
     MerkleNoteHash,
 };
@@ -73,6 +74,22 @@ impl MerkleNote {
         note: &Note,
         value_commitment: &ValueCommitment,
         diffie_hellman_keys: &(jubjub::Fr, SubgroupPoint),
+    ) -> MerkleNote {
+        MerkleNote::for_outgoing_view_key(
+            spender_key.outgoing_view_key(),
+            note,
+            value_commitment,
+            diffie_hellman_keys,
+        )
+    }
+
+    /// Create a merkle note as in new, given only the outgoing view key the
+    /// note should be recoverable with instead of the spender's whole key.
+    pub(crate) fn for_outgoing_view_key(
+        outgoing_view_key: &OutgoingViewKey,
+        note: &Note,
+        value_commitment: &ValueCommitment,
+        diffie_hellman_keys: &(jubjub::Fr, SubgroupPoint),
     ) -> MerkleNote {
         let (secret_key, public_key) = diffie_hellman_keys;
 
@@ -87,7 +104,7 @@ impl MerkleNote {
         key_bytes[32..].clone_from_slice(secret_key.to_repr().as_ref());
 
         let encryption_key = calculate_key_for_encryption_keys(
-            spender_key.outgoing_view_key(),
+            outgoing_view_key,
             &value_commitment.commitment().into(),
             &note.commitment_point(),
             public_key,
@@ -105,39 +122,13 @@ impl MerkleNote {
     }
 
     /// Load a MerkleNote from the given stream
-    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
-        let value_commitment = {
-            let mut bytes = [0; 32];
-            reader.read_exact(&mut bytes)?;
-            let point = ExtendedPoint::from_bytes(&bytes);
-            if point.is_none().into() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Unable to convert note commitment",
-                ));
-            }
-            point.unwrap()
-        };
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::EncodingError> {
+        let value_commitment = read_canonical_point(&mut reader)?;
 
-        let note_commitment = read_scalar(&mut reader).map_err(|_| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Unable to convert note commitment",
-            )
-        })?;
-
-        let ephemeral_public_key = {
-            let mut bytes = [0; 32];
-            reader.read_exact(&mut bytes)?;
-            let point = SubgroupPoint::from_bytes(&bytes);
-            if point.is_none().into() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Unable to convert note commitment",
-                ));
-            }
-            point.unwrap()
-        };
+        let note_commitment =
+            read_scalar(&mut reader).map_err(|_| errors::EncodingError::InvalidEncoding)?;
+
+        let ephemeral_public_key = read_canonical_point(&mut reader)?;
 
         let mut encrypted_note = [0; ENCRYPTED_NOTE_SIZE + aead::MAC_SIZE];
         reader.read_exact(&mut encrypted_note[..])?;
@@ -176,10 +167,88 @@ impl MerkleNote {
         Ok(note)
     }
 
+    /// Decrypt the note for its owner, keeping track of where this merkle
+    /// note is in the note commitment tree.
+    pub fn decrypt_positioned_note_for_owner(
+        &self,
+        owner_view_key: &IncomingViewKey,
+        position: u64,
+    ) -> Result<PositionedNote, errors::NoteError> {
+        let note = self.decrypt_note_for_owner(owner_view_key)?;
+        Ok(PositionedNote::new(note, position))
+    }
+
     pub fn decrypt_note_for_spender(
         &self,
         spender_key: &OutgoingViewKey,
     ) -> Result<Note, errors::NoteError> {
+        let (transmission_key, shared_key) = self.decrypt_encryption_keys(spender_key)?;
+        let note =
+            Note::from_spender_encrypted(transmission_key, &shared_key, &self.encrypted_note)?;
+        note.verify_commitment(self.note_commitment)?;
+        Ok(note)
+    }
+
+    /// Decrypt a note created with an epoch outgoing view key, trying the
+    /// epoch keys of the account's outgoing view key in turn. Returns the
+    /// epoch the note was encrypted for along with the note.
+    ///
+    /// Every epoch costs a trial decryption, so callers should pass a small
+    /// window of epochs around when the note was sent.
+    pub fn decrypt_note_for_spender_in_epochs(
+        &self,
+        spender_key: &OutgoingViewKey,
+        epochs: impl IntoIterator<Item = u32>,
+    ) -> Result<(u32, Note), errors::NoteError> {
+        for epoch in epochs {
+            if let Ok(note) = self.decrypt_note_for_spender(&spender_key.for_epoch(epoch)) {
+                return Ok((epoch, note));
+            }
+        }
+
+        Err(errors::NoteError::KeyError)
+    }
+
+    /// Recover the shared secret used to encrypt this note, given the
+    /// outgoing view key of the account that created it.
+    ///
+    /// The sender can hand the shared secret and this merkle note to the
+    /// recipient, who can then decrypt the note with
+    /// decrypt_note_with_shared_secret without scanning the chain. The secret
+    /// only decrypts this note.
+    pub fn note_encryption_key_for_spender(
+        &self,
+        spender_key: &OutgoingViewKey,
+    ) -> Result<[u8; 32], errors::NoteError> {
+        let (_, shared_key) = self.decrypt_encryption_keys(spender_key)?;
+        Ok(shared_key)
+    }
+
+    /// Calculate the shared secret used to encrypt this note, given the
+    /// incoming view key of its owner. Nothing is checked, so the result is
+    /// only meaningful if the note belongs to the key.
+    pub fn note_encryption_key_for_owner(&self, owner_view_key: &IncomingViewKey) -> [u8; 32] {
+        owner_view_key.shared_secret(&self.ephemeral_public_key)
+    }
+
+    /// Decrypt the note with a shared secret exported by its sender, given
+    /// the public address it was sent to.
+    pub fn decrypt_note_with_shared_secret(
+        &self,
+        owner: &PublicAddress,
+        shared_secret: &[u8; 32],
+    ) -> Result<Note, errors::NoteError> {
+        let note = Note::decrypt_with_shared_secret(owner, shared_secret, &self.encrypted_note)?;
+        note.verify_commitment(self.note_commitment)?;
+        Ok(note)
+    }
+
+    /// Decrypt the keys the spender stored on the note, returning the
+    /// transmission key of the owner and the shared secret.
+    fn decrypt_encryption_keys(
+        &self,
+        spender_key: &OutgoingViewKey,
+    ) -> Result<(SubgroupPoint, [u8; 32]), errors::NoteError> {
         let encryption_key = calculate_key_for_encryption_keys(
             spender_key,
             &self.value_commitment,
@@ -197,10 +266,7 @@ impl MerkleNote {
         let transmission_key = PublicAddress::load_transmission_key(&note_encryption_keys[..32])?;
         let secret_key = read_scalar(&note_encryption_keys[32..])?;
         let shared_key = shared_secret(&secret_key, &transmission_key, &self.ephemeral_public_key);
-        let note =
-            Note::from_spender_encrypted(transmission_key, &shared_key, &self.encrypted_note)?;
-        note.verify_commitment(self.note_commitment)?;
-        Ok(note)
+        Ok((transmission_key, shared_key))
     }
 }
 
@@ -268,7 +334,7 @@ fn calculate_key_for_encryption_keys(
 mod test {
     use super::MerkleNote;
     use crate::{
-        keys::SaplingKey,
+        keys::{shared_secret, PublicAddress, SaplingKey},
         note::{Memo, Note},
     };
 
@@ -339,4 +405,92 @@ mod test {
             .decrypt_note_for_spender(spender_key.outgoing_view_key())
             .is_err());
     }
+
+    #[test]
+    fn test_deterministic_encryption() {
+        let spender_key = SaplingKey::new([7; 32]).expect("should create key");
+        let owner = PublicAddress::from_hex(
+            "6b23f9d6a1a8560c0a91f7a605b88bbb6322181870fc931d170bc27b163781117eac496fc5e1697ff164ae",
+        )
+        .expect("should load public address");
+        let note = Note::with_randomness(owner, 42, "known answer".into(), jubjub::Fr::from(3));
+        let diffie_hellman_keys = note
+            .owner
+            .diffie_hellman_keys_from_secret(jubjub::Fr::from(5));
+        let value_commitment = ValueCommitment {
+            value: note.value,
+            randomness: jubjub::Fr::from(11),
+        };
+
+        let write = || {
+            let merkle_note =
+                MerkleNote::new(&spender_key, &note, &value_commitment, &diffie_hellman_keys);
+            let mut bytes = vec![];
+            merkle_note.write(&mut bytes).expect("should serialize");
+            (merkle_note, bytes)
+        };
+        let (merkle_note, bytes) = write();
+        assert_eq!(bytes, write().1);
+
+        let shared_secret = shared_secret(
+            &diffie_hellman_keys.0,
+            &note.owner.transmission_key,
+            &diffie_hellman_keys.1,
+        );
+        assert_eq!(
+            &merkle_note.encrypted_note[..],
+            &note.encrypt(&shared_secret)[..]
+        );
+        let decrypted = merkle_note
+            .decrypt_note_for_spender(spender_key.outgoing_view_key())
+            .expect("should be able to decrypt note");
+        assert_eq!(decrypted.randomness, note.randomness);
+    }
+
+    #[test]
+    fn test_shared_secret_export() {
+        let spender_key: SaplingKey = SaplingKey::generate_key();
+        let receiver_key: SaplingKey = SaplingKey::generate_key();
+        let receiver_address = receiver_key.generate_public_address();
+        let note = Note::new(receiver_address.clone(), 42, Memo::from("out of band"));
+        let diffie_hellman_keys = note.owner.generate_diffie_hellman_keys();
+
+        let mut buffer = [0u8; 64];
+        thread_rng().fill(&mut buffer[..]);
+
+        let value_commitment = ValueCommitment {
+            value: note.value,
+            randomness: jubjub::Fr::from_bytes_wide(&buffer),
+        };
+
+        let merkle_note =
+            MerkleNote::new(&spender_key, &note, &value_commitment, &diffie_hellman_keys);
+
+        let shared_secret = merkle_note
+            .note_encryption_key_for_spender(spender_key.outgoing_view_key())
+            .expect("spender should recover shared secret");
+        assert_eq!(
+            shared_secret,
+            merkle_note.note_encryption_key_for_owner(receiver_key.incoming_view_key())
+        );
+
+        let decrypted = merkle_note
+            .decrypt_note_with_shared_secret(&receiver_address, &shared_secret)
+            .expect("should decrypt with shared secret");
+        assert_eq!(decrypted.value(), 42);
+        assert_eq!(decrypted.memo(), note.memo());
+
+        // Wrong secret or wrong address should fail
+        assert!(merkle_note
+            .decrypt_note_with_shared_secret(&receiver_address, &[0u8; 32])
+            .is_err());
+        assert!(merkle_note
+            .decrypt_note_with_shared_secret(&spender_key.generate_public_address(), &shared_secret)
+            .is_err());
+
+        // Another spender can't recover the secret
+        assert!(merkle_note
+            .note_encryption_key_for_spender(receiver_key.outgoing_view_key())
+            .is_err());
+    }
 }
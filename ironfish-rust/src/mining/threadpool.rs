@@ -1,15 +1,29 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
-use std::sync::mpsc::{self, Receiver};
+use std::{
+    collections::HashSet,
+    sync::mpsc::{self, Receiver},
+};
 
 use super::thread::Thread;
 
+// How many requests before the latest a request kept valid with
+// keep_request_valid is remembered for
+const MAX_VALID_REQUEST_AGE: u32 = 32;
+
 pub struct ThreadPool {
     threads: Vec<Thread>,
     block_found_receiver: Receiver<(u64, u32)>,
     hash_rate_receiver: Receiver<u32>,
     mining_request_id: u32,
+    // The header and target the threads are currently mining, so repeated
+    // work for the same request doesn't restart the threads
+    current_work: Option<(Vec<u8>, Vec<u8>)>,
+    // Request ids whose solutions are still accepted, in addition to the latest
+    valid_request_ids: HashSet<u32>,
+    // Solutions already returned from get_found_block
+    found_blocks: HashSet<(u64, u32)>,
 }
 impl ThreadPool {
     pub fn new(thread_count: usize, batch_size: u32) -> Self {
@@ -33,11 +47,34 @@ impl ThreadPool {
             block_found_receiver,
             hash_rate_receiver,
             mining_request_id: 0,
+            current_work: None,
+            valid_request_ids: HashSet::new(),
+            found_blocks: HashSet::new(),
         }
     }
 
+    /// Start mining the given header. Solutions for previous requests become
+    /// stale unless they were marked valid with keep_request_valid.
+    ///
+    /// Sending the same work again for the current request is ignored, so the
+    /// threads don't restart from the beginning of their search space.
     pub fn new_work(&mut self, header_bytes: &[u8], target: &[u8], mining_request_id: u32) {
+        if mining_request_id == self.mining_request_id {
+            if let Some((current_header, current_target)) = &self.current_work {
+                if current_header == header_bytes && current_target == target {
+                    return;
+                }
+            }
+        }
+
         self.mining_request_id = mining_request_id;
+        self.current_work = Some((header_bytes.to_vec(), target.to_vec()));
+
+        self.valid_request_ids
+            .retain(|id| mining_request_id.wrapping_sub(*id) <= MAX_VALID_REQUEST_AGE);
+        let valid_request_ids = &self.valid_request_ids;
+        self.found_blocks
+            .retain(|(_, id)| *id == mining_request_id || valid_request_ids.contains(id));
 
         for thread in self.threads.iter() {
             thread
@@ -52,19 +89,48 @@ impl ThreadPool {
         }
     }
 
-    pub fn pause(&self) {
+    /// Pause the threads. The next new_work restarts them, even if it's the
+    /// same work they were mining before.
+    pub fn pause(&mut self) {
+        self.current_work = None;
         for thread in self.threads.iter() {
             thread.pause().unwrap();
         }
     }
 
-    pub fn get_found_block(&self) -> Option<(u64, u32)> {
-        if let Ok((randomness, mining_request_id)) = self.block_found_receiver.try_recv() {
-            // Stale work
-            if mining_request_id != self.mining_request_id {
-                return None;
+    /// Keep accepting solutions for a previous request after new work is sent,
+    /// such as when the node can still use a block for an older header.
+    /// Requests are forgotten once they're more than 32 requests old.
+    pub fn keep_request_valid(&mut self, mining_request_id: u32) {
+        self.valid_request_ids.insert(mining_request_id);
+    }
+
+    /// Stop accepting solutions for the given request. Expiring the latest
+    /// request drops its solutions until new work is sent.
+    pub fn expire_request(&mut self, mining_request_id: u32) {
+        self.valid_request_ids.remove(&mining_request_id);
+        if mining_request_id == self.mining_request_id {
+            self.current_work = None;
+        }
+    }
+
+    /// Whether solutions for the given request are still accepted.
+    pub fn is_request_valid(&self, mining_request_id: u32) -> bool {
+        (mining_request_id == self.mining_request_id && self.current_work.is_some())
+            || self.valid_request_ids.contains(&mining_request_id)
+    }
+
+    /// Get the next solution found by the threads, skipping solutions for
+    /// stale requests and solutions that were already returned.
+    pub fn get_found_block(&mut self) -> Option<(u64, u32)> {
+        while let Ok((randomness, mining_request_id)) = self.block_found_receiver.try_recv() {
+            if !self.is_request_valid(mining_request_id) {
+                continue;
+            }
+
+            if self.found_blocks.insert((randomness, mining_request_id)) {
+                return Some((randomness, mining_request_id));
             }
-            return Some((randomness, mining_request_id));
         }
         None
     }
@@ -77,3 +143,89 @@ impl ThreadPool {
         total_hash_rate
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ThreadPool;
+    use std::{thread, time::Duration};
+
+    fn wait_for_block(pool: &mut ThreadPool) -> (u64, u32) {
+        for _ in 0..1000 {
+            if let Some(found) = pool.get_found_block() {
+                return found;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("no block found");
+    }
+
+    #[test]
+    fn test_stale_work() {
+        let header_bytes = [0u8; 8];
+        let target = [255u8; 32];
+        let mut pool = ThreadPool::new(1, 1);
+
+        pool.new_work(&header_bytes, &target, 1);
+        assert_eq!(wait_for_block(&mut pool).1, 1);
+
+        // Solutions for request 1 are dropped once request 2 starts
+        pool.new_work(&header_bytes, &target, 2);
+        assert!(!pool.is_request_valid(1));
+        for _ in 0..10 {
+            assert_eq!(wait_for_block(&mut pool).1, 2);
+        }
+
+        // Unless request 2 is kept valid
+        pool.keep_request_valid(2);
+        pool.new_work(&header_bytes, &target, 3);
+        assert!(pool.is_request_valid(2));
+        assert!(pool.is_request_valid(3));
+
+        pool.expire_request(2);
+        pool.expire_request(3);
+        assert!(!pool.is_request_valid(2));
+        assert!(!pool.is_request_valid(3));
+        pool.pause();
+        thread::sleep(Duration::from_millis(10));
+        assert!(pool.get_found_block().is_none());
+
+        pool.stop();
+    }
+
+    #[test]
+    fn test_resume_after_pause() {
+        let header_bytes = [0u8; 8];
+        let target = [255u8; 32];
+        let mut pool = ThreadPool::new(1, 1);
+
+        pool.new_work(&header_bytes, &target, 1);
+        assert_eq!(wait_for_block(&mut pool).1, 1);
+
+        pool.pause();
+        thread::sleep(Duration::from_millis(10));
+        while pool.get_found_block().is_some() {}
+
+        // The same work restarts the paused threads
+        pool.new_work(&header_bytes, &target, 1);
+        assert_eq!(wait_for_block(&mut pool).1, 1);
+
+        pool.stop();
+    }
+
+    #[test]
+    fn test_valid_request_ids_pruned() {
+        let header_bytes = [0u8; 8];
+        let target = [0u8; 32];
+        let mut pool = ThreadPool::new(1, 1);
+
+        pool.keep_request_valid(1);
+        pool.new_work(&header_bytes, &target, 33);
+        assert!(pool.is_request_valid(1));
+
+        pool.new_work(&header_bytes, &target, 34);
+        assert!(!pool.is_request_valid(1));
+        assert!(pool.valid_request_ids.is_empty());
+
+        pool.stop();
+    }
+}
@@ -0,0 +1,545 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Wire format of the peer to peer messages for gossiping and fetching
+//! blocks and transactions.
+//!
+//! The format matches the messages in the node's `network/messages`
+//! directory: a message type byte, then a 16 byte nonce for gossip messages
+//! or a 2 byte RPC id for requests and responses, then the message body.
+//! Integers are little endian, and variable length byte strings are prefixed
+//! with a bitcoin style varint.
+//!
+//! Decoding never panics and never allocates more than the size of the
+//! input, so it's safe to run on anything received from a peer.
+
+use crate::{compact_block::CompactBlock, errors::NetworkMessageError};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read},
+};
+
+/// Size of the nonce that identifies a gossip message
+pub const NONCE_SIZE: usize = 16;
+
+/// Size of a serialized block header
+pub const BLOCK_HEADER_SIZE: usize = 196;
+
+/// Message type ids. These must match NetworkMessageType in the node, and
+/// changing the format of a message means adding a new type id for it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+pub enum NetworkMessageType {
+    GetBlocksRequest = 4,
+    GetBlocksResponse = 5,
+    NewBlock = 7,
+    NewTransaction = 8,
+    GetCompactBlockRequest = 21,
+    GetCompactBlockResponse = 22,
+}
+
+impl TryFrom<u8> for NetworkMessageType {
+    type Error = NetworkMessageError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            4 => Ok(NetworkMessageType::GetBlocksRequest),
+            5 => Ok(NetworkMessageType::GetBlocksResponse),
+            7 => Ok(NetworkMessageType::NewBlock),
+            8 => Ok(NetworkMessageType::NewTransaction),
+            21 => Ok(NetworkMessageType::GetCompactBlockRequest),
+            22 => Ok(NetworkMessageType::GetCompactBlockResponse),
+            _ => Err(NetworkMessageError::UnknownMessageType),
+        }
+    }
+}
+
+/// A block header as sent over the network
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockHeader {
+    pub sequence: u32,
+    pub previous_block_hash: [u8; 32],
+    pub note_commitment: [u8; 32],
+    pub note_commitment_size: u32,
+    pub nullifier_commitment: [u8; 32],
+    pub nullifier_commitment_size: u32,
+    /// Little endian 256 bit mining target
+    pub target: [u8; 32],
+    pub randomness: u64,
+    pub timestamp: u64,
+    /// The miner's fee is negative in the block header, but is sent as its
+    /// absolute value
+    pub miners_fee: u64,
+    pub graffiti: [u8; 32],
+}
+
+impl BlockHeader {
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let sequence = reader.read_u32::<LittleEndian>()?;
+        let previous_block_hash = read_hash(&mut reader)?;
+        let note_commitment = read_hash(&mut reader)?;
+        let note_commitment_size = reader.read_u32::<LittleEndian>()?;
+        let nullifier_commitment = read_hash(&mut reader)?;
+        let nullifier_commitment_size = reader.read_u32::<LittleEndian>()?;
+        let target = read_hash(&mut reader)?;
+        let randomness = reader.read_u64::<LittleEndian>()?;
+        let timestamp = reader.read_u64::<LittleEndian>()?;
+        let miners_fee = reader.read_u64::<LittleEndian>()?;
+        let graffiti = read_hash(&mut reader)?;
+
+        Ok(BlockHeader {
+            sequence,
+            previous_block_hash,
+            note_commitment,
+            note_commitment_size,
+            nullifier_commitment,
+            nullifier_commitment_size,
+            target,
+            randomness,
+            timestamp,
+            miners_fee,
+            graffiti,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(self.sequence)?;
+        writer.write_all(&self.previous_block_hash)?;
+        writer.write_all(&self.note_commitment)?;
+        writer.write_u32::<LittleEndian>(self.note_commitment_size)?;
+        writer.write_all(&self.nullifier_commitment)?;
+        writer.write_u32::<LittleEndian>(self.nullifier_commitment_size)?;
+        writer.write_all(&self.target)?;
+        writer.write_u64::<LittleEndian>(self.randomness)?;
+        writer.write_u64::<LittleEndian>(self.timestamp)?;
+        writer.write_u64::<LittleEndian>(self.miners_fee)?;
+        writer.write_all(&self.graffiti)?;
+        Ok(())
+    }
+}
+
+/// A full block, with its transactions in serialized form
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializedBlock {
+    pub header: BlockHeader,
+    pub transactions: Vec<Vec<u8>>,
+}
+
+impl SerializedBlock {
+    fn read(reader: &mut &[u8]) -> Result<Self, NetworkMessageError> {
+        let header = BlockHeader::read(&mut *reader)?;
+        let num_transactions = reader.read_u16::<LittleEndian>()?;
+        let mut transactions = vec![];
+        for _ in 0..num_transactions {
+            transactions.push(read_var_bytes(reader)?);
+        }
+
+        Ok(SerializedBlock {
+            header,
+            transactions,
+        })
+    }
+
+    fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        self.header.write(&mut writer)?;
+        writer.write_u16::<LittleEndian>(length_as_u16(self.transactions.len())?)?;
+        for transaction in self.transactions.iter() {
+            write_var_bytes(&mut writer, transaction)?;
+        }
+        Ok(())
+    }
+}
+
+/// A peer to peer message
+#[derive(Clone)]
+pub enum NetworkMessage {
+    /// Gossip a newly mined block
+    NewBlock {
+        nonce: [u8; NONCE_SIZE],
+        block: SerializedBlock,
+    },
+    /// Gossip a transaction that was added to the mempool
+    NewTransaction {
+        nonce: [u8; NONCE_SIZE],
+        transaction: Vec<u8>,
+    },
+    /// Request up to limit blocks, starting at the block with the given hash
+    GetBlocksRequest {
+        rpc_id: u16,
+        start: [u8; 32],
+        limit: u16,
+    },
+    GetBlocksResponse {
+        rpc_id: u16,
+        blocks: Vec<SerializedBlock>,
+    },
+    /// Request the compact form of the block with the given hash
+    GetCompactBlockRequest { rpc_id: u16, hash: [u8; 32] },
+    GetCompactBlockResponse {
+        rpc_id: u16,
+        compact_block: CompactBlock,
+    },
+}
+
+impl NetworkMessage {
+    pub fn message_type(&self) -> NetworkMessageType {
+        match self {
+            NetworkMessage::NewBlock { .. } => NetworkMessageType::NewBlock,
+            NetworkMessage::NewTransaction { .. } => NetworkMessageType::NewTransaction,
+            NetworkMessage::GetBlocksRequest { .. } => NetworkMessageType::GetBlocksRequest,
+            NetworkMessage::GetBlocksResponse { .. } => NetworkMessageType::GetBlocksResponse,
+            NetworkMessage::GetCompactBlockRequest { .. } => {
+                NetworkMessageType::GetCompactBlockRequest
+            }
+            NetworkMessage::GetCompactBlockResponse { .. } => {
+                NetworkMessageType::GetCompactBlockResponse
+            }
+        }
+    }
+
+    /// Decode a complete message, including its type and header. Fails if
+    /// the message is malformed or has bytes left over after decoding.
+    pub fn read(bytes: &[u8]) -> Result<Self, NetworkMessageError> {
+        let mut reader = bytes;
+        let message_type = NetworkMessageType::try_from(reader.read_u8()?)?;
+
+        let message = match message_type {
+            NetworkMessageType::NewBlock => {
+                let nonce = read_nonce(&mut reader)?;
+                let block = SerializedBlock::read(&mut reader)?;
+                NetworkMessage::NewBlock { nonce, block }
+            }
+            NetworkMessageType::NewTransaction => {
+                let nonce = read_nonce(&mut reader)?;
+                let transaction = read_var_bytes(&mut reader)?;
+                NetworkMessage::NewTransaction { nonce, transaction }
+            }
+            NetworkMessageType::GetBlocksRequest => {
+                let rpc_id = reader.read_u16::<LittleEndian>()?;
+                let start = read_hash(&mut reader)?;
+                let limit = reader.read_u16::<LittleEndian>()?;
+                NetworkMessage::GetBlocksRequest {
+                    rpc_id,
+                    start,
+                    limit,
+                }
+            }
+            NetworkMessageType::GetBlocksResponse => {
+                let rpc_id = reader.read_u16::<LittleEndian>()?;
+                let num_blocks = reader.read_u16::<LittleEndian>()?;
+                let mut blocks = vec![];
+                for _ in 0..num_blocks {
+                    blocks.push(SerializedBlock::read(&mut reader)?);
+                }
+                NetworkMessage::GetBlocksResponse { rpc_id, blocks }
+            }
+            NetworkMessageType::GetCompactBlockRequest => {
+                let rpc_id = reader.read_u16::<LittleEndian>()?;
+                let hash = read_hash(&mut reader)?;
+                NetworkMessage::GetCompactBlockRequest { rpc_id, hash }
+            }
+            NetworkMessageType::GetCompactBlockResponse => {
+                let rpc_id = reader.read_u16::<LittleEndian>()?;
+                let compact_block = CompactBlock::read(&mut reader)?;
+                NetworkMessage::GetCompactBlockResponse {
+                    rpc_id,
+                    compact_block,
+                }
+            }
+        };
+
+        if !reader.is_empty() {
+            return Err(NetworkMessageError::TrailingBytes);
+        }
+
+        Ok(message)
+    }
+
+    /// Encode the complete message, including its type and header
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u8(self.message_type() as u8)?;
+
+        match self {
+            NetworkMessage::NewBlock { nonce, block } => {
+                writer.write_all(nonce)?;
+                block.write(&mut writer)?;
+            }
+            NetworkMessage::NewTransaction { nonce, transaction } => {
+                writer.write_all(nonce)?;
+                write_var_bytes(&mut writer, transaction)?;
+            }
+            NetworkMessage::GetBlocksRequest {
+                rpc_id,
+                start,
+                limit,
+            } => {
+                writer.write_u16::<LittleEndian>(*rpc_id)?;
+                writer.write_all(start)?;
+                writer.write_u16::<LittleEndian>(*limit)?;
+            }
+            NetworkMessage::GetBlocksResponse { rpc_id, blocks } => {
+                writer.write_u16::<LittleEndian>(*rpc_id)?;
+                writer.write_u16::<LittleEndian>(length_as_u16(blocks.len())?)?;
+                for block in blocks.iter() {
+                    block.write(&mut writer)?;
+                }
+            }
+            NetworkMessage::GetCompactBlockRequest { rpc_id, hash } => {
+                writer.write_u16::<LittleEndian>(*rpc_id)?;
+                writer.write_all(hash)?;
+            }
+            NetworkMessage::GetCompactBlockResponse {
+                rpc_id,
+                compact_block,
+            } => {
+                writer.write_u16::<LittleEndian>(*rpc_id)?;
+                compact_block.write(&mut writer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_nonce(reader: &mut &[u8]) -> io::Result<[u8; NONCE_SIZE]> {
+    let mut nonce = [0; NONCE_SIZE];
+    reader.read_exact(&mut nonce)?;
+    Ok(nonce)
+}
+
+fn read_hash<R: io::Read>(mut reader: R) -> io::Result<[u8; 32]> {
+    let mut hash = [0; 32];
+    reader.read_exact(&mut hash)?;
+    Ok(hash)
+}
+
+/// Read a varint as encoded by bufio. Non-canonical encodings are rejected,
+/// as they are by bufio.
+fn read_varint(reader: &mut &[u8]) -> Result<u64, NetworkMessageError> {
+    let (value, minimum) = match reader.read_u8()? {
+        0xfd => (reader.read_u16::<LittleEndian>()? as u64, 0xfd),
+        0xfe => (reader.read_u32::<LittleEndian>()? as u64, 0x1_0000),
+        0xff => (reader.read_u64::<LittleEndian>()?, 0x1_0000_0000),
+        value => return Ok(value as u64),
+    };
+
+    if value < minimum {
+        return Err(NetworkMessageError::NonCanonicalVarint);
+    }
+
+    Ok(value)
+}
+
+fn write_varint<W: io::Write>(mut writer: W, value: u64) -> io::Result<()> {
+    if value < 0xfd {
+        writer.write_u8(value as u8)
+    } else if value <= 0xffff {
+        writer.write_u8(0xfd)?;
+        writer.write_u16::<LittleEndian>(value as u16)
+    } else if value <= 0xffff_ffff {
+        writer.write_u8(0xfe)?;
+        writer.write_u32::<LittleEndian>(value as u32)
+    } else {
+        writer.write_u8(0xff)?;
+        writer.write_u64::<LittleEndian>(value)
+    }
+}
+
+fn read_var_bytes(reader: &mut &[u8]) -> Result<Vec<u8>, NetworkMessageError> {
+    let length = read_varint(reader)?;
+    // Check the length against the input before allocating for it
+    if length > reader.len() as u64 {
+        return Err(NetworkMessageError::UnexpectedEndOfMessage);
+    }
+
+    let (bytes, remaining) = reader.split_at(length as usize);
+    *reader = remaining;
+    Ok(bytes.to_vec())
+}
+
+fn write_var_bytes<W: io::Write>(mut writer: W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(&mut writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+fn length_as_u16(length: usize) -> io::Result<u16> {
+    u16::try_from(length).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too many items to serialize in a message",
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        read_varint, write_varint, BlockHeader, NetworkMessage, SerializedBlock, BLOCK_HEADER_SIZE,
+    };
+    use crate::{compact_block::CompactBlock, errors::NetworkMessageError};
+
+    use rand::{thread_rng, Rng};
+
+    fn header() -> BlockHeader {
+        BlockHeader {
+            sequence: 2,
+            previous_block_hash: [1; 32],
+            note_commitment: [2; 32],
+            note_commitment_size: 3,
+            nullifier_commitment: [4; 32],
+            nullifier_commitment_size: 5,
+            target: [6; 32],
+            randomness: 7,
+            timestamp: 8,
+            miners_fee: 9,
+            graffiti: [10; 32],
+        }
+    }
+
+    fn messages() -> Vec<NetworkMessage> {
+        let block = SerializedBlock {
+            header: header(),
+            transactions: vec![vec![1, 2, 3], vec![0; 300]],
+        };
+
+        vec![
+            NetworkMessage::NewBlock {
+                nonce: [1; 16],
+                block: block.clone(),
+            },
+            NetworkMessage::NewTransaction {
+                nonce: [2; 16],
+                transaction: vec![5; 70000],
+            },
+            NetworkMessage::GetBlocksRequest {
+                rpc_id: 3,
+                start: [3; 32],
+                limit: 10,
+            },
+            NetworkMessage::GetBlocksResponse {
+                rpc_id: 4,
+                blocks: vec![block.clone(), block],
+            },
+            NetworkMessage::GetCompactBlockRequest {
+                rpc_id: 5,
+                hash: [4; 32],
+            },
+            NetworkMessage::GetCompactBlockResponse {
+                rpc_id: 6,
+                compact_block: CompactBlock {
+                    header: vec![7; BLOCK_HEADER_SIZE],
+                    transactions: vec![],
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn test_block_header_size() {
+        let mut serialized = vec![];
+        header().write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), BLOCK_HEADER_SIZE);
+        assert_eq!(BlockHeader::read(&serialized[..]).unwrap(), header());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for message in messages() {
+            let mut serialized = vec![];
+            message.write(&mut serialized).unwrap();
+
+            let read_back = NetworkMessage::read(&serialized).unwrap();
+            assert_eq!(read_back.message_type(), message.message_type());
+
+            let mut reserialized = vec![];
+            read_back.write(&mut reserialized).unwrap();
+            assert_eq!(reserialized, serialized);
+        }
+    }
+
+    #[test]
+    fn test_new_transaction_wire_format() {
+        let message = NetworkMessage::NewTransaction {
+            nonce: [9; 16],
+            transaction: vec![1, 2, 3],
+        };
+        let mut serialized = vec![];
+        message.write(&mut serialized).unwrap();
+
+        let mut expected = vec![8];
+        expected.extend_from_slice(&[9; 16]);
+        expected.extend_from_slice(&[3, 1, 2, 3]);
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn test_malformed_messages() {
+        assert!(matches!(
+            NetworkMessage::read(&[]),
+            Err(NetworkMessageError::UnexpectedEndOfMessage)
+        ));
+        assert!(matches!(
+            NetworkMessage::read(&[255]),
+            Err(NetworkMessageError::UnknownMessageType)
+        ));
+
+        // A varint claiming more bytes than the message contains
+        let mut huge_transaction = vec![8];
+        huge_transaction.extend_from_slice(&[0; 16]);
+        huge_transaction.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert!(matches!(
+            NetworkMessage::read(&huge_transaction),
+            Err(NetworkMessageError::UnexpectedEndOfMessage)
+        ));
+
+        // Truncating or extending any valid message must fail cleanly
+        for message in messages() {
+            let mut serialized = vec![];
+            message.write(&mut serialized).unwrap();
+
+            for length in 0..serialized.len() {
+                assert!(NetworkMessage::read(&serialized[..length]).is_err());
+            }
+
+            serialized.push(0);
+            assert!(matches!(
+                NetworkMessage::read(&serialized),
+                Err(NetworkMessageError::TrailingBytes)
+            ));
+        }
+
+        // Random input must never panic
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let mut bytes = vec![0u8; rng.gen_range(0, 512)];
+            rng.fill(&mut bytes[..]);
+            let _ = NetworkMessage::read(&bytes);
+        }
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [
+            0,
+            0xfc,
+            0xfd,
+            0xffff,
+            0x1_0000,
+            0xffff_ffff,
+            0x1_0000_0000,
+            u64::MAX,
+        ] {
+            let mut serialized = vec![];
+            write_varint(&mut serialized, value).unwrap();
+            assert_eq!(read_varint(&mut &serialized[..]).unwrap(), value);
+        }
+
+        assert!(matches!(
+            read_varint(&mut &[0xfd, 0x01, 0x00][..]),
+            Err(NetworkMessageError::NonCanonicalVarint)
+        ));
+    }
+}
@@ -18,16 +18,118 @@ use std::{fmt, io, io::Read};
 
 pub const ENCRYPTED_NOTE_SIZE: usize = 83;
 
+/// Size of the start of an encrypted note that holds everything except the
+/// memo: the diversifier, randomness, and value.
+pub const COMPACT_NOTE_SIZE: usize = 11 + 32 + 8;
+
+/// Number of bytes in the memo field of a note.
+pub const MEMO_SIZE: usize = 32;
+
+/// Size of the header at the start of each part of a multi-part memo. The
+/// header holds the index of the part, the total number of parts, and the
+/// number of message bytes in the part.
+const MEMO_PART_HEADER_SIZE: usize = 3;
+
+/// Number of message bytes that fit in each part of a multi-part memo.
+pub const MEMO_PART_PAYLOAD_SIZE: usize = MEMO_SIZE - MEMO_PART_HEADER_SIZE;
+
+/// Largest message that can be split into a multi-part memo.
+pub const MAX_MEMO_MESSAGE_SIZE: usize = MEMO_PART_PAYLOAD_SIZE * u8::MAX as usize;
+
 /// Memo field on a Note. Used to encode transaction IDs or other information
 /// about the transaction.
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
-pub struct Memo(pub [u8; 32]);
+pub struct Memo(pub [u8; MEMO_SIZE]);
+
+/// Check that a memo fits in a note without being truncated.
+///
+/// Memo::from silently drops anything past MEMO_SIZE bytes, so callers
+/// taking memos from users should check them with this first.
+pub fn validate_memo_bytes(memo: &[u8]) -> Result<(), errors::NoteError> {
+    if memo.len() > MEMO_SIZE {
+        return Err(errors::NoteError::MemoTooLong(memo.len()));
+    }
+
+    Ok(())
+}
+
+impl Memo {
+    /// Build a memo from bytes, failing with MemoTooLong rather than
+    /// truncating them. Shorter memos are padded with zeros.
+    pub fn from_bytes(memo: &[u8]) -> Result<Memo, errors::NoteError> {
+        validate_memo_bytes(memo)?;
+
+        let mut memo_bytes = [0; MEMO_SIZE];
+        memo_bytes[..memo.len()].copy_from_slice(memo);
+        Ok(Memo(memo_bytes))
+    }
+
+    /// Split a message that is too large for a single memo into several
+    /// memos, each prefixed with a small header recording its position.
+    ///
+    /// Each memo is intended to be attached to a separate note for the same
+    /// recipient; see ProposedTransaction::receive_message. The recipient
+    /// reassembles the message with Memo::join_parts.
+    pub fn split_message(message: &[u8]) -> Result<Vec<Memo>, errors::NoteError> {
+        if message.is_empty() || message.len() > MAX_MEMO_MESSAGE_SIZE {
+            return Err(errors::NoteError::InvalidMemo);
+        }
+
+        let chunks = message.chunks(MEMO_PART_PAYLOAD_SIZE);
+        let count = chunks.len() as u8;
+
+        Ok(chunks
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut memo = Memo::default();
+                memo.0[0] = index as u8;
+                memo.0[1] = count;
+                memo.0[2] = chunk.len() as u8;
+                memo.0[MEMO_PART_HEADER_SIZE..MEMO_PART_HEADER_SIZE + chunk.len()]
+                    .copy_from_slice(chunk);
+                memo
+            })
+            .collect())
+    }
+
+    /// Reassemble a message that was split with Memo::split_message. The parts
+    /// may be provided in any order, but every part must be present exactly
+    /// once.
+    pub fn join_parts(parts: &[Memo]) -> Result<Vec<u8>, errors::NoteError> {
+        let count = parts.len();
+        if count == 0 || count > u8::MAX as usize {
+            return Err(errors::NoteError::InvalidMemo);
+        }
+
+        let mut ordered: Vec<Option<&[u8]>> = vec![None; count];
+        for part in parts {
+            let index = part.0[0] as usize;
+            let length = part.0[2] as usize;
+            if part.0[1] as usize != count
+                || index >= count
+                || length == 0
+                || length > MEMO_PART_PAYLOAD_SIZE
+                || ordered[index].is_some()
+            {
+                return Err(errors::NoteError::InvalidMemo);
+            }
+            ordered[index] = Some(&part.0[MEMO_PART_HEADER_SIZE..MEMO_PART_HEADER_SIZE + length]);
+        }
+
+        let mut message = Vec::with_capacity(count * MEMO_PART_PAYLOAD_SIZE);
+        for chunk in ordered {
+            // Every slot is filled: there are count parts with distinct indexes
+            message.extend_from_slice(chunk.expect("all parts are present"));
+        }
+        Ok(message)
+    }
+}
 
 impl From<&str> for Memo {
     fn from(string: &str) -> Self {
         let memo_as_bytes = string.as_bytes();
-        let num_to_clone = std::cmp::min(memo_as_bytes.len(), 32);
-        let mut memo_bytes = [0; 32];
+        let num_to_clone = std::cmp::min(memo_as_bytes.len(), MEMO_SIZE);
+        let mut memo_bytes = [0; MEMO_SIZE];
         memo_bytes[..num_to_clone].clone_from_slice(&memo_as_bytes[..num_to_clone]);
         Memo(memo_bytes)
     }
@@ -92,6 +194,27 @@ impl<'a> Note {
         }
     }
 
+    /// Construct a new Note with the given randomness instead of a random
+    /// one.
+    ///
+    /// This is only for generating and checking known answer tests of the
+    /// note encryption format. Notes that share randomness can be linked
+    /// together, so it must never be used for real notes.
+    #[cfg(any(test, feature = "deterministic-encryption"))]
+    pub fn with_randomness(
+        owner: PublicAddress,
+        value: u64,
+        memo: Memo,
+        randomness: jubjub::Fr,
+    ) -> Self {
+        Self {
+            owner,
+            value,
+            randomness,
+            memo,
+        }
+    }
+
     /// Read a note from the given stream IN PLAINTEXT.
     ///
     /// You probably don't want to use this unless you are transmitting
@@ -151,6 +274,60 @@ impl<'a> Note {
         })
     }
 
+    /// Create a note from the start of its encrypted representation, given
+    /// the owner's view key.
+    ///
+    /// Only the first COMPACT_NOTE_SIZE bytes of the encrypted note are
+    /// needed, so light clients don't have to download the memos of notes
+    /// that aren't theirs. The memo of the returned note is empty. Since the
+    /// MAC isn't checked, the caller must verify the note's commitment.
+    pub fn from_owner_compact(
+        owner_view_key: &'a IncomingViewKey,
+        shared_secret: &[u8; 32],
+        ciphertext_prefix: &[u8; COMPACT_NOTE_SIZE],
+    ) -> Result<Self, errors::NoteError> {
+        let mut plaintext_bytes = [0; COMPACT_NOTE_SIZE];
+        aead::decrypt_prefix(shared_secret, ciphertext_prefix, &mut plaintext_bytes);
+
+        let mut reader = plaintext_bytes[..].as_ref();
+        let mut diversifier_bytes = [0; 11];
+        reader.read_exact(&mut diversifier_bytes[..])?;
+
+        let randomness: jubjub::Fr = read_scalar(&mut reader)?;
+        let value = reader.read_u64::<LittleEndian>()?;
+        let owner = owner_view_key.public_address(&diversifier_bytes)?;
+
+        Ok(Note {
+            owner,
+            value,
+            randomness,
+            memo: Memo::default(),
+        })
+    }
+
+    /// Create a note from its encrypted representation, given the shared
+    /// secret used to encrypt it and the public address it was sent to.
+    ///
+    /// This lets a recipient decrypt a note that was handed to them along
+    /// with its shared secret, without needing their view key. The shared
+    /// secret for a note can be exported with
+    /// MerkleNote::note_encryption_key_for_spender.
+    ///
+    /// Fails if the note wasn't sent to the given address.
+    pub fn decrypt_with_shared_secret(
+        owner: &PublicAddress,
+        shared_secret: &[u8; 32],
+        encrypted_bytes: &[u8; ENCRYPTED_NOTE_SIZE + aead::MAC_SIZE],
+    ) -> Result<Self, errors::NoteError> {
+        let note =
+            Note::from_spender_encrypted(owner.transmission_key, shared_secret, encrypted_bytes)?;
+        if note.owner.diversifier.0 != owner.diversifier.0 {
+            return Err(errors::NoteError::KeyError);
+        }
+
+        Ok(note)
+    }
+
     /// Create a note from its encrypted representation, given the spender's
     /// view key.
     ///
@@ -285,8 +462,13 @@ impl<'a> Note {
 
 #[cfg(test)]
 mod test {
-    use super::{Memo, Note};
-    use crate::keys::{shared_secret, SaplingKey};
+    use super::{
+        validate_memo_bytes, Memo, Note, ENCRYPTED_NOTE_SIZE, MAX_MEMO_MESSAGE_SIZE, MEMO_SIZE,
+    };
+    use crate::{
+        keys::{shared_secret, PublicAddress, SaplingKey},
+        serializing::{aead, bytes_to_hex, scalar_to_bytes},
+    };
 
     #[test]
     fn test_plaintext_serialization() {
@@ -351,6 +533,88 @@ mod test {
         assert!(note.memo == spender_decrypted.memo);
     }
 
+    #[test]
+    fn test_note_encryption_known_answer() {
+        // Fixed inputs, so any change to the plaintext layout or the cipher
+        // shows up as a different ciphertext
+        let owner = PublicAddress::from_hex(
+            "6b23f9d6a1a8560c0a91f7a605b88bbb6322181870fc931d170bc27b163781117eac496fc5e1697ff164ae",
+        )
+        .expect("should load public address");
+        let mut randomness_bytes = [0; 64];
+        for (i, byte) in randomness_bytes.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let randomness = jubjub::Fr::from_bytes_wide(&randomness_bytes);
+        let note =
+            Note::with_randomness(owner.clone(), 1_000_000, "known answer".into(), randomness);
+        let shared_secret = [0x2a; 32];
+
+        let mut plaintext = vec![];
+        plaintext.extend_from_slice(&owner.diversifier.0);
+        plaintext.extend_from_slice(&scalar_to_bytes(&randomness));
+        plaintext.extend_from_slice(&1_000_000u64.to_le_bytes());
+        plaintext.extend_from_slice(&note.memo.0);
+        assert_eq!(
+            bytes_to_hex(&plaintext[11..43]),
+            "46f6a95a971b7481d69b944761090e691f6bec2bedc68776dc840c5b4e602c04"
+        );
+
+        let encrypted = note.encrypt(&shared_secret);
+        assert_eq!(
+            bytes_to_hex(&encrypted),
+            "5dd3f7817587d11233161f742fa60c518f7fd8e1f31a9862cdf35017c528295365135dda220decc3f4cf72\
+             32a146eb2da2dd4fbb1f076e8e5fa70fe3c5a41f8b5bc924d7609e7569e42c234bb9a6d32c9e679d5d45\
+             0096a2f04b1c4a10b9e5f7516c92"
+        );
+
+        let mut decrypted = [0; ENCRYPTED_NOTE_SIZE];
+        aead::decrypt(&shared_secret, &encrypted, &mut decrypted)
+            .expect("should decrypt known answer");
+        assert_eq!(&decrypted[..], &plaintext[..]);
+
+        let restored = Note::decrypt_with_shared_secret(&owner, &shared_secret, &encrypted)
+            .expect("should load note from known answer");
+        assert_eq!(restored.value, 1_000_000);
+        assert_eq!(restored.randomness, randomness);
+        assert_eq!(restored.memo, note.memo);
+    }
+
+    #[test]
+    fn test_memo_parts_round_trip() {
+        let message: Vec<u8> = (0..100).collect();
+        let mut parts = Memo::split_message(&message).expect("should split message");
+        assert_eq!(parts.len(), 4);
+
+        parts.reverse();
+        let joined = Memo::join_parts(&parts).expect("should join parts");
+        assert_eq!(joined, message);
+
+        let short = Memo::split_message(b"short").expect("should split message");
+        assert_eq!(short.len(), 1);
+        assert_eq!(Memo::join_parts(&short).unwrap(), b"short");
+    }
+
+    #[test]
+    fn test_memo_parts_invalid() {
+        assert!(Memo::split_message(&[]).is_err());
+        assert!(Memo::split_message(&[1; MAX_MEMO_MESSAGE_SIZE + 1]).is_err());
+        assert!(Memo::split_message(&[1; MAX_MEMO_MESSAGE_SIZE]).is_ok());
+
+        let message = [7u8; 70];
+        let parts = Memo::split_message(&message).expect("should split message");
+
+        // Missing a part
+        assert!(Memo::join_parts(&parts[..2]).is_err());
+
+        // Duplicated part
+        let duplicated = [parts[0], parts[0], parts[2]];
+        assert!(Memo::join_parts(&duplicated).is_err());
+
+        // Not a multi-part memo
+        assert!(Memo::join_parts(&[Memo::from("a memo")]).is_err());
+    }
+
     #[test]
     fn construct_memo_from_string() {
         let memo = Memo::from("a memo");
@@ -361,4 +625,14 @@ mod test {
         let memo = Memo::from(string);
         assert_eq!(&memo.0[..6], b"a memo");
     }
+
+    #[test]
+    fn test_memo_from_bytes() {
+        assert!(validate_memo_bytes(&[1; MEMO_SIZE]).is_ok());
+        assert!(validate_memo_bytes(&[1; MEMO_SIZE + 1]).is_err());
+
+        let memo = Memo::from_bytes(b"a memo").expect("should fit in a memo");
+        assert_eq!(memo, Memo::from("a memo"));
+        assert!(Memo::from_bytes(&[1; MEMO_SIZE + 1]).is_err());
+    }
 }
@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{
+    errors, keys::SaplingKey, merkle_note::position as witness_position, note::Note,
+    serializing::read_scalar, witness::WitnessTrait, MerkleNoteHash,
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use zcash_primitives::primitives::Nullifier;
+
+use std::io;
+
+/// A decrypted note along with the position of its commitment in the
+/// note commitment tree.
+///
+/// The position is needed to calculate the note's nullifier and to look up
+/// its witness, so keeping the two together avoids passing positions around
+/// separately from the notes they belong to.
+#[derive(Clone)]
+pub struct PositionedNote {
+    /// The decrypted note
+    pub note: Note,
+
+    /// Index of the note's commitment in the note commitment tree
+    pub position: u64,
+
+    /// The note's commitment, as stored in the leaf of the tree
+    pub commitment: MerkleNoteHash,
+}
+
+impl PositionedNote {
+    /// Associate a note with the position of its commitment in the tree.
+    pub fn new(note: Note, position: u64) -> Self {
+        let commitment = MerkleNoteHash::new(note.commitment_point());
+        PositionedNote {
+            note,
+            position,
+            commitment,
+        }
+    }
+
+    /// Read a positioned note from the given stream IN PLAINTEXT.
+    ///
+    /// Fails if the stored commitment doesn't match the note.
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::NoteError> {
+        let note = Note::read(&mut reader)?;
+        let position = reader.read_u64::<LittleEndian>()?;
+        let commitment = read_scalar(&mut reader)?;
+        note.verify_commitment(commitment)?;
+
+        Ok(PositionedNote {
+            note,
+            position,
+            commitment: MerkleNoteHash::new(commitment),
+        })
+    }
+
+    /// Write the positioned note to the given stream IN PLAINTEXT.
+    ///
+    /// Like Note::write, this is intended for storing notes in a wallet or
+    /// moving them across thread boundaries, not for the network.
+    pub fn write<W: io::Write>(&self, mut writer: &mut W) -> io::Result<()> {
+        self.note.write(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.position)?;
+        self.commitment.write(writer)?;
+        Ok(())
+    }
+
+    /// Compute the nullifier for this note at its position, given the
+    /// private key of its owner.
+    pub fn nullifier(&self, private_key: &SaplingKey) -> Nullifier {
+        self.note.nullifier(private_key, self.position)
+    }
+
+    /// Check that the witness authenticates this note at its position.
+    ///
+    /// Spending a note with a witness for a different position produces a
+    /// proof that fails to verify, so it's better to catch it here.
+    pub fn verify_witness(
+        &self,
+        witness: &dyn WitnessTrait,
+    ) -> Result<(), errors::SaplingProofError> {
+        if witness_position(witness) != self.position || !witness.verify(&self.commitment) {
+            return Err(errors::SaplingProofError::InconsistentWitness);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PositionedNote;
+    use crate::{
+        keys::SaplingKey,
+        merkle_note::position as witness_position,
+        note::{Memo, Note},
+        test_util::make_fake_witness,
+    };
+
+    #[test]
+    fn test_positioned_note_serialization() {
+        let key = SaplingKey::generate_key();
+        let note = Note::new(key.generate_public_address(), 42, Memo::from("hello"));
+        let positioned_note = PositionedNote::new(note, 1234);
+
+        let mut serialized = vec![];
+        positioned_note
+            .write(&mut serialized)
+            .expect("should serialize");
+        let read_back = PositionedNote::read(&serialized[..]).expect("should deserialize");
+
+        assert_eq!(read_back.position, 1234);
+        assert_eq!(read_back.commitment, positioned_note.commitment);
+        assert_eq!(read_back.note.value(), 42);
+        assert_eq!(
+            read_back.nullifier(&key),
+            positioned_note.note.nullifier(&key, 1234)
+        );
+
+        // Corrupting the commitment should fail
+        let last = serialized.len() - 1;
+        serialized[last] ^= 1;
+        assert!(PositionedNote::read(&serialized[..]).is_err());
+    }
+
+    #[test]
+    fn test_verify_witness() {
+        let key = SaplingKey::generate_key();
+        let note = Note::new(key.generate_public_address(), 42, Memo::default());
+        let witness = make_fake_witness(&note);
+        let position = witness_position(&witness);
+
+        PositionedNote::new(note.clone(), position)
+            .verify_witness(&witness)
+            .expect("witness should match note");
+
+        assert!(PositionedNote::new(note, position + 1)
+            .verify_witness(&witness)
+            .is_err());
+
+        let other_note = Note::new(key.generate_public_address(), 42, Memo::default());
+        assert!(PositionedNote::new(other_note, position)
+            .verify_witness(&witness)
+            .is_err());
+    }
+}
@@ -2,11 +2,19 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use super::{errors, keys::SaplingKey, merkle_note::MerkleNote, note::Note, Sapling};
+use super::{
+    errors,
+    keys::{OutgoingViewKey, SaplingKey},
+    merkle_note::MerkleNote,
+    note::Note,
+    serializing::read_canonical_proof,
+    value_commitment_opening::ValueCommitmentOpening,
+    Sapling,
+};
 use bellman::groth16;
 use bls12_381::{Bls12, Scalar};
 use group::Curve;
-use jubjub::ExtendedPoint;
+use jubjub::{ExtendedPoint, SubgroupPoint};
 use rand::{rngs::OsRng, thread_rng, Rng};
 use zcash_primitives::primitives::ValueCommitment;
 use zcash_proofs::circuit::sapling::Output;
@@ -27,6 +35,9 @@ pub struct ReceiptParams {
     /// Randomness used to create the ValueCommitment point on the Merkle Note
     pub(crate) value_commitment_randomness: jubjub::Fr,
 
+    /// Value of the note, needed to open the value commitment
+    pub(crate) value: u64,
+
     /// Merkle note containing all the values verified by the proof. These values
     /// are shared on the blockchain and can be snapshotted into a Merkle Tree
     pub(crate) merkle_note: MerkleNote,
@@ -40,7 +51,67 @@ impl ReceiptParams {
         note: &Note,
     ) -> Result<ReceiptParams, errors::SaplingProofError> {
         let diffie_hellman_keys = note.owner.generate_diffie_hellman_keys();
+        ReceiptParams::create(
+            sapling,
+            spender_key.outgoing_view_key(),
+            note,
+            &diffie_hellman_keys,
+        )
+    }
+
+    /// Construct the parameters for proving a new specific note, given only
+    /// the outgoing view key of the sender rather than their spending key.
+    ///
+    /// This lets a receipt be built on its own, outside of a
+    /// ProposedTransaction, such as by one party of a transaction built by
+    /// several parties. The result can be added to a transaction with
+    /// ProposedTransaction::add_receipt_proof, and value_commitment_opening
+    /// returns the opening of its value commitment. The sender can recover
+    /// the note with the outgoing view key.
+    pub fn for_outgoing_view_key(
+        sapling: Arc<Sapling>,
+        outgoing_view_key: &OutgoingViewKey,
+        note: &Note,
+    ) -> Result<ReceiptParams, errors::SaplingProofError> {
+        let diffie_hellman_keys = note.owner.generate_diffie_hellman_keys();
+        ReceiptParams::create(sapling, outgoing_view_key, note, &diffie_hellman_keys)
+    }
+
+    /// Construct the parameters for proving a new specific note, encrypting
+    /// it with the given ephemeral Diffie Hellman key pair instead of a
+    /// randomly generated one.
+    ///
+    /// This is for callers that need to control the key agreement, such as
+    /// protocols that share the ephemeral secret key with the recipient out
+    /// of band, and for deterministic tests. The key pair must have been
+    /// generated for the note's owner, as by
+    /// PublicAddress::generate_diffie_hellman_keys, and must never be reused
+    /// for another note.
+    pub fn with_diffie_hellman_keys(
+        sapling: Arc<Sapling>,
+        spender_key: &SaplingKey,
+        note: &Note,
+        diffie_hellman_keys: &(jubjub::Fr, SubgroupPoint),
+    ) -> Result<ReceiptParams, errors::SaplingProofError> {
+        let (secret_key, public_key) = diffie_hellman_keys;
+        if note.owner.diversifier_point * secret_key != *public_key {
+            return Err(errors::SaplingProofError::InconsistentDiffieHellmanKeys);
+        }
+
+        ReceiptParams::create(
+            sapling,
+            spender_key.outgoing_view_key(),
+            note,
+            diffie_hellman_keys,
+        )
+    }
 
+    fn create(
+        sapling: Arc<Sapling>,
+        outgoing_view_key: &OutgoingViewKey,
+        note: &Note,
+        diffie_hellman_keys: &(jubjub::Fr, SubgroupPoint),
+    ) -> Result<ReceiptParams, errors::SaplingProofError> {
         let mut buffer = [0u8; 64];
         thread_rng().fill(&mut buffer[..]);
 
@@ -51,8 +122,12 @@ impl ReceiptParams {
             randomness: value_commitment_randomness,
         };
 
-        let merkle_note =
-            MerkleNote::new(spender_key, note, &value_commitment, &diffie_hellman_keys);
+        let merkle_note = MerkleNote::for_outgoing_view_key(
+            outgoing_view_key,
+            note,
+            &value_commitment,
+            diffie_hellman_keys,
+        );
 
         let output_circuit = Output {
             value_commitment: Some(value_commitment),
@@ -61,12 +136,13 @@ impl ReceiptParams {
             esk: Some(diffie_hellman_keys.0),
         };
         let proof =
-            groth16::create_random_proof(output_circuit, &sapling.receipt_params, &mut OsRng)?;
+            groth16::create_random_proof(output_circuit, sapling.receipt_params()?, &mut OsRng)?;
 
         let receipt_proof = ReceiptParams {
             sapling,
             proof,
             value_commitment_randomness,
+            value: note.value,
             merkle_note,
         };
 
@@ -90,6 +166,11 @@ impl ReceiptParams {
         Ok(receipt_proof)
     }
 
+    /// The value and randomness of this receipt's value commitment.
+    pub fn value_commitment_opening(&self) -> ValueCommitmentOpening {
+        ValueCommitmentOpening::new(self.value, self.value_commitment_randomness)
+    }
+
     /// Write the signature of this proof to the provided writer.
     ///
     /// The signature is used by the transaction to calculate the signature
@@ -121,7 +202,7 @@ impl ReceiptProof {
     /// This is the main entry-point when reconstructing a serialized
     /// transaction.
     pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::SaplingProofError> {
-        let proof = groth16::Proof::read(&mut reader)?;
+        let proof = read_canonical_proof(&mut reader)?;
         let merkle_note = MerkleNote::read(&mut reader)?;
 
         Ok(ReceiptProof { proof, merkle_note })
@@ -135,23 +216,7 @@ impl ReceiptProof {
     /// Verify that the proof demonstrates knowledge that a note exists with
     /// the value_commitment, public_key, and note_commitment on this proof.
     pub fn verify_proof(&self, sapling: &Sapling) -> Result<(), errors::SaplingProofError> {
-        if self.merkle_note.value_commitment.is_small_order().into()
-            || ExtendedPoint::from(self.merkle_note.ephemeral_public_key)
-                .is_small_order()
-                .into()
-        {
-            return Err(errors::SaplingProofError::VerificationFailed);
-        }
-        let mut public_input = [Scalar::zero(); 5];
-        let p = self.merkle_note.value_commitment.to_affine();
-        public_input[0] = p.get_u();
-        public_input[1] = p.get_v();
-
-        let p = ExtendedPoint::from(self.merkle_note.ephemeral_public_key).to_affine();
-        public_input[2] = p.get_u();
-        public_input[3] = p.get_v();
-
-        public_input[4] = self.merkle_note.note_commitment;
+        let public_input = self.public_inputs()?;
 
         match groth16::verify_proof(
             &sapling.receipt_verifying_key,
@@ -162,6 +227,24 @@ impl ReceiptProof {
             _ => Err(errors::SaplingProofError::VerificationFailed),
         }
     }
+
+    /// The public inputs the proof should be verified against, rejecting
+    /// value commitments and ephemeral keys that are of small order.
+    pub(crate) fn public_inputs(&self) -> Result<[Scalar; 5], errors::SaplingProofError> {
+        if self.merkle_note.value_commitment.is_small_order().into()
+            || ExtendedPoint::from(self.merkle_note.ephemeral_public_key)
+                .is_small_order()
+                .into()
+        {
+            return Err(errors::SaplingProofError::VerificationFailed);
+        }
+
+        Ok(receipt_public_inputs(
+            &self.merkle_note.value_commitment,
+            &self.merkle_note.ephemeral_public_key,
+            self.merkle_note.note_commitment,
+        ))
+    }
     /// Get a MerkleNote, which can be used as a node in a Merkle Tree.
     pub fn merkle_note(&self) -> MerkleNote {
         self.merkle_note.clone()
@@ -179,10 +262,39 @@ impl ReceiptProof {
     }
 }
 
+/// Construct the public inputs to the output circuit, in the order the
+/// circuit expects them:
+///
+///  *  `[0..2]` the u and v coordinates of the value commitment (`cv`)
+///  *  `[2..4]` the u and v coordinates of the ephemeral public key (`epk`)
+///  *  `[4]` the note commitment (`cmu`)
+///
+/// External verifiers should use this rather than assembling the inputs
+/// themselves, so the ordering can't drift from the one used here.
+pub fn receipt_public_inputs(
+    value_commitment: &ExtendedPoint,
+    ephemeral_public_key: &SubgroupPoint,
+    note_commitment: Scalar,
+) -> [Scalar; 5] {
+    let mut public_input = [Scalar::zero(); 5];
+    let p = value_commitment.to_affine();
+    public_input[0] = p.get_u();
+    public_input[1] = p.get_v();
+
+    let p = ExtendedPoint::from(*ephemeral_public_key).to_affine();
+    public_input[2] = p.get_u();
+    public_input[3] = p.get_v();
+
+    public_input[4] = note_commitment;
+
+    public_input
+}
+
 #[cfg(test)]
 mod test {
-    use super::{ReceiptParams, ReceiptProof};
+    use super::{receipt_public_inputs, ReceiptParams, ReceiptProof};
     use crate::{
+        errors,
         keys::SaplingKey,
         note::{Memo, Note},
         sapling_bls12,
@@ -191,6 +303,69 @@ mod test {
     use group::Curve;
     use jubjub::ExtendedPoint;
 
+    #[test]
+    fn test_receipt_with_diffie_hellman_keys() {
+        let sapling = &*sapling_bls12::SAPLING;
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let note = Note::new(receiver_key.generate_public_address(), 42, Memo::default());
+
+        let diffie_hellman_keys = note.owner().generate_diffie_hellman_keys();
+        let receipt = ReceiptParams::with_diffie_hellman_keys(
+            sapling.clone(),
+            &spender_key,
+            &note,
+            &diffie_hellman_keys,
+        )
+        .expect("should be able to create receipt proof");
+        assert_eq!(
+            receipt.merkle_note.ephemeral_public_key,
+            diffie_hellman_keys.1
+        );
+        receipt
+            .post()
+            .expect("should be able to post receipt proof");
+
+        // Keys generated for a different address are rejected
+        let other_keys = spender_key
+            .generate_public_address()
+            .generate_diffie_hellman_keys();
+        assert!(matches!(
+            ReceiptParams::with_diffie_hellman_keys(
+                sapling.clone(),
+                &spender_key,
+                &note,
+                &other_keys
+            ),
+            Err(errors::SaplingProofError::InconsistentDiffieHellmanKeys)
+        ));
+    }
+
+    #[test]
+    fn test_receipt_for_outgoing_view_key() {
+        let sapling = &*sapling_bls12::SAPLING;
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let note = Note::new(receiver_key.generate_public_address(), 42, Memo::default());
+
+        let receipt = ReceiptParams::for_outgoing_view_key(
+            sapling.clone(),
+            spender_key.outgoing_view_key(),
+            &note,
+        )
+        .expect("should be able to create receipt proof");
+        let proof = receipt
+            .post()
+            .expect("should be able to post receipt proof");
+
+        assert!(receipt.value_commitment_opening().verify(&proof));
+        let recovered = proof
+            .merkle_note()
+            .decrypt_note_for_spender(spender_key.outgoing_view_key())
+            .expect("sender should be able to recover the note");
+        assert_eq!(recovered.value(), 42);
+    }
+
     #[test]
     fn test_receipt_round_trip() {
         let sapling = &*sapling_bls12::SAPLING;
@@ -242,4 +417,55 @@ mod test {
             .expect("should be able to serialize proof again");
         assert_eq!(serialized_proof, serialized_again);
     }
+
+    #[test]
+    fn test_receipt_public_inputs() {
+        use bellman::groth16;
+        use bls12_381::Scalar;
+        use zcash_proofs::sapling::SaplingVerificationContext;
+
+        let sapling = &*sapling_bls12::SAPLING;
+        let spender_key = SaplingKey::generate_key();
+        let note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+
+        let receipt = ReceiptParams::new(sapling.clone(), &spender_key, &note)
+            .expect("should be able to create receipt proof");
+        let proof = receipt
+            .post()
+            .expect("should be able to post receipt proof");
+
+        let public_inputs = receipt_public_inputs(
+            &proof.merkle_note.value_commitment,
+            &proof.merkle_note.ephemeral_public_key,
+            proof.merkle_note.note_commitment,
+        );
+        assert_eq!(public_inputs, proof.public_inputs().unwrap());
+
+        // zcash assembles the inputs for the same circuit independently
+        assert!(SaplingVerificationContext::new().check_output(
+            proof.merkle_note.value_commitment,
+            proof.merkle_note.note_commitment,
+            ExtendedPoint::from(proof.merkle_note.ephemeral_public_key),
+            proof.proof.clone(),
+            &sapling.receipt_verifying_key,
+        ));
+        assert!(groth16::verify_proof(
+            &sapling.receipt_verifying_key,
+            &proof.proof,
+            &public_inputs
+        )
+        .is_ok());
+
+        // Every input is bound by the proof
+        for i in 0..public_inputs.len() {
+            let mut altered = public_inputs;
+            altered[i] += Scalar::one();
+            assert!(
+                groth16::verify_proof(&sapling.receipt_verifying_key, &proof.proof, &altered)
+                    .is_err(),
+                "input {} should be checked",
+                i
+            );
+        }
+    }
 }
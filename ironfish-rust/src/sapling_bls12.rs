@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 pub use bls12_381::Scalar;
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use crate::Sapling;
 
@@ -13,6 +13,66 @@ lazy_static! {
     pub static ref SAPLING: Arc<Sapling> = Arc::new(load());
 }
 
+// A verification-only Sapling object using the embedded verifying keys. Prefer
+// this over SAPLING for consumers that never create proofs.
+#[cfg(feature = "embedded-verifying-keys")]
+lazy_static! {
+    pub static ref SAPLING_VERIFIER: Arc<Sapling> = Arc::new(Sapling::load_verifier());
+}
+
+lazy_static! {
+    static ref PARAMS_FINGERPRINT: ParamsFingerprint = ParamsFingerprint::of(&SAPLING);
+}
+
+/// Hashes of the parameters and verifying keys a Sapling object was loaded
+/// with.
+///
+/// Nodes with parameter files that don't match the rest of the network
+/// create proofs that nobody else accepts, and reject everyone else's.
+/// Comparing fingerprints against the network's expected values catches
+/// this at startup rather than at the first transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamsFingerprint {
+    /// Hash of the spend circuit parameters, if they were loaded
+    pub spend_params: Option<[u8; 32]>,
+    /// Hash of the output circuit parameters, if they were loaded
+    pub receipt_params: Option<[u8; 32]>,
+    pub spend_verifying_key: [u8; 32],
+    pub receipt_verifying_key: [u8; 32],
+}
+
+impl ParamsFingerprint {
+    /// Hash the parameters and verifying keys of the given Sapling object.
+    /// Verifier-only objects have no parameters to hash.
+    pub fn of(sapling: &Sapling) -> Self {
+        ParamsFingerprint {
+            spend_params: sapling
+                .spend_params
+                .as_ref()
+                .map(|params| fingerprint(|writer| params.write(writer))),
+            receipt_params: sapling
+                .receipt_params
+                .as_ref()
+                .map(|params| fingerprint(|writer| params.write(writer))),
+            spend_verifying_key: fingerprint(|writer| sapling.spend_vk.write(writer)),
+            receipt_verifying_key: fingerprint(|writer| sapling.receipt_vk.write(writer)),
+        }
+    }
+}
+
+/// The fingerprint of the parameters loaded into SAPLING. Hashing the
+/// parameters takes a moment, so it's only done the first time this is
+/// called.
+pub fn params_fingerprint() -> ParamsFingerprint {
+    *PARAMS_FINGERPRINT
+}
+
+fn fingerprint(write: impl FnOnce(&mut blake3::Hasher) -> io::Result<()>) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    write(&mut hasher).expect("should be able to hash serialized parameters");
+    *hasher.finalize().as_bytes()
+}
+
 /// Load a sapling object configured to a BLS12 jubjub curve. This is currently
 /// the only pairing for which a jubjub curve has been defined, and is the
 /// default implementation.
@@ -22,3 +82,60 @@ lazy_static! {
 fn load() -> Sapling {
     Sapling::load()
 }
+
+#[cfg(test)]
+mod test {
+    use super::{params_fingerprint, ParamsFingerprint, SAPLING};
+
+    #[test]
+    fn test_params_fingerprint() {
+        let fingerprint = params_fingerprint();
+        assert_eq!(fingerprint, ParamsFingerprint::of(&SAPLING));
+        assert!(fingerprint.spend_params.is_some());
+        assert!(fingerprint.receipt_params.is_some());
+        assert_ne!(fingerprint.spend_params, fingerprint.receipt_params);
+        assert_ne!(
+            fingerprint.spend_verifying_key,
+            fingerprint.receipt_verifying_key
+        );
+    }
+}
+
+#[cfg(all(test, feature = "embedded-verifying-keys"))]
+mod embedded_verifying_keys_test {
+    use super::{params_fingerprint, ParamsFingerprint, SAPLING};
+    use crate::{Sapling, RECEIPT_VERIFYING_KEY_BYTES, SPEND_VERIFYING_KEY_BYTES};
+
+    #[test]
+    fn test_embedded_verifying_keys_match_params() {
+        let mut spend_vk = vec![];
+        SAPLING
+            .spend_params()
+            .unwrap()
+            .vk
+            .write(&mut spend_vk)
+            .unwrap();
+        assert_eq!(spend_vk, SPEND_VERIFYING_KEY_BYTES);
+
+        let mut receipt_vk = vec![];
+        SAPLING
+            .receipt_params()
+            .unwrap()
+            .vk
+            .write(&mut receipt_vk)
+            .unwrap();
+        assert_eq!(receipt_vk, RECEIPT_VERIFYING_KEY_BYTES);
+
+        let verifier = Sapling::load_verifier();
+        assert!(!verifier.can_prove());
+        assert!(verifier.spend_params().is_err());
+
+        // The verifying keys are the same however they were loaded
+        let fingerprint = ParamsFingerprint::of(&verifier);
+        assert_eq!(fingerprint.spend_params, None);
+        assert_eq!(
+            fingerprint.spend_verifying_key,
+            params_fingerprint().spend_verifying_key
+        );
+    }
+}
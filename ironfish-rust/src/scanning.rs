@@ -0,0 +1,714 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Find notes in chain data that belong to, or were sent by, an account.
+
+use super::{
+    errors,
+    keys::{DepositTag, IncomingViewKey, OutgoingViewKey, PublicAddress, SaplingKey, ViewKeys},
+    note::Note,
+    transaction::Transaction,
+    MerkleNote,
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use std::{collections::BTreeMap, io};
+
+/// The range of block sequences that may contain notes for an account.
+///
+/// An account can't have notes in blocks mined before it was created, so
+/// scanning can start at its birthday instead of the genesis block. This
+/// saves trial decrypting every output on the chain for new accounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanRange {
+    /// First sequence to scan
+    pub start: u32,
+
+    /// Last sequence to scan, inclusive. None scans to the head of the chain.
+    pub end: Option<u32>,
+}
+
+impl ScanRange {
+    pub fn new(start: u32, end: Option<u32>) -> Self {
+        ScanRange { start, end }
+    }
+
+    /// Scan everything from the block an account was created in.
+    pub fn from_birthday(birthday: u32) -> Self {
+        ScanRange::new(birthday, None)
+    }
+
+    pub fn contains(&self, sequence: u32) -> bool {
+        sequence >= self.start && self.end.map_or(true, |end| sequence <= end)
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let start = reader.read_u32::<LittleEndian>()?;
+        let end = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_u32::<LittleEndian>()?),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid scan range end flag",
+                ))
+            }
+        };
+
+        Ok(ScanRange { start, end })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(self.start)?;
+        match self.end {
+            None => writer.write_u8(0)?,
+            Some(end) => {
+                writer.write_u8(1)?;
+                writer.write_u32::<LittleEndian>(end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ScanRange {
+    fn default() -> Self {
+        ScanRange::from_birthday(0)
+    }
+}
+
+/// Progress of a rescan, saved as it goes so that an interrupted rescan
+/// picks up where it stopped instead of starting over from the birthday.
+///
+/// Blocks are expected to be scanned in order, as are the outputs within
+/// each block, so the position within a partly scanned block is just the
+/// number of its outputs already done.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScannerState {
+    /// Sequence of the last block whose outputs were all scanned. None if
+    /// no block has been finished yet.
+    pub last_scanned_sequence: Option<u32>,
+
+    /// Sequence of the block being scanned when the state was saved, and the
+    /// number of its outputs that were already scanned
+    pub partial_block: Option<(u32, u32)>,
+
+    /// Number of notes decrypted so far for each account, by public address
+    decrypted_notes: BTreeMap<[u8; 43], u64>,
+}
+
+impl ScannerState {
+    pub fn new() -> Self {
+        ScannerState::default()
+    }
+
+    /// The part of the range that is left to scan.
+    pub fn remaining_range(&self, range: &ScanRange) -> ScanRange {
+        match self.last_scanned_sequence {
+            Some(sequence) if sequence >= range.start => {
+                ScanRange::new(sequence.saturating_add(1), range.end)
+            }
+            _ => *range,
+        }
+    }
+
+    /// Whether the output was already scanned before the rescan was
+    /// interrupted, and can be skipped.
+    pub fn is_scanned(&self, sequence: u32, output_index: u32) -> bool {
+        if self
+            .last_scanned_sequence
+            .map_or(false, |last| sequence <= last)
+        {
+            return true;
+        }
+
+        match self.partial_block {
+            Some((partial, outputs_scanned)) if partial == sequence => {
+                output_index < outputs_scanned
+            }
+            _ => false,
+        }
+    }
+
+    /// Record that an output of a block has been scanned.
+    pub fn record_output(&mut self, sequence: u32, output_index: u32) {
+        let outputs_scanned = match self.partial_block {
+            Some((partial, outputs_scanned)) if partial == sequence => {
+                outputs_scanned.max(output_index + 1)
+            }
+            _ => output_index + 1,
+        };
+        self.partial_block = Some((sequence, outputs_scanned));
+    }
+
+    /// Record that an output was decrypted for the account with the given
+    /// address.
+    pub fn record_decrypted_note(&mut self, owner: &PublicAddress) {
+        *self
+            .decrypted_notes
+            .entry(owner.public_address())
+            .or_insert(0) += 1;
+    }
+
+    /// Record that every output of the block has been scanned.
+    pub fn finish_block(&mut self, sequence: u32) {
+        self.last_scanned_sequence = Some(sequence);
+        self.partial_block = None;
+    }
+
+    pub fn decrypted_note_count(&self, owner: &PublicAddress) -> u64 {
+        self.decrypted_notes
+            .get(&owner.public_address())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> io::Result<Self> {
+        let last_scanned_sequence = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_u32::<LittleEndian>()?),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid last scanned sequence flag",
+                ))
+            }
+        };
+        let partial_block = match reader.read_u8()? {
+            0 => None,
+            1 => Some((
+                reader.read_u32::<LittleEndian>()?,
+                reader.read_u32::<LittleEndian>()?,
+            )),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid partial block flag",
+                ))
+            }
+        };
+
+        let account_count = reader.read_u32::<LittleEndian>()?;
+        let mut decrypted_notes = BTreeMap::new();
+        for _ in 0..account_count {
+            let mut address = [0; 43];
+            reader.read_exact(&mut address)?;
+            let count = reader.read_u64::<LittleEndian>()?;
+            decrypted_notes.insert(address, count);
+        }
+
+        Ok(ScannerState {
+            last_scanned_sequence,
+            partial_block,
+            decrypted_notes,
+        })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        match self.last_scanned_sequence {
+            None => writer.write_u8(0)?,
+            Some(sequence) => {
+                writer.write_u8(1)?;
+                writer.write_u32::<LittleEndian>(sequence)?;
+            }
+        }
+        match self.partial_block {
+            None => writer.write_u8(0)?,
+            Some((sequence, outputs_scanned)) => {
+                writer.write_u8(1)?;
+                writer.write_u32::<LittleEndian>(sequence)?;
+                writer.write_u32::<LittleEndian>(outputs_scanned)?;
+            }
+        }
+
+        writer.write_u32::<LittleEndian>(self.decrypted_notes.len() as u32)?;
+        for (address, count) in self.decrypted_notes.iter() {
+            writer.write_all(address)?;
+            writer.write_u64::<LittleEndian>(*count)?;
+        }
+        Ok(())
+    }
+}
+
+/// A key along with the sequence of the chain head when it was created, so
+/// that importing it elsewhere doesn't require rescanning the whole chain.
+#[derive(Clone)]
+pub struct ImportedAccount {
+    pub key: SaplingKey,
+
+    /// Sequence of the head of the chain when the key was created
+    pub birthday: u32,
+}
+
+impl ImportedAccount {
+    pub fn new(key: SaplingKey, birthday: u32) -> Self {
+        ImportedAccount { key, birthday }
+    }
+
+    pub fn scan_range(&self) -> ScanRange {
+        ScanRange::from_birthday(self.birthday)
+    }
+
+    /// Read the spending key followed by the birthday.
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::SaplingKeyError> {
+        let key = SaplingKey::read(&mut reader)?;
+        let birthday = reader.read_u32::<LittleEndian>()?;
+        Ok(ImportedAccount { key, birthday })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        self.key.write(&mut writer)?;
+        writer.write_u32::<LittleEndian>(self.birthday)
+    }
+}
+
+/// A note recovered by the account that created it, rather than the one
+/// that owns it.
+#[derive(Clone)]
+pub struct SentNote {
+    /// Index of the output the note was recovered from
+    pub index: usize,
+
+    /// The decrypted note. The recipient, value, and memo are all available
+    /// on it.
+    pub note: Note,
+}
+
+/// Recover the notes that were sent by the account with the given outgoing
+/// view key.
+///
+/// Outputs that can't be decrypted with the key are skipped, so this can be
+/// run over every output on the chain to rebuild an account's history of
+/// sent transactions when only its keys have been imported.
+pub fn scan_outputs_for_spender<'a>(
+    outputs: impl IntoIterator<Item = &'a MerkleNote>,
+    outgoing_view_key: &OutgoingViewKey,
+) -> Vec<SentNote> {
+    outputs
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, output)| {
+            output
+                .decrypt_note_for_spender(outgoing_view_key)
+                .ok()
+                .map(|note| SentNote { index, note })
+        })
+        .collect()
+}
+
+/// Recover the notes sent by the account from the outputs of the block at
+/// the given sequence, skipping the block entirely if it's outside of the
+/// account's scan range.
+pub fn scan_block_for_spender<'a>(
+    sequence: u32,
+    outputs: impl IntoIterator<Item = &'a MerkleNote>,
+    outgoing_view_key: &OutgoingViewKey,
+    scan_range: &ScanRange,
+) -> Vec<SentNote> {
+    if !scan_range.contains(sequence) {
+        return vec![];
+    }
+
+    scan_outputs_for_spender(outputs, outgoing_view_key)
+}
+
+/// Where a note owned by an account came from, so that wallet history can
+/// show change separately from funds received from others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteOrigin {
+    /// Sent to the account by someone else
+    Received,
+
+    /// Sent by the account to itself in a transaction that also pays
+    /// someone else, which is how change is created
+    Change,
+
+    /// Sent by the account to itself in a transaction that pays no one
+    /// else, such as when combining notes
+    SelfSend,
+}
+
+/// A note owned by an account, found in a transaction.
+#[derive(Clone)]
+pub struct OwnedNote {
+    /// Index of the receipt the note was decrypted from
+    pub index: usize,
+
+    pub note: Note,
+
+    pub origin: NoteOrigin,
+}
+
+/// Decrypt the notes in the transaction that belong to the account, and
+/// work out whether each was received from someone else or created by the
+/// account itself.
+///
+/// A note was created by the account if the account's outgoing view key
+/// decrypts it. Such a note is change when some other receipt of the same
+/// transaction goes to someone else. Zero-value receipts the account sent
+/// to someone else are padding added by ProposedTransaction::pad_to, and
+/// don't count as paying anyone. Note that a transaction built with an
+/// outgoing view key epoch isn't recognized as created by the account.
+pub fn scan_transaction_for_account(
+    transaction: &Transaction,
+    view_keys: &ViewKeys,
+) -> Vec<OwnedNote> {
+    let mut owned = vec![];
+    let mut pays_others = false;
+    for (index, receipt) in transaction.iter_receipts().enumerate() {
+        match receipt
+            .merkle_note
+            .decrypt_note_for_owner(&view_keys.incoming)
+        {
+            Ok(note) => owned.push((index, note)),
+            Err(_) => {
+                let is_padding = receipt
+                    .merkle_note
+                    .decrypt_note_for_spender(&view_keys.outgoing)
+                    .map_or(false, |note| note.value() == 0);
+                if !is_padding {
+                    pays_others = true;
+                }
+            }
+        }
+    }
+
+    owned
+        .into_iter()
+        .map(|(index, note)| {
+            let sent_by_account = transaction.receipts()[index]
+                .merkle_note
+                .decrypt_note_for_spender(&view_keys.outgoing)
+                .is_ok();
+            let origin = match (sent_by_account, pays_others) {
+                (false, _) => NoteOrigin::Received,
+                (true, true) => NoteOrigin::Change,
+                (true, false) => NoteOrigin::SelfSend,
+            };
+
+            OwnedNote {
+                index,
+                note,
+                origin,
+            }
+        })
+        .collect()
+}
+
+/// A deposit to an exchange, attributed to one of its users by the deposit
+/// tag in the note's memo.
+#[derive(Clone)]
+pub struct Deposit {
+    /// Index of the output the deposit was found in
+    pub index: usize,
+
+    /// The user the deposit tag was issued to
+    pub user_id: u64,
+
+    pub note: Note,
+}
+
+/// Find the deposits in a batch of outputs for the exchange with the given
+/// incoming view key.
+///
+/// Outputs that the key can't decrypt, that have no deposit tag, or whose
+/// tag wasn't derived from the key are skipped. Deposits without a valid
+/// tag still belong to the exchange, so they should be found with the usual
+/// scan and handled separately.
+pub fn extract_deposits<'a>(
+    outputs: impl IntoIterator<Item = &'a MerkleNote>,
+    incoming_view_key: &IncomingViewKey,
+) -> Vec<Deposit> {
+    outputs
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, output)| {
+            let note = output.decrypt_note_for_owner(incoming_view_key).ok()?;
+            let tag = DepositTag::from_memo(&note.memo())
+                .filter(|tag| incoming_view_key.verify_deposit_tag(tag))?;
+
+            Some(Deposit {
+                index,
+                user_id: tag.user_id(),
+                note,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        extract_deposits, scan_block_for_spender, scan_outputs_for_spender,
+        scan_transaction_for_account, ImportedAccount, NoteOrigin, ScanRange, ScannerState,
+    };
+    use crate::{
+        keys::SaplingKey,
+        note::{Memo, Note},
+        sapling_bls12,
+        test_util::make_fake_witness,
+        MerkleNote, ProposedTransaction,
+    };
+
+    use rand::{thread_rng, Rng};
+    use zcash_primitives::primitives::ValueCommitment;
+
+    fn make_output(spender_key: &SaplingKey, note: &Note) -> MerkleNote {
+        let mut buffer = [0u8; 64];
+        thread_rng().fill(&mut buffer[..]);
+
+        let value_commitment = ValueCommitment {
+            value: note.value(),
+            randomness: jubjub::Fr::from_bytes_wide(&buffer),
+        };
+        let diffie_hellman_keys = note.owner().generate_diffie_hellman_keys();
+
+        MerkleNote::new(spender_key, note, &value_commitment, &diffie_hellman_keys)
+    }
+
+    #[test]
+    fn test_scan_outputs_for_spender() {
+        let spender_key = SaplingKey::generate_key();
+        let other_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+        let receiver_address = receiver_key.generate_public_address();
+
+        let outputs = [
+            make_output(
+                &other_key,
+                &Note::new(receiver_address.clone(), 1, Memo::default()),
+            ),
+            make_output(
+                &spender_key,
+                &Note::new(receiver_address.clone(), 42, Memo::from("sent")),
+            ),
+            make_output(&other_key, &Note::new(receiver_address, 3, Memo::default())),
+        ];
+
+        let sent = scan_outputs_for_spender(&outputs, spender_key.outgoing_view_key());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].index, 1);
+        assert_eq!(sent[0].note.value(), 42);
+        assert_eq!(sent[0].note.memo(), Memo::from("sent"));
+        assert_eq!(
+            sent[0].note.owner().public_address(),
+            receiver_key.generate_public_address().public_address()
+        );
+    }
+
+    #[test]
+    fn test_scan_range() {
+        let range = ScanRange::new(10, Some(20));
+        assert!(!range.contains(9));
+        assert!(range.contains(10));
+        assert!(range.contains(20));
+        assert!(!range.contains(21));
+        assert!(ScanRange::from_birthday(10).contains(u32::MAX));
+
+        for range in [range, ScanRange::from_birthday(5)].iter() {
+            let mut serialized = vec![];
+            range.write(&mut serialized).unwrap();
+            assert_eq!(ScanRange::read(&serialized[..]).unwrap(), *range);
+        }
+    }
+
+    #[test]
+    fn test_scan_block_before_birthday() {
+        let key = SaplingKey::generate_key();
+        let account = ImportedAccount::new(key.clone(), 100);
+
+        let mut serialized = vec![];
+        account.write(&mut serialized).unwrap();
+        let account = ImportedAccount::read(&serialized[..]).unwrap();
+        assert_eq!(account.birthday, 100);
+        assert_eq!(account.key.spending_key(), key.spending_key());
+
+        let outputs = [make_output(
+            &key,
+            &Note::new(key.generate_public_address(), 42, Memo::default()),
+        )];
+        let scan_range = account.scan_range();
+
+        assert!(
+            scan_block_for_spender(99, &outputs, key.outgoing_view_key(), &scan_range).is_empty()
+        );
+        assert_eq!(
+            scan_block_for_spender(100, &outputs, key.outgoing_view_key(), &scan_range).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_scanner_state() {
+        let key = SaplingKey::generate_key();
+        let address = key.generate_public_address();
+        let range = ScanRange::from_birthday(10);
+
+        let mut state = ScannerState::new();
+        assert_eq!(state.remaining_range(&range), range);
+        assert!(!state.is_scanned(10, 0));
+
+        state.finish_block(10);
+        state.record_output(11, 0);
+        state.record_output(11, 1);
+        state.record_decrypted_note(&address);
+        state.record_decrypted_note(&address);
+
+        // Simulate the rescan being interrupted partway through block 11
+        let mut serialized = vec![];
+        state.write(&mut serialized).unwrap();
+        let state = ScannerState::read(&serialized[..]).unwrap();
+
+        assert_eq!(state.remaining_range(&range), ScanRange::from_birthday(11));
+        assert!(state.is_scanned(10, 5));
+        assert!(state.is_scanned(11, 1));
+        assert!(!state.is_scanned(11, 2));
+        assert!(!state.is_scanned(12, 0));
+        assert_eq!(state.decrypted_note_count(&address), 2);
+        assert_eq!(
+            state.decrypted_note_count(&SaplingKey::generate_key().generate_public_address()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_scan_transaction_for_account() {
+        let sapling = sapling_bls12::SAPLING.clone();
+        let spender_key = SaplingKey::generate_key();
+        let receiver_key = SaplingKey::generate_key();
+
+        let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+        let witness = make_fake_witness(&in_note);
+        let mut transaction = ProposedTransaction::new(sapling.clone());
+        transaction
+            .spend(spender_key.clone(), &in_note, &witness)
+            .expect("should be able to spend note");
+        transaction
+            .receive(
+                &spender_key,
+                &Note::new(receiver_key.generate_public_address(), 40, Memo::default()),
+            )
+            .expect("should be able to receive note");
+        let payment = transaction
+            .post(&spender_key, None, 1)
+            .expect("should be able to post transaction");
+
+        let spender_notes = scan_transaction_for_account(&payment, &spender_key.view_keys());
+        assert_eq!(spender_notes.len(), 1);
+        assert_eq!(spender_notes[0].origin, NoteOrigin::Change);
+        assert_eq!(spender_notes[0].note.value(), 1);
+
+        let receiver_notes = scan_transaction_for_account(&payment, &receiver_key.view_keys());
+        assert_eq!(receiver_notes.len(), 1);
+        assert_eq!(receiver_notes[0].origin, NoteOrigin::Received);
+        assert_eq!(receiver_notes[0].note.value(), 40);
+
+        // Paying only yourself is a self-send rather than change
+        let mut transaction = ProposedTransaction::new(sapling);
+        transaction
+            .spend(spender_key.clone(), &in_note, &witness)
+            .expect("should be able to spend note");
+        let combine = transaction
+            .post(&spender_key, None, 1)
+            .expect("should be able to post transaction");
+        let notes = scan_transaction_for_account(&combine, &spender_key.view_keys());
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].origin, NoteOrigin::SelfSend);
+
+        // Padding receipts don't make a self-send look like a payment
+        let mut transaction = ProposedTransaction::new(sapling_bls12::SAPLING.clone());
+        transaction
+            .spend(spender_key.clone(), &in_note, &witness)
+            .expect("should be able to spend note");
+        transaction
+            .pad_to(
+                &spender_key,
+                1,
+                3,
+                witness.root_hash,
+                witness.tree_size as u32,
+                None,
+                1,
+            )
+            .expect("should be able to pad transaction");
+        let padded = transaction
+            .post(&spender_key, None, 1)
+            .expect("should be able to post transaction");
+        assert_eq!(padded.receipts().len(), 3);
+        let notes = scan_transaction_for_account(&padded, &spender_key.view_keys());
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].origin, NoteOrigin::SelfSend);
+    }
+
+    #[test]
+    fn test_extract_deposits() {
+        let sapling = sapling_bls12::SAPLING.clone();
+        let depositor_key = SaplingKey::generate_key();
+        let exchange_key = SaplingKey::generate_key();
+        let exchange_address = exchange_key.generate_public_address();
+        let incoming = exchange_key.incoming_view_key();
+        let other_exchange_key = SaplingKey::generate_key();
+
+        let in_note = Note::new(
+            depositor_key.generate_public_address(),
+            100,
+            Memo::default(),
+        );
+        let mut transaction = ProposedTransaction::new(sapling);
+        transaction
+            .spend(
+                depositor_key.clone(),
+                &in_note,
+                &make_fake_witness(&in_note),
+            )
+            .expect("should be able to spend note");
+        transaction
+            .receive_deposit(
+                &depositor_key,
+                &exchange_address,
+                10,
+                &incoming.deposit_tag(7),
+            )
+            .expect("should be able to deposit");
+        // A tag from another exchange doesn't count
+        transaction
+            .receive_deposit(
+                &depositor_key,
+                &exchange_address,
+                20,
+                &other_exchange_key.incoming_view_key().deposit_tag(8),
+            )
+            .expect("should be able to deposit");
+        transaction
+            .receive(
+                &depositor_key,
+                &Note::new(exchange_address.clone(), 30, Memo::from("untagged")),
+            )
+            .expect("should be able to receive note");
+        transaction
+            .receive_deposit(
+                &depositor_key,
+                &exchange_address,
+                40,
+                &incoming.deposit_tag(9),
+            )
+            .expect("should be able to deposit");
+        let posted = transaction
+            .post(&depositor_key, None, 0)
+            .expect("should be able to post transaction");
+
+        let outputs: Vec<&MerkleNote> = posted
+            .iter_receipts()
+            .map(|receipt| &receipt.merkle_note)
+            .collect();
+        let deposits = extract_deposits(outputs, incoming);
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(deposits[0].index, 0);
+        assert_eq!(deposits[0].user_id, 7);
+        assert_eq!(deposits[0].note.value(), 10);
+        assert_eq!(deposits[1].index, 3);
+        assert_eq!(deposits[1].user_id, 9);
+        assert_eq!(deposits[1].note.value(), 40);
+    }
+}
@@ -8,9 +8,12 @@
 /// all have functions for serializing, but their interface
 /// can be a bit clunky if you're just working with bytearrays.
 use super::errors;
+use bellman::groth16;
+use bls12_381::Bls12;
 use ff::PrimeField;
 use group::GroupEncoding;
-use jubjub::SubgroupPoint;
+use jubjub::{ExtendedPoint, SubgroupPoint};
+use zcash_primitives::redjubjub::Signature;
 
 use std::io;
 
@@ -44,6 +47,57 @@ pub(crate) fn read_scalar<F: PrimeField, R: io::Read>(
     Ok(scalar)
 }
 
+/// Read a point, rejecting any encoding other than the one the point
+/// serializes to.
+///
+/// Points with an x coordinate of zero can otherwise be read with either
+/// sign bit, which would let anyone change the bytes of a transaction, and so
+/// its hash, without invalidating it.
+pub(crate) fn read_canonical_point<G, R>(mut reader: R) -> Result<G, errors::EncodingError>
+where
+    G: GroupEncoding<Repr = [u8; 32]>,
+    R: io::Read,
+{
+    let mut bytes = [0; 32];
+    reader.read_exact(&mut bytes)?;
+    let point: G =
+        Option::from(G::from_bytes(&bytes)).ok_or(errors::EncodingError::InvalidEncoding)?;
+    if point.to_bytes() != bytes {
+        return Err(errors::EncodingError::NonCanonicalEncoding);
+    }
+    Ok(point)
+}
+
+/// Read a redjubjub signature, rejecting a non-canonical R or an s that
+/// isn't reduced, so that a signature only has one valid encoding.
+pub(crate) fn read_canonical_signature<R: io::Read>(
+    mut reader: R,
+) -> Result<Signature, errors::EncodingError> {
+    let mut bytes = [0; 64];
+    reader.read_exact(&mut bytes)?;
+    read_canonical_point::<ExtendedPoint, _>(&bytes[..32])?;
+    read_scalar::<jubjub::Fr, _>(&bytes[32..])
+        .map_err(|_| errors::EncodingError::NonCanonicalEncoding)?;
+    Ok(Signature::read(&bytes[..])?)
+}
+
+/// Read a groth16 proof, rejecting it if it doesn't serialize back to the
+/// same bytes.
+pub(crate) fn read_canonical_proof<R: io::Read>(
+    mut reader: R,
+) -> Result<groth16::Proof<Bls12>, errors::EncodingError> {
+    let mut bytes = [0; 192];
+    reader.read_exact(&mut bytes)?;
+    let proof =
+        groth16::Proof::read(&bytes[..]).map_err(|_| errors::EncodingError::InvalidEncoding)?;
+    let mut serialized = Vec::with_capacity(192);
+    proof.write(&mut serialized)?;
+    if serialized[..] != bytes[..] {
+        return Err(errors::EncodingError::NonCanonicalEncoding);
+    }
+    Ok(proof)
+}
+
 /// Output the bytes as a hexadecimal String
 pub(crate) fn bytes_to_hex(bytes: &[u8]) -> String {
     bytes
@@ -77,11 +131,86 @@ pub(crate) fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, ()> {
     Ok(bytes)
 }
 
+/// Output secret key bytes as a hexadecimal String, without branches or
+/// table lookups that depend on the bytes.
+///
+/// Use this rather than bytes_to_hex for private keys and view keys.
+pub(crate) fn secret_bytes_to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(nibble_to_hex_char(byte >> 4));
+        hex.push(nibble_to_hex_char(byte & 0x0f));
+    }
+    hex
+}
+
+/// Parse a 32 byte secret key from a hexadecimal String in constant time
+/// with respect to its contents. Only the length of the input affects the
+/// running time.
+///
+/// Use this rather than hex_to_bytes for private keys and view keys.
+pub(crate) fn hex_to_secret_bytes(hex: &str) -> Result<[u8; 32], ()> {
+    hex_bytes_to_secret_bytes(hex.as_bytes())
+}
+
+/// The byte level half of hex_to_secret_bytes, which any byte can be passed
+/// to, including ones that don't form valid UTF-8.
+fn hex_bytes_to_secret_bytes(hex: &[u8]) -> Result<[u8; 32], ()> {
+    if hex.len() != 64 {
+        return Err(());
+    }
+
+    let mut bytes = [0; 32];
+    let mut valid = 0xff;
+    for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+        let (high, high_valid) = hex_char_to_nibble(pair[0]);
+        let (low, low_valid) = hex_char_to_nibble(pair[1]);
+        *byte = high << 4 | low;
+        valid &= high_valid & low_valid;
+    }
+
+    if valid == 0xff {
+        Ok(bytes)
+    } else {
+        Err(())
+    }
+}
+
+/// Convert a nibble to a lowercase hex character without branching on it.
+fn nibble_to_hex_char(nibble: u8) -> char {
+    let nibble = nibble as i16;
+    // 87 + nibble is 'a'..'f' for 10..15. Below 10, the mask subtracts 39 to
+    // land on '0'..'9'.
+    (87 + nibble + (((nibble - 10) >> 8) & !38)) as u8 as char
+}
+
+/// Convert a hex character to its value, returning 0xff as the second value
+/// if the character is valid and 0 if it isn't, without branching on it.
+fn hex_char_to_nibble(c: u8) -> (u8, u8) {
+    let c = c as i16;
+
+    // Digits: c ^ '0' is 0..9, and the mask is 0xff only in that range
+    let digit = c ^ 48;
+    let digit_mask = ((digit - 10) >> 8) as u8;
+
+    // Letters: clearing the lowercase bit and subtracting 55 maps 'a'..'f' and
+    // 'A'..'F' to 10..15, and the mask is 0xff only in that range
+    let letter = (c & !32) - 55;
+    let letter_mask = (((letter - 10) ^ (letter - 16)) >> 8) as u8;
+
+    (
+        (digit_mask & digit as u8) | (letter_mask & letter as u8),
+        digit_mask | letter_mask,
+    )
+}
+
 pub(crate) mod aead {
     use crate::errors;
     use crypto::{
         aead::{AeadDecryptor, AeadEncryptor},
+        chacha20::ChaCha20,
         chacha20poly1305::ChaCha20Poly1305,
+        symmetriccipher::SynchronousStreamCipher,
     };
 
     pub const MAC_SIZE: usize = 16;
@@ -127,9 +256,28 @@ pub(crate) mod aead {
         }
     }
 
+    /// Decrypt the first bytes of a ciphertext created with encrypt, without
+    /// the rest of the ciphertext or the MAC tag.
+    ///
+    /// Nothing is authenticated, so the caller must check the plaintext some
+    /// other way, such as by recomputing a commitment to it.
+    pub(crate) fn decrypt_prefix(
+        key: &[u8],
+        ciphertext_prefix: &[u8],
+        plaintext_output: &mut [u8],
+    ) {
+        assert_eq!(plaintext_output.len(), ciphertext_prefix.len());
+        // ChaCha20Poly1305 uses the first block of the keystream to derive
+        // the poly1305 key, so the ciphertext starts after it
+        let mut cipher = ChaCha20::new(key, &[0; 8]);
+        let mut mac_key = [0; 64];
+        cipher.process(&[0; 64], &mut mac_key);
+        cipher.process(ciphertext_prefix, plaintext_output);
+    }
+
     #[cfg(test)]
     mod test {
-        use super::{decrypt, encrypt};
+        use super::{decrypt, decrypt_prefix, encrypt};
 
         #[test]
         fn test_aead_facade() {
@@ -143,5 +291,69 @@ pub(crate) mod aead {
                 .expect("Should successfully decrypt with MAC verification");
             assert_eq!(&decrypted_plaintext, plaintext);
         }
+
+        #[test]
+        fn test_decrypt_prefix() {
+            let key = [7; 32];
+            let plaintext = b"hello world, only the start of this message is decrypted";
+            let mut encrypted_text = [0; 72];
+            encrypt(&key[..], &plaintext[..], &mut encrypted_text[..]);
+
+            let mut decrypted_prefix = [0; 11];
+            decrypt_prefix(&key[..], &encrypted_text[..11], &mut decrypted_prefix[..]);
+            assert_eq!(&decrypted_prefix, &plaintext[..11]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        bytes_to_hex, hex_bytes_to_secret_bytes, hex_to_bytes, hex_to_secret_bytes,
+        secret_bytes_to_hex,
+    };
+
+    #[test]
+    fn test_secret_hex() {
+        let bytes: Vec<u8> = (0..32).map(|i| i * 8 + 1).collect();
+        let hex = secret_bytes_to_hex(&bytes);
+        assert_eq!(hex, bytes_to_hex(&bytes));
+        assert_eq!(hex_to_secret_bytes(&hex).unwrap()[..], bytes[..]);
+        assert_eq!(
+            hex_to_secret_bytes(&hex.to_uppercase()).unwrap()[..],
+            bytes[..]
+        );
+
+        // Every byte value should match the general purpose functions
+        let all: Vec<u8> = (0..=255).collect();
+        for chunk in all.chunks(32) {
+            let hex = bytes_to_hex(chunk);
+            assert_eq!(secret_bytes_to_hex(chunk), hex);
+            assert_eq!(
+                hex_to_secret_bytes(&hex).unwrap()[..],
+                hex_to_bytes(&hex).unwrap()[..]
+            );
+        }
+
+        // Every invalid byte should be rejected, in either half of a byte
+        for c in (0..=255u8).filter(|c| !c.is_ascii_hexdigit()) {
+            for position in [10, 11].iter() {
+                let mut invalid = hex.clone().into_bytes();
+                invalid[*position] = c;
+                assert!(hex_bytes_to_secret_bytes(&invalid).is_err());
+
+                if c.is_ascii() {
+                    let invalid = String::from_utf8(invalid).unwrap();
+                    assert!(hex_to_secret_bytes(&invalid).is_err());
+                }
+            }
+        }
+
+        // A multi-byte character in place of two hex digits keeps the length
+        let invalid = format!("{}\u{e9}{}", &hex[..10], &hex[12..]);
+        assert_eq!(invalid.len(), 64);
+        assert!(hex_to_secret_bytes(&invalid).is_err());
+
+        assert!(hex_to_secret_bytes(&hex[..62]).is_err());
     }
 }
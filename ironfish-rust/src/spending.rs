@@ -7,9 +7,11 @@ use super::{
     keys::SaplingKey,
     merkle_note::{position as witness_position, sapling_auth_path},
     merkle_note_hash::MerkleNoteHash,
-    note::Note,
-    serializing::read_scalar,
-    witness::WitnessTrait,
+    note::{Memo, Note},
+    serializing::{
+        read_canonical_point, read_canonical_proof, read_canonical_signature, read_scalar,
+    },
+    witness::{Witness, WitnessNode, WitnessTrait},
     Sapling,
 };
 use bellman::gadgets::multipack;
@@ -20,7 +22,7 @@ use group::{Curve, GroupEncoding};
 use jubjub::ExtendedPoint;
 use rand::{rngs::OsRng, thread_rng, Rng};
 
-use zcash_proofs::circuit::sapling::Spend;
+use zcash_proofs::circuit::sapling::{Spend, TREE_DEPTH};
 
 use ff::PrimeField;
 use std::{io, sync::Arc};
@@ -38,8 +40,9 @@ pub struct SpendParams {
     /// value.
     pub(crate) sapling: Arc<Sapling>,
 
-    /// Private key of the person spending the note.
-    spender_key: SaplingKey,
+    /// Private key of the person spending the note, or None if it was
+    /// removed with without_spender_key.
+    spender_key: Option<SaplingKey>,
 
     /// Used to add randomness to signature generation without leaking the key.
     /// Referred to as
@@ -94,18 +97,93 @@ impl<'a> SpendParams {
             return Err(errors::SaplingProofError::InconsistentWitness);
         }
 
-        let mut buffer = [0u8; 64];
-        thread_rng().fill(&mut buffer[..]);
+        SpendParams::create(sapling, spender_key, note, witness, random_scalar())
+    }
+
+    /// Construct a new SpendParams as in new, using the given randomness for
+    /// the randomized public key instead of generating it.
+    ///
+    /// This lets a spend be built on its own, outside of a
+    /// ProposedTransaction, by parties that need to know the randomized
+    /// public key ahead of time, such as the coordinator of a transaction
+    /// built by several parties. The result can be added to a transaction
+    /// with ProposedTransaction::add_spend_proof. The randomness must never
+    /// be reused, or the spends can be linked.
+    pub fn with_public_key_randomness(
+        sapling: Arc<Sapling>,
+        spender_key: SaplingKey,
+        note: &Note,
+        witness: &dyn WitnessTrait,
+        public_key_randomness: jubjub::Fr,
+    ) -> Result<SpendParams, errors::SaplingProofError> {
+        if !witness.verify(&MerkleNoteHash::new(note.commitment_point())) {
+            return Err(errors::SaplingProofError::InconsistentWitness);
+        }
+
+        SpendParams::create(sapling, spender_key, note, witness, public_key_randomness)
+    }
+
+    /// Construct a spend of a zero-value note owned by a throwaway key, for
+    /// padding transactions or referencing an anchor without spending any
+    /// value.
+    ///
+    /// The spend circuit computes the root of the note's authentication path
+    /// and only enforces `(computed_root - root_hash) * value = 0`, so for a
+    /// zero-value note the authentication path is random and only the root
+    /// hash and tree size need to be real. Those should identify a root hash
+    /// that is actually in the chain, because nodes check that every spend
+    /// references a known root hash; see SpendProof::verify_against_tree.
+    /// An empty tree has no root, so a tree size of zero is rejected.
+    ///
+    /// The result can be added to a transaction with
+    /// ProposedTransaction::add_spend_proof and a note value of zero.
+    pub fn new_dummy(
+        sapling: Arc<Sapling>,
+        root_hash: Scalar,
+        tree_size: u32,
+    ) -> Result<SpendParams, errors::SaplingProofError> {
+        if tree_size == 0 {
+            return Err(errors::SaplingProofError::InvalidAnchor);
+        }
+
+        let spender_key = SaplingKey::generate_key();
+        let note = Note::new(spender_key.generate_public_address(), 0, Memo::default());
+
+        let mut rng = thread_rng();
+        let mut auth_path = Vec::with_capacity(TREE_DEPTH);
+        for _ in 0..TREE_DEPTH {
+            let mut buffer = [0u8; 64];
+            rng.fill(&mut buffer[..]);
+            let sibling_hash = Scalar::from_bytes_wide(&buffer);
+            auth_path.push(match rng.gen() {
+                false => WitnessNode::Left(sibling_hash),
+                true => WitnessNode::Right(sibling_hash),
+            });
+        }
+
+        let witness = Witness {
+            tree_size: tree_size as usize,
+            root_hash,
+            auth_path,
+        };
+
+        SpendParams::create(sapling, spender_key, &note, &witness, random_scalar())
+    }
 
+    /// Construct the proof and values for a spend, without checking that the
+    /// witness is consistent with the note.
+    fn create(
+        sapling: Arc<Sapling>,
+        spender_key: SaplingKey,
+        note: &Note,
+        witness: &dyn WitnessTrait,
+        public_key_randomness: jubjub::Fr,
+    ) -> Result<SpendParams, errors::SaplingProofError> {
         let value_commitment = ValueCommitment {
             value: note.value,
-            randomness: jubjub::Fr::from_bytes_wide(&buffer),
+            randomness: random_scalar(),
         };
 
-        let mut buffer = [0u8; 64];
-        thread_rng().fill(&mut buffer[..]);
-        let public_key_randomness = jubjub::Fr::from_bytes_wide(&buffer);
-
         let proof_generation_key = spender_key.sapling_proof_generation_key();
 
         let spend_circuit = Spend {
@@ -117,7 +195,8 @@ impl<'a> SpendParams {
             anchor: Some(witness.root_hash()),
             ar: Some(public_key_randomness),
         };
-        let proof = groth16::create_random_proof(spend_circuit, &sapling.spend_params, &mut OsRng)?;
+        let proof =
+            groth16::create_random_proof(spend_circuit, sapling.spend_params()?, &mut OsRng)?;
 
         let randomized_public_key = redjubjub::PublicKey(spender_key.authorizing_key.into())
             .randomize(public_key_randomness, SPENDING_KEY_GENERATOR);
@@ -125,7 +204,7 @@ impl<'a> SpendParams {
 
         Ok(SpendParams {
             sapling,
-            spender_key,
+            spender_key: Some(spender_key),
             public_key_randomness,
             proof,
             value_commitment,
@@ -142,8 +221,52 @@ impl<'a> SpendParams {
     /// Verifies the proof before returning to prevent posting broken
     /// transactions
     pub fn post(&self, signature_hash: &[u8; 32]) -> Result<SpendProof, errors::SaplingProofError> {
-        let private_key = redjubjub::PrivateKey(self.spender_key.spend_authorizing_key);
-        let randomized_private_key = private_key.randomize(self.public_key_randomness);
+        let authorizing_signature = self.sign(signature_hash)?;
+        self.post_with_signature(signature_hash, authorizing_signature)
+    }
+
+    /// Return a SpendProof suitable for serialization, authorized by a
+    /// signature the spender made with sign.
+    ///
+    /// This is how a spend is posted by someone who doesn't hold the
+    /// spender's key, such as the coordinator of a transaction built by
+    /// several parties. Fails with VerificationFailed if the signature isn't
+    /// a valid signature of signature_hash for this spend.
+    pub fn post_with_signature(
+        &self,
+        signature_hash: &[u8; 32],
+        authorizing_signature: redjubjub::Signature,
+    ) -> Result<SpendProof, errors::SaplingProofError> {
+        let spend_proof = SpendProof {
+            proof: self.proof.clone(),
+            value_commitment: self.value_commitment(),
+            randomized_public_key: redjubjub::PublicKey(self.randomized_public_key.0),
+            root_hash: self.root_hash,
+            tree_size: self.tree_size,
+            nullifier: self.nullifier,
+            authorizing_signature,
+        };
+
+        spend_proof.verify_signature(signature_hash)?;
+        spend_proof.verify_proof(&self.sapling)?;
+
+        Ok(spend_proof)
+    }
+
+    /// Sign the transaction signature hash with the randomized spend
+    /// authorizing key, authorizing this spend.
+    ///
+    /// Fails with SigningError if the spender's key was removed.
+    pub fn sign(
+        &self,
+        signature_hash: &[u8; 32],
+    ) -> Result<redjubjub::Signature, errors::SaplingProofError> {
+        let spender_key = self
+            .spender_key
+            .as_ref()
+            .ok_or(errors::SaplingProofError::SigningError)?;
+        let randomized_private_key = redjubjub::PrivateKey(spender_key.spend_authorizing_key)
+            .randomize(self.public_key_randomness);
         let randomized_public_key =
             redjubjub::PublicKey::from_private(&randomized_private_key, SPENDING_KEY_GENERATOR);
         if randomized_public_key.0 != self.randomized_public_key.0 {
@@ -153,22 +276,26 @@ impl<'a> SpendParams {
         data_to_be_signed[..32].copy_from_slice(&randomized_public_key.0.to_bytes());
         data_to_be_signed[32..].copy_from_slice(&signature_hash[..]);
 
-        let authorizing_signature =
-            randomized_private_key.sign(&data_to_be_signed, &mut OsRng, SPENDING_KEY_GENERATOR);
+        Ok(randomized_private_key.sign(&data_to_be_signed, &mut OsRng, SPENDING_KEY_GENERATOR))
+    }
 
-        let spend_proof = SpendProof {
+    /// A copy of this spend without the spender's key, which can be handed
+    /// to the coordinator of a transaction built by several parties.
+    ///
+    /// The copy can't sign itself; it's posted with post_with_signature and
+    /// a signature from the spender.
+    pub fn without_spender_key(&self) -> SpendParams {
+        SpendParams {
+            sapling: self.sapling.clone(),
+            spender_key: None,
+            public_key_randomness: self.public_key_randomness,
             proof: self.proof.clone(),
-            value_commitment: self.value_commitment(),
-            randomized_public_key,
+            value_commitment: self.value_commitment.clone(),
+            randomized_public_key: redjubjub::PublicKey(self.randomized_public_key.0),
             root_hash: self.root_hash,
             tree_size: self.tree_size,
             nullifier: self.nullifier,
-            authorizing_signature,
-        };
-
-        spend_proof.verify_proof(&self.sapling)?;
-
-        Ok(spend_proof)
+        }
     }
 
     /// Serialize the fields that are needed in calculating a signature to
@@ -260,22 +387,14 @@ impl SpendProof {
     /// This is the main entry-point when reconstructing a serialized
     /// transaction.
     pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::SaplingProofError> {
-        let proof = groth16::Proof::read(&mut reader)?;
-        let value_commitment = {
-            let mut bytes = [0; 32];
-            reader.read_exact(&mut bytes)?;
-            let point = ExtendedPoint::from_bytes(&bytes);
-            if point.is_none().into() {
-                return Err(errors::SaplingProofError::IOError);
-            }
-            point.unwrap()
-        };
-        let randomized_public_key = redjubjub::PublicKey::read(&mut reader)?;
+        let proof = read_canonical_proof(&mut reader)?;
+        let value_commitment = read_canonical_point(&mut reader)?;
+        let randomized_public_key = redjubjub::PublicKey(read_canonical_point(&mut reader)?);
         let root_hash = read_scalar(&mut reader)?;
         let tree_size = reader.read_u32::<LittleEndian>()?;
         let mut nullifier = Nullifier([0; 32]);
         reader.read_exact(&mut nullifier.0)?;
-        let authorizing_signature = redjubjub::Signature::read(&mut reader)?;
+        let authorizing_signature = read_canonical_signature(&mut reader)?;
 
         Ok(SpendProof {
             proof,
@@ -308,6 +427,23 @@ impl SpendProof {
         self.tree_size
     }
 
+    /// Check that the root hash of this spend is the root of the note tree
+    /// when it had tree_size notes.
+    ///
+    /// The proof only shows that the note is in a tree with this root, so
+    /// the root must also be checked against the chain. tree_root_provider
+    /// returns the historical root of the chain's note tree at a given size,
+    /// or None if the tree has never been that size.
+    pub fn verify_against_tree<F: Fn(u32) -> Option<Scalar>>(
+        &self,
+        tree_root_provider: F,
+    ) -> Result<(), errors::SaplingProofError> {
+        match tree_root_provider(self.tree_size) {
+            Some(root_hash) if root_hash == self.root_hash => Ok(()),
+            _ => Err(errors::SaplingProofError::InvalidAnchor),
+        }
+    }
+
     /// Verify that the signature on this proof is signing the provided input
     /// with the randomized_public_key on this proof.
     pub fn verify_signature(
@@ -317,9 +453,7 @@ impl SpendProof {
         if self.randomized_public_key.0.is_small_order().into() {
             return Err(errors::SaplingProofError::VerificationFailed);
         }
-        let mut data_to_be_signed = [0; 64];
-        data_to_be_signed[..32].copy_from_slice(&self.randomized_public_key.0.to_bytes());
-        data_to_be_signed[32..].copy_from_slice(&signature_hash_value[..]);
+        let data_to_be_signed = self.signature_data(signature_hash_value);
 
         if !self.randomized_public_key.verify(
             &data_to_be_signed,
@@ -332,31 +466,22 @@ impl SpendProof {
         }
     }
 
+    /// The bytes the spend authorizing signature is computed over: the
+    /// randomized public key followed by the transaction signature hash.
+    pub(crate) fn signature_data(&self, signature_hash_value: &[u8; 32]) -> [u8; 64] {
+        let mut data_to_be_signed = [0; 64];
+        data_to_be_signed[..32].copy_from_slice(&self.randomized_public_key.0.to_bytes());
+        data_to_be_signed[32..].copy_from_slice(&signature_hash_value[..]);
+        data_to_be_signed
+    }
+
     /// Verify that the bellman proof confirms the randomized_public_key,
     /// commitment_value, nullifier, and anchor attached to this SpendProof.
     ///
     /// This entails converting all the values to appropriate inputs to the
     /// bellman circuit and executing it.
     pub fn verify_proof(&self, sapling: &Sapling) -> Result<(), errors::SaplingProofError> {
-        if self.value_commitment.is_small_order().into() {
-            return Err(errors::SaplingProofError::VerificationFailed);
-        }
-
-        let mut public_input = [Scalar::zero(); 7];
-        let p = self.randomized_public_key.0.to_affine();
-        public_input[0] = p.get_u();
-        public_input[1] = p.get_v();
-
-        let p = self.value_commitment.to_affine();
-        public_input[2] = p.get_u();
-        public_input[3] = p.get_v();
-
-        public_input[4] = self.root_hash;
-
-        let nullifier = multipack::bytes_to_bits_le(&self.nullifier.0);
-        let nullifier = multipack::compute_multipacking(&nullifier);
-        public_input[5] = nullifier[0];
-        public_input[6] = nullifier[1];
+        let public_input = self.public_inputs()?;
 
         match groth16::verify_proof(&sapling.spend_verifying_key, &self.proof, &public_input[..]) {
             Ok(()) => Ok(()),
@@ -364,6 +489,21 @@ impl SpendProof {
         }
     }
 
+    /// The public inputs the proof should be verified against, rejecting
+    /// value commitments that are of small order.
+    pub(crate) fn public_inputs(&self) -> Result<[Scalar; 7], errors::SaplingProofError> {
+        if self.value_commitment.is_small_order().into() {
+            return Err(errors::SaplingProofError::VerificationFailed);
+        }
+
+        Ok(spend_public_inputs(
+            &self.randomized_public_key.0,
+            &self.value_commitment,
+            self.root_hash,
+            &self.nullifier,
+        ))
+    }
+
     /// Serialize the fields that are needed in calculating a signature to
     /// the provided writer (probably a Blake2B writer)
     pub(crate) fn serialize_signature_fields<W: io::Write>(&self, writer: W) -> io::Result<()> {
@@ -379,6 +519,47 @@ impl SpendProof {
     }
 }
 
+fn random_scalar() -> jubjub::Fr {
+    let mut buffer = [0u8; 64];
+    thread_rng().fill(&mut buffer[..]);
+    jubjub::Fr::from_bytes_wide(&buffer)
+}
+
+/// Construct the public inputs to the spend circuit, in the order the circuit
+/// expects them:
+///
+///  *  `[0..2]` the u and v coordinates of the randomized public key (`rk`)
+///  *  `[2..4]` the u and v coordinates of the value commitment (`cv`)
+///  *  `[4]` the root hash of the note tree (`anchor`)
+///  *  `[5..7]` the nullifier, multipacked into two scalars
+///
+/// External verifiers should use this rather than assembling the inputs
+/// themselves, so the ordering can't drift from the one used here.
+pub fn spend_public_inputs(
+    randomized_public_key: &ExtendedPoint,
+    value_commitment: &ExtendedPoint,
+    root_hash: Scalar,
+    nullifier: &Nullifier,
+) -> [Scalar; 7] {
+    let mut public_input = [Scalar::zero(); 7];
+    let p = randomized_public_key.to_affine();
+    public_input[0] = p.get_u();
+    public_input[1] = p.get_v();
+
+    let p = value_commitment.to_affine();
+    public_input[2] = p.get_u();
+    public_input[3] = p.get_v();
+
+    public_input[4] = root_hash;
+
+    let nullifier = multipack::bytes_to_bits_le(&nullifier.0);
+    let nullifier = multipack::compute_multipacking(&nullifier);
+    public_input[5] = nullifier[0];
+    public_input[6] = nullifier[1];
+
+    public_input
+}
+
 /// Given a writer (probably a Blake2b hasher), write byte representations
 /// of the parameters that are used in calculating the signature of a transaction.
 /// This function is called from both SpendProof and SpendParams because
@@ -404,7 +585,7 @@ fn serialize_signature_fields<W: io::Write>(
 
 #[cfg(test)]
 mod test {
-    use super::{SpendParams, SpendProof};
+    use super::{spend_public_inputs, SpendParams, SpendProof};
     use crate::{
         keys::SaplingKey,
         note::{Memo, Note},
@@ -476,4 +657,58 @@ mod test {
             .expect("should be able to serialize proof again");
         assert_eq!(serialized_proof, serialized_again);
     }
+
+    #[test]
+    fn test_spend_public_inputs() {
+        use bellman::groth16;
+        use bls12_381::Scalar;
+        use zcash_proofs::sapling::SaplingVerificationContext;
+
+        let sapling = sapling_bls12::SAPLING.clone();
+        let key = SaplingKey::generate_key();
+        let note = Note::new(key.generate_public_address(), 42, Memo::default());
+        let witness = make_fake_witness(&note);
+
+        let spend = SpendParams::new(sapling.clone(), key, &note, &witness)
+            .expect("should be able to create spend proof");
+        let mut sig_hash = [0u8; 32];
+        thread_rng().fill(&mut sig_hash[..]);
+        let proof = spend.post(&sig_hash).expect("should be able to sign proof");
+
+        let public_inputs = spend_public_inputs(
+            &proof.randomized_public_key.0,
+            &proof.value_commitment,
+            proof.root_hash,
+            &proof.nullifier,
+        );
+        assert_eq!(public_inputs, proof.public_inputs().unwrap());
+
+        // zcash assembles the inputs for the same circuit independently
+        assert!(SaplingVerificationContext::new().check_spend(
+            proof.value_commitment,
+            proof.root_hash,
+            &proof.nullifier.0,
+            proof.randomized_public_key.clone(),
+            &sig_hash,
+            proof.authorizing_signature.clone(),
+            proof.proof.clone(),
+            &sapling.spend_verifying_key,
+        ));
+        assert!(
+            groth16::verify_proof(&sapling.spend_verifying_key, &proof.proof, &public_inputs)
+                .is_ok()
+        );
+
+        // Every input is bound by the proof
+        for i in 0..public_inputs.len() {
+            let mut altered = public_inputs;
+            altered[i] += Scalar::one();
+            assert!(
+                groth16::verify_proof(&sapling.spend_verifying_key, &proof.proof, &altered)
+                    .is_err(),
+                "input {} should be checked",
+                i
+            );
+        }
+    }
 }
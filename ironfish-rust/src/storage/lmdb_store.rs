@@ -0,0 +1,177 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{BatchOperation, Store, WriteBatch};
+use crate::errors::StorageError;
+
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use std::path::Path;
+
+/// A Store persisted to disk with LMDB.
+pub struct LmdbStore {
+    environment: Environment,
+    database: Database,
+}
+
+impl LmdbStore {
+    /// Open or create the store in the given directory. map_size is the
+    /// maximum size the database can grow to, in bytes.
+    pub fn open(path: &Path, map_size: usize) -> Result<Self, StorageError> {
+        let environment = Environment::new().set_map_size(map_size).open(path)?;
+        let database = environment.create_db(None, DatabaseFlags::empty())?;
+
+        Ok(LmdbStore {
+            environment,
+            database,
+        })
+    }
+}
+
+impl Store for LmdbStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let transaction = self.environment.begin_ro_txn()?;
+        match transaction.get(self.database, &key) {
+            Ok(value) => Ok(Some(value.to_vec())),
+            Err(lmdb::Error::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut transaction = self.environment.begin_rw_txn()?;
+        for operation in batch.operations {
+            match operation {
+                BatchOperation::Put(key, value) => {
+                    transaction.put(self.database, &key, &value, WriteFlags::empty())?;
+                }
+                BatchOperation::Delete(key) => match transaction.del(self.database, &key, None) {
+                    Ok(()) | Err(lmdb::Error::NotFound) => {}
+                    Err(e) => return Err(e.into()),
+                },
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn iterate(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, StorageError> {
+        let transaction = self.environment.begin_ro_txn()?;
+        let mut cursor = transaction.open_ro_cursor(self.database)?;
+        let iter = if prefix.is_empty() {
+            cursor.iter_start()
+        } else {
+            cursor.iter_from(prefix)
+        };
+
+        Ok(iter
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect())
+    }
+}
+
+impl From<lmdb::Error> for StorageError {
+    fn from(e: lmdb::Error) -> StorageError {
+        StorageError::BackendError(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LmdbStore;
+    use crate::storage::{Store, WriteBatch};
+
+    use std::{
+        env, fs,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    const MAP_SIZE: usize = 10 * 1024 * 1024;
+
+    /// A directory for one test's database, removed when the test ends.
+    struct TestDirectory(PathBuf);
+
+    impl TestDirectory {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = env::temp_dir().join(format!(
+                "ironfish-lmdb-test-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TestDirectory(path)
+        }
+    }
+
+    impl Drop for TestDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_get_put_delete() {
+        let directory = TestDirectory::new();
+        let store = LmdbStore::open(&directory.0, MAP_SIZE).unwrap();
+        assert_eq!(store.get(b"a").unwrap(), None);
+
+        store.put(b"a", b"1").unwrap();
+        assert_eq!(store.get(b"a").unwrap(), Some(b"1".to_vec()));
+
+        store.put(b"a", b"2").unwrap();
+        assert_eq!(store.get(b"a").unwrap(), Some(b"2".to_vec()));
+
+        store.delete(b"a").unwrap();
+        assert_eq!(store.get(b"a").unwrap(), None);
+        store.delete(b"a").unwrap();
+    }
+
+    #[test]
+    fn test_batch_and_iterate() {
+        let directory = TestDirectory::new();
+        let store = LmdbStore::open(&directory.0, MAP_SIZE).unwrap();
+        store.put(b"b:0", b"stale").unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch
+            .put(b"a:0", b"0")
+            .put(b"b:1", b"1")
+            .put(b"b:0", b"0")
+            .delete(b"c:0")
+            .put(b"c", b"2");
+        store.write(batch).unwrap();
+
+        assert_eq!(
+            store.iterate(b"b:").unwrap(),
+            vec![
+                (b"b:0".to_vec(), b"0".to_vec()),
+                (b"b:1".to_vec(), b"1".to_vec())
+            ]
+        );
+        assert_eq!(store.iterate(b"").unwrap().len(), 4);
+        assert!(store.iterate(b"d").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let directory = TestDirectory::new();
+        {
+            let store = LmdbStore::open(&directory.0, MAP_SIZE).unwrap();
+            let mut batch = WriteBatch::new();
+            batch.put(b"a", b"1").put(b"b", b"2").delete(b"a");
+            store.write(batch).unwrap();
+        }
+
+        // Everything written is there after reopening
+        let store = LmdbStore::open(&directory.0, MAP_SIZE).unwrap();
+        assert_eq!(store.get(b"a").unwrap(), None);
+        assert_eq!(store.get(b"b").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(
+            store.iterate(b"").unwrap(),
+            vec![(b"b".to_vec(), b"2".to_vec())]
+        );
+    }
+}
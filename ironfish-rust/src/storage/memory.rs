@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{BatchOperation, Store, WriteBatch};
+use crate::errors::StorageError;
+
+use std::{collections::BTreeMap, sync::RwLock};
+
+/// A Store that keeps everything in memory, for tests and short-lived state.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Store for MemoryStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let entries = self.entries.read().map_err(|_| StorageError::LockError)?;
+        Ok(entries.get(key).cloned())
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        // Holding the write lock for the whole batch makes it atomic to readers
+        let mut entries = self.entries.write().map_err(|_| StorageError::LockError)?;
+        for operation in batch.operations {
+            match operation {
+                BatchOperation::Put(key, value) => {
+                    entries.insert(key, value);
+                }
+                BatchOperation::Delete(key) => {
+                    entries.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn iterate(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, StorageError> {
+        let entries = self.entries.read().map_err(|_| StorageError::LockError)?;
+        Ok(entries
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryStore;
+    use crate::storage::{Store, WriteBatch};
+
+    #[test]
+    fn test_get_put_delete() {
+        let store = MemoryStore::new();
+        assert_eq!(store.get(b"a").unwrap(), None);
+
+        store.put(b"a", b"1").unwrap();
+        assert_eq!(store.get(b"a").unwrap(), Some(b"1".to_vec()));
+
+        store.put(b"a", b"2").unwrap();
+        assert_eq!(store.get(b"a").unwrap(), Some(b"2".to_vec()));
+
+        store.delete(b"a").unwrap();
+        assert_eq!(store.get(b"a").unwrap(), None);
+        store.delete(b"a").unwrap();
+    }
+
+    #[test]
+    fn test_batch_and_iterate() {
+        let store = MemoryStore::new();
+        store.put(b"b:0", b"stale").unwrap();
+
+        let mut batch = WriteBatch::new();
+        batch
+            .put(b"a:0", b"0")
+            .put(b"b:1", b"1")
+            .put(b"b:0", b"0")
+            .delete(b"c:0")
+            .put(b"c", b"2");
+        store.write(batch).unwrap();
+
+        assert_eq!(
+            store.iterate(b"b:").unwrap(),
+            vec![
+                (b"b:0".to_vec(), b"0".to_vec()),
+                (b"b:1".to_vec(), b"1".to_vec())
+            ]
+        );
+        assert_eq!(store.iterate(b"").unwrap().len(), 4);
+        assert!(store.iterate(b"d").unwrap().is_empty());
+    }
+}
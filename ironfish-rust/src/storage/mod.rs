@@ -0,0 +1,86 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Key-value storage for state kept on the Rust side, such as wallet notes.
+//!
+//! Stores are shared between threads, so every method takes `&self` and
+//! implementations handle their own locking. Writes that need to happen
+//! together are grouped in a WriteBatch, which is applied atomically.
+
+use crate::errors::StorageError;
+
+mod memory;
+pub use memory::*;
+
+#[cfg(feature = "lmdb")]
+mod lmdb_store;
+#[cfg(feature = "lmdb")]
+pub use lmdb_store::*;
+
+/// A single change in a WriteBatch
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchOperation {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// A group of writes that are applied to a store together, or not at all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WriteBatch {
+    operations: Vec<BatchOperation>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the key to the value, replacing any existing value
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> &mut Self {
+        self.operations
+            .push(BatchOperation::Put(key.to_vec(), value.to_vec()));
+        self
+    }
+
+    /// Remove the key. Removing a missing key is not an error.
+    pub fn delete(&mut self, key: &[u8]) -> &mut Self {
+        self.operations.push(BatchOperation::Delete(key.to_vec()));
+        self
+    }
+
+    /// The operations in the batch, in the order they will be applied
+    pub fn operations(&self) -> &[BatchOperation] {
+        &self.operations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+/// A transactional, ordered key-value store.
+pub trait Store: Send + Sync {
+    /// Get the value stored at the key, if any
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Apply all the operations in the batch atomically
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError>;
+
+    /// All of the entries whose keys start with the prefix, in key order
+    fn iterate(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>, StorageError>;
+
+    /// Set the key to the value, replacing any existing value
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::new();
+        batch.put(key, value);
+        self.write(batch)
+    }
+
+    /// Remove the key. Removing a missing key is not an error.
+    fn delete(&self, key: &[u8]) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::new();
+        batch.delete(key);
+        self.write(batch)
+    }
+}
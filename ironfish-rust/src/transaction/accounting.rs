@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use std::collections::BTreeMap;
+
+/// An opaque identifier for one of the parties contributing spends or
+/// receiving notes in a transaction built by several parties.
+///
+/// The transaction never interprets it; coordinators can use whatever bytes
+/// identify a party to them, such as a public address or a session id.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(pub Vec<u8>);
+
+impl From<&[u8]> for SourceId {
+    fn from(bytes: &[u8]) -> Self {
+        SourceId(bytes.to_vec())
+    }
+}
+
+impl From<&str> for SourceId {
+    fn from(id: &str) -> Self {
+        SourceId(id.as_bytes().to_vec())
+    }
+}
+
+/// The values spent and received by a single source in a proposed
+/// transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceAccounting {
+    /// Total value of the notes spent by this source
+    pub spent: u64,
+
+    /// Total value of the notes created for this source, including change
+    pub received: u64,
+
+    pub spend_count: usize,
+
+    pub receipt_count: usize,
+}
+
+impl SourceAccounting {
+    /// How much this source put into the transaction, net of what it got
+    /// back. Summed over all sources this is the transaction fee.
+    pub fn contribution(&self) -> i128 {
+        i128::from(self.spent) - i128::from(self.received)
+    }
+}
+
+/// A summary of who funded what in a proposed transaction.
+///
+/// Spends and receipts added without a source are counted as unattributed,
+/// which includes the change added by ProposedTransaction::post.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionAccounting {
+    /// Accounting for each tagged source, ordered by source id
+    pub sources: Vec<(SourceId, SourceAccounting)>,
+
+    pub unattributed: SourceAccounting,
+
+    /// The balance of all spends minus all receipts, the same as the fee the
+    /// transaction would pay if posted as it is
+    pub transaction_fee: i128,
+}
+
+impl TransactionAccounting {
+    /// Get the accounting for a single source, if it contributed anything.
+    pub fn source(&self, source: &SourceId) -> Option<&SourceAccounting> {
+        self.sources
+            .iter()
+            .find(|(id, _)| id == source)
+            .map(|(_, accounting)| accounting)
+    }
+}
+
+/// Running totals per source, kept on the ProposedTransaction as spends and
+/// receipts are added.
+#[derive(Default)]
+pub(crate) struct SourceLedger {
+    sources: BTreeMap<SourceId, SourceAccounting>,
+    unattributed: SourceAccounting,
+}
+
+impl SourceLedger {
+    pub(crate) fn record_spend(&mut self, source: Option<SourceId>, value: u64) {
+        let accounting = self.entry(source);
+        accounting.spent = accounting.spent.saturating_add(value);
+        accounting.spend_count += 1;
+    }
+
+    pub(crate) fn record_receipt(&mut self, source: Option<SourceId>, value: u64) {
+        let accounting = self.entry(source);
+        accounting.received = accounting.received.saturating_add(value);
+        accounting.receipt_count += 1;
+    }
+
+    pub(crate) fn summary(&self, transaction_fee: i128) -> TransactionAccounting {
+        TransactionAccounting {
+            sources: self
+                .sources
+                .iter()
+                .map(|(id, accounting)| (id.clone(), accounting.clone()))
+                .collect(),
+            unattributed: self.unattributed.clone(),
+            transaction_fee,
+        }
+    }
+
+    fn entry(&mut self, source: Option<SourceId>) -> &mut SourceAccounting {
+        match source {
+            Some(id) => self.sources.entry(id).or_default(),
+            None => &mut self.unattributed,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{ProposedTransaction, SourceId, Transaction, TransactionAccounting};
+use crate::{errors::TransactionError, receiving::ReceiptParams, spending::SpendParams, Sapling};
+
+use std::sync::Arc;
+use zcash_primitives::redjubjub::Signature;
+
+/// Combines spends and receipts that were built independently, such as by
+/// the different parties of a coinjoin, into a single transaction.
+///
+/// Each spend and receipt carries the randomness of its own value
+/// commitment, and the binding signature key is accumulated from those as
+/// they are added. The coordinator doesn't hold any of the parties' keys:
+/// parties hand over their spends with SpendParams::without_spender_key,
+/// and once everything has been added, each party signs signature_hash with
+/// SpendParams::sign and returns only the signature.
+///
+/// Unlike ProposedTransaction::post, assembling never adds change. Every
+/// party is expected to add their own change, so the spends and receipts
+/// must balance out to exactly the transaction fee.
+pub struct TransactionAssembler {
+    transaction: ProposedTransaction,
+}
+
+impl TransactionAssembler {
+    pub fn new(sapling: Arc<Sapling>) -> Self {
+        TransactionAssembler {
+            transaction: ProposedTransaction::new(sapling),
+        }
+    }
+
+    /// Add a spend built with SpendParams::new or
+    /// SpendParams::with_public_key_randomness, attributing it to a party.
+    /// The party keeps the original to sign with, and hands over the copy
+    /// from SpendParams::without_spender_key.
+    pub fn add_spend(&mut self, source: SourceId, spend: SpendParams, note_value: u64) {
+        self.transaction
+            .add_spend_proof_from_source(source, spend, note_value);
+    }
+
+    /// Add a receipt built with ReceiptParams::for_outgoing_view_key,
+    /// attributing it to a party.
+    pub fn add_receipt(&mut self, source: SourceId, receipt: ReceiptParams, note_value: u64) {
+        self.transaction
+            .add_receipt_proof_for_source(source, receipt, note_value);
+    }
+
+    pub fn set_expiration_sequence(&mut self, expiration_sequence: u32) {
+        self.transaction
+            .set_expiration_sequence(expiration_sequence);
+    }
+
+    /// How much each party has put in and taken out so far.
+    pub fn accounting_summary(&self) -> TransactionAccounting {
+        self.transaction.accounting_summary()
+    }
+
+    /// The hash each party signs with SpendParams::sign to authorize their
+    /// spends. It covers every spend and receipt and the expiration
+    /// sequence, so it must be computed after all of them have been added.
+    pub fn signature_hash(&self) -> [u8; 32] {
+        self.transaction.transaction_signature_hash()
+    }
+
+    /// Post the transaction with the parties' signatures of signature_hash,
+    /// one for each spend in the order the spends were added.
+    ///
+    /// Fails with InvalidBalanceError if the spends minus the receipts
+    /// don't come to exactly transaction_fee, and with SigningError or
+    /// VerificationFailed if a signature is missing or invalid.
+    pub fn assemble(
+        self,
+        transaction_fee: u64,
+        spend_signatures: &[Signature],
+    ) -> Result<Transaction, TransactionError> {
+        if self.transaction.value_balance.value() != i128::from(transaction_fee) {
+            return Err(TransactionError::InvalidBalanceError);
+        }
+
+        self.transaction
+            .post_with_spend_signatures(spend_signatures)
+    }
+}
@@ -0,0 +1,272 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{Transaction, VerificationContext};
+use crate::{errors::TransactionError, receiving::ReceiptProof, spending::SpendProof, Sapling};
+use bellman::groth16;
+use bls12_381::{multi_miller_loop, Bls12, G1Affine, G1Projective, G2Prepared, Gt, Scalar};
+use ff::Field;
+use group::{Curve, Group};
+use jubjub::ExtendedPoint;
+use rand::rngs::OsRng;
+
+use std::{collections::HashSet, sync::Arc};
+
+/// Verifies the transactions of a block as they arrive, rather than waiting
+/// for the whole block before starting.
+///
+/// Each transaction passed to add_transaction is checked against the
+/// consensus rules in the context and has its signatures verified right
+/// away, so most invalid transactions are rejected while the rest of the
+/// block is still downloading. The groth16 proofs are only queued, and
+/// finalize checks all of them with a single batched pairing check. This
+/// keeps the work left after the last transaction arrives small.
+///
+/// As with batch_verify_transactions, if finalize fails it is not possible
+/// to tell which transaction was at fault.
+pub struct BlockVerifier {
+    sapling: Arc<Sapling>,
+    context: VerificationContext,
+    spend_proofs: ProofBatch,
+    receipt_proofs: ProofBatch,
+    /// Spends whose proofs are queued, to be added to the context's spend
+    /// proof cache once they have been verified
+    uncached_spends: Vec<SpendProof>,
+    /// Likewise for receipts and the receipt proof cache
+    uncached_receipts: Vec<ReceiptProof>,
+    nullifiers: HashSet<[u8; 32]>,
+    transaction_count: usize,
+}
+
+impl BlockVerifier {
+    pub fn new(sapling: Arc<Sapling>, context: VerificationContext) -> Self {
+        BlockVerifier {
+            sapling,
+            context,
+            spend_proofs: ProofBatch::default(),
+            receipt_proofs: ProofBatch::default(),
+            uncached_spends: vec![],
+            uncached_receipts: vec![],
+            nullifiers: HashSet::new(),
+            transaction_count: 0,
+        }
+    }
+
+    /// Check everything about the transaction except its proofs, and queue
+    /// the proofs to be verified by finalize.
+    ///
+    /// A nullifier that was already spent by an earlier transaction in the
+    /// block is rejected with DuplicateSpendError.
+    pub fn add_transaction(&mut self, transaction: &Transaction) -> Result<(), TransactionError> {
+        self.context.check(transaction)?;
+
+        let mut nullifiers = HashSet::new();
+        for spend in transaction.spends.iter() {
+            if self.nullifiers.contains(&spend.nullifier.0) || !nullifiers.insert(spend.nullifier.0)
+            {
+                return Err(TransactionError::DuplicateSpendError);
+            }
+        }
+
+        let mut spend_inputs = Vec::with_capacity(transaction.spends.len());
+        let mut uncached_spends = vec![];
+        for spend in transaction.spends.iter() {
+            let public_inputs = spend.public_inputs()?;
+            match &self.context.spend_proof_cache {
+                Some(cache) if cache.contains(spend) => {}
+                _ => {
+                    spend_inputs.push((spend.proof.clone(), public_inputs.to_vec()));
+                    uncached_spends.push(spend.clone());
+                }
+            }
+        }
+
+        let mut receipt_inputs = Vec::with_capacity(transaction.receipts.len());
+        let mut uncached_receipts = vec![];
+        for receipt in transaction.receipts.iter() {
+            let public_inputs = receipt.public_inputs()?;
+            match &self.context.receipt_proof_cache {
+                Some(cache) if cache.contains(receipt) => {}
+                _ => {
+                    receipt_inputs.push((receipt.proof.clone(), public_inputs.to_vec()));
+                    uncached_receipts.push(receipt.clone());
+                }
+            }
+        }
+
+        let hash_to_verify_signature = transaction.transaction_signature_hash();
+        for spend in transaction.spends.iter() {
+            spend.verify_signature(&hash_to_verify_signature)?;
+        }
+
+        let mut binding_verification_key = ExtendedPoint::identity();
+        for spend in transaction.spends.iter() {
+            binding_verification_key += spend.value_commitment;
+        }
+        for receipt in transaction.receipts.iter() {
+            binding_verification_key -= receipt.merkle_note.value_commitment;
+        }
+        transaction.verify_binding_signature(&binding_verification_key)?;
+
+        // Only queue the proofs once everything else has passed, so a
+        // rejected transaction doesn't leave anything behind in the batch
+        self.nullifiers.extend(nullifiers);
+        for (proof, inputs) in spend_inputs {
+            self.spend_proofs.queue(proof, inputs);
+        }
+        for (proof, inputs) in receipt_inputs {
+            self.receipt_proofs.queue(proof, inputs);
+        }
+        self.uncached_spends.append(&mut uncached_spends);
+        self.uncached_receipts.append(&mut uncached_receipts);
+        self.transaction_count += 1;
+
+        Ok(())
+    }
+
+    /// The number of transactions accepted by add_transaction so far.
+    pub fn transaction_count(&self) -> usize {
+        self.transaction_count
+    }
+
+    /// Verify all of the queued proofs.
+    pub fn finalize(self) -> Result<(), TransactionError> {
+        if !self.spend_proofs.verify(&self.sapling.spend_vk)
+            || !self.receipt_proofs.verify(&self.sapling.receipt_vk)
+        {
+            return Err(TransactionError::VerificationFailed);
+        }
+
+        if let Some(cache) = &self.context.spend_proof_cache {
+            for spend in self.uncached_spends.iter() {
+                cache.insert(spend);
+            }
+        }
+        if let Some(cache) = &self.context.receipt_proof_cache {
+            for receipt in self.uncached_receipts.iter() {
+                cache.insert(receipt);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Groth16 proofs for a single circuit, waiting to be verified together.
+#[derive(Default)]
+struct ProofBatch {
+    entries: Vec<(groth16::Proof<Bls12>, Vec<Scalar>)>,
+}
+
+impl ProofBatch {
+    fn queue(&mut self, proof: groth16::Proof<Bls12>, public_inputs: Vec<Scalar>) {
+        self.entries.push((proof, public_inputs));
+    }
+
+    /// Check every proof in the batch at once.
+    ///
+    /// A proof is valid if e(A, B) = e(alpha, beta) e(acc, gamma) e(C, delta),
+    /// where acc combines the public inputs with the verifying key's ic.
+    /// Each proof's equation is raised to a random power and the equations
+    /// are multiplied together, so the pairings against the verifying key
+    /// are shared and only one final exponentiation is needed. A batch
+    /// containing an invalid proof passes with negligible probability.
+    fn verify(&self, vk: &groth16::VerifyingKey<Bls12>) -> bool {
+        if self.entries.is_empty() {
+            return true;
+        }
+
+        let mut randomness_sum = Scalar::zero();
+        let mut accumulated_inputs = G1Projective::identity();
+        let mut accumulated_c = G1Projective::identity();
+        let mut proof_terms: Vec<(G1Affine, G2Prepared)> = Vec::with_capacity(self.entries.len());
+
+        for (proof, public_inputs) in self.entries.iter() {
+            if public_inputs.len() + 1 != vk.ic.len() {
+                return false;
+            }
+
+            let randomness = Scalar::random(&mut OsRng);
+
+            let mut acc = G1Projective::from(vk.ic[0]);
+            for (input, base) in public_inputs.iter().zip(vk.ic.iter().skip(1)) {
+                acc += base * input;
+            }
+
+            randomness_sum += randomness;
+            accumulated_inputs += acc * randomness;
+            accumulated_c += proof.c * randomness;
+            proof_terms.push((
+                (proof.a * randomness).to_affine(),
+                G2Prepared::from(proof.b),
+            ));
+        }
+
+        let alpha = (vk.alpha_g1 * -randomness_sum).to_affine();
+        let inputs = (-accumulated_inputs).to_affine();
+        let c = (-accumulated_c).to_affine();
+        let beta = G2Prepared::from(vk.beta_g2);
+        let gamma = G2Prepared::from(vk.gamma_g2);
+        let delta = G2Prepared::from(vk.delta_g2);
+
+        let mut terms: Vec<(&G1Affine, &G2Prepared)> =
+            proof_terms.iter().map(|(a, b)| (a, b)).collect();
+        terms.push((&alpha, &beta));
+        terms.push((&inputs, &gamma));
+        terms.push((&c, &delta));
+
+        multi_miller_loop(&terms).final_exponentiation() == Gt::identity()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProofBatch;
+    use crate::{
+        keys::SaplingKey,
+        note::{Memo, Note},
+        receiving::ReceiptParams,
+        sapling_bls12,
+    };
+    use bls12_381::Scalar;
+
+    #[test]
+    fn test_proof_batch() {
+        let sapling = sapling_bls12::SAPLING.clone();
+        let spender_key = SaplingKey::generate_key();
+
+        let mut batch = ProofBatch::default();
+        let mut inputs = vec![];
+        for value in 1..4 {
+            let note = Note::new(
+                spender_key.generate_public_address(),
+                value,
+                Memo::default(),
+            );
+            let receipt = ReceiptParams::new(sapling.clone(), &spender_key, &note)
+                .expect("should be able to create receipt")
+                .post()
+                .expect("should be able to post receipt");
+            let public_inputs = receipt.public_inputs().unwrap().to_vec();
+            batch.queue(receipt.proof.clone(), public_inputs.clone());
+            inputs.push((receipt.proof, public_inputs));
+        }
+        assert!(batch.verify(&sapling.receipt_vk));
+        assert!(ProofBatch::default().verify(&sapling.receipt_vk));
+
+        // Changing the inputs of a single proof fails the whole batch
+        let mut bad_batch = ProofBatch::default();
+        for (i, (proof, public_inputs)) in inputs.iter().enumerate() {
+            let mut public_inputs = public_inputs.clone();
+            if i == 1 {
+                public_inputs[4] += Scalar::one();
+            }
+            bad_batch.queue(proof.clone(), public_inputs);
+        }
+        assert!(!bad_batch.verify(&sapling.receipt_vk));
+
+        // Proofs don't verify against another circuit's key
+        assert!(!batch.verify(&sapling.spend_vk));
+    }
+}
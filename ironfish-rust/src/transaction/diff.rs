@@ -0,0 +1,118 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::Transaction;
+
+use std::collections::{HashMap, HashSet};
+
+/// The nullifiers and note commitments that change when one set of
+/// transactions is replaced by another, such as when a block is replaced
+/// during a reorg.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionDiff {
+    /// Nullifiers spent by the new transactions but not the old ones
+    pub added_nullifiers: Vec<[u8; 32]>,
+
+    /// Nullifiers spent by the old transactions but not the new ones. Notes
+    /// with these nullifiers are unspent again.
+    pub removed_nullifiers: Vec<[u8; 32]>,
+
+    /// Commitments of notes created by the new transactions but not the old
+    /// ones
+    pub added_note_commitments: Vec<[u8; 32]>,
+
+    /// Commitments of notes created by the old transactions but not the new
+    /// ones. These notes no longer exist on the chain.
+    pub removed_note_commitments: Vec<[u8; 32]>,
+
+    /// Notes created by both the old and new transactions, but at a
+    /// different position in the note tree. The nullifiers of these notes
+    /// depend on their position, so wallets must recompute them.
+    pub moved_note_commitments: Vec<MovedNoteCommitment>,
+}
+
+/// A note that is in the note tree before and after the transactions are
+/// replaced, but at a different position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovedNoteCommitment {
+    pub note_commitment: [u8; 32],
+    pub old_position: u64,
+    pub new_position: u64,
+}
+
+/// Compare the transactions of a block that is being disconnected with the
+/// transactions of the block replacing it. The tree sizes are the size of
+/// the note tree before each block, used to find the positions of the notes
+/// the blocks add.
+///
+/// Transactions that are in both blocks don't appear in the diff, so a
+/// wallet can roll back only what actually changed, except for the notes
+/// they create that end up at a different position. Each list is in the
+/// order the items appear in the transactions.
+pub fn diff(
+    old_transactions: &[Transaction],
+    old_tree_size: u64,
+    new_transactions: &[Transaction],
+    new_tree_size: u64,
+) -> TransactionDiff {
+    let old_nullifiers = nullifiers(old_transactions);
+    let new_nullifiers = nullifiers(new_transactions);
+    let old_commitments = note_commitments(old_transactions);
+    let new_commitments = note_commitments(new_transactions);
+
+    let old_positions: HashMap<&[u8; 32], u64> = old_commitments
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, commitment)| (commitment, old_tree_size + index as u64))
+        .collect();
+    let mut moved_note_commitments = vec![];
+    for (index, commitment) in new_commitments.iter().enumerate() {
+        let new_position = new_tree_size + index as u64;
+        match old_positions.get(commitment) {
+            Some(&old_position) if old_position != new_position => {
+                moved_note_commitments.push(MovedNoteCommitment {
+                    note_commitment: *commitment,
+                    old_position,
+                    new_position,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    TransactionDiff {
+        added_nullifiers: difference(&new_nullifiers, &old_nullifiers),
+        removed_nullifiers: difference(&old_nullifiers, &new_nullifiers),
+        added_note_commitments: difference(&new_commitments, &old_commitments),
+        removed_note_commitments: difference(&old_commitments, &new_commitments),
+        moved_note_commitments,
+    }
+}
+
+fn nullifiers(transactions: &[Transaction]) -> Vec<[u8; 32]> {
+    transactions
+        .iter()
+        .flat_map(|transaction| transaction.iter_spends())
+        .map(|spend| spend.nullifier().0)
+        .collect()
+}
+
+fn note_commitments(transactions: &[Transaction]) -> Vec<[u8; 32]> {
+    transactions
+        .iter()
+        .flat_map(|transaction| transaction.iter_receipts())
+        .map(|receipt| receipt.merkle_note.note_commitment.to_bytes())
+        .collect()
+}
+
+/// The items of first that aren't in second, keeping their order.
+fn difference(first: &[[u8; 32]], second: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let second: HashSet<&[u8; 32]> = second.iter().collect();
+    first
+        .iter()
+        .filter(|item| !second.contains(item))
+        .copied()
+        .collect()
+}
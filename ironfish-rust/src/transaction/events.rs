@@ -0,0 +1,73 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::Transaction;
+
+/// A change to the chain state made by a transaction, for explorers and
+/// indexers that want to follow the chain without parsing transactions
+/// themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainEvent {
+    /// A note was added to the note commitment tree
+    NoteCommitmentAdded {
+        /// Index of the transaction in the list the events were extracted
+        /// from
+        transaction_index: usize,
+        transaction_hash: [u8; 32],
+        /// Index of the receipt in the transaction
+        receipt_index: usize,
+        /// Position of the note among the notes added by the transactions.
+        /// Add the size of the note tree before the transactions to get its
+        /// position in the tree.
+        position: u64,
+        note_commitment: [u8; 32],
+    },
+
+    /// A note was spent, revealing its nullifier
+    NullifierSpent {
+        /// Index of the transaction in the list the events were extracted
+        /// from
+        transaction_index: usize,
+        transaction_hash: [u8; 32],
+        /// Index of the spend in the transaction
+        spend_index: usize,
+        nullifier: [u8; 32],
+    },
+}
+
+/// List the changes the transactions make to the chain state, such as the
+/// transactions of a verified block.
+///
+/// Events are in the order the transactions are applied: transaction by
+/// transaction, with each transaction's spends before its receipts.
+pub fn extract_events(transactions: &[Transaction]) -> Vec<ChainEvent> {
+    let mut events = vec![];
+    let mut position = 0;
+
+    for (transaction_index, transaction) in transactions.iter().enumerate() {
+        let transaction_hash = transaction.hash();
+
+        for (spend_index, spend) in transaction.iter_spends().enumerate() {
+            events.push(ChainEvent::NullifierSpent {
+                transaction_index,
+                transaction_hash,
+                spend_index,
+                nullifier: spend.nullifier().0,
+            });
+        }
+
+        for (receipt_index, receipt) in transaction.iter_receipts().enumerate() {
+            events.push(ChainEvent::NoteCommitmentAdded {
+                transaction_index,
+                transaction_hash,
+                receipt_index,
+                position,
+                note_commitment: receipt.merkle_note.note_commitment.to_bytes(),
+            });
+            position += 1;
+        }
+    }
+
+    events
+}
@@ -3,38 +3,78 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use super::{
-    errors::{SaplingProofError, TransactionError},
-    keys::{PublicAddress, SaplingKey},
-    merkle_note::NOTE_ENCRYPTION_MINER_KEYS,
+    consensus::constants::MAX_MESSAGE_SIZE,
+    errors::{NoteError, SaplingProofError, TransactionError},
+    keys::{DepositTag, OutgoingViewKey, PublicAddress, SaplingKey},
+    merkle_note::{position as witness_position, NOTE_ENCRYPTION_MINER_KEYS},
     note::{Memo, Note},
+    positioned_note::PositionedNote,
     receiving::{ReceiptParams, ReceiptProof},
+    serializing::read_canonical_signature,
     spending::{SpendParams, SpendProof},
+    value_commitment_opening::{ValueCommitmentOpening, ENCRYPTED_OPENING_SIZE},
     witness::WitnessTrait,
     Sapling,
 };
-use blake2b_simd::Params as Blake2b;
+use bls12_381::Scalar;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use ff::Field;
 use group::GroupEncoding;
-use jubjub::ExtendedPoint;
-use rand::rngs::OsRng;
+use jubjub::{ExtendedPoint, SubgroupPoint};
+use rand::{rngs::OsRng, seq::SliceRandom, thread_rng};
 
 use zcash_primitives::{
-    constants::{VALUE_COMMITMENT_RANDOMNESS_GENERATOR, VALUE_COMMITMENT_VALUE_GENERATOR},
-    redjubjub::{PrivateKey, PublicKey, Signature},
+    constants::{
+        SPENDING_KEY_GENERATOR, VALUE_COMMITMENT_RANDOMNESS_GENERATOR,
+        VALUE_COMMITMENT_VALUE_GENERATOR,
+    },
+    redjubjub::{self, BatchEntry, PrivateKey, PublicKey, Signature},
 };
 
-use std::{io, slice::Iter, sync::Arc};
+use std::{collections::HashSet, convert::TryFrom, io, slice::Iter, sync::Arc};
 
 use std::ops::AddAssign;
 use std::ops::SubAssign;
 
+mod accounting;
+pub use accounting::*;
+
+mod assembler;
+pub use assembler::*;
+
+mod block_verifier;
+pub use block_verifier::*;
+
+mod diff;
+pub use diff::*;
+
+mod events;
+pub use events::*;
+
+mod replacement;
+pub use replacement::*;
+
+mod signature_hasher;
+pub use signature_hasher::*;
+
+mod size;
+pub use size::*;
+
+mod spend_proof_cache;
+pub use spend_proof_cache::*;
+
+mod value_balance;
+pub use value_balance::*;
+
+mod verification_context;
+pub use verification_context::*;
+
+mod version;
+pub use version::*;
+
 #[cfg(test)]
 mod tests;
 
-const SIGNATURE_HASH_PERSONALIZATION: &[u8; 8] = b"Bnsighsh";
-const TRANSACTION_SIGNATURE_VERSION: &[u8; 1] = &[0];
-
 /// A collection of spend and receipt proofs that can be signed and verified.
 /// In general, all the spent values should add up to all the receipt values.
 ///
@@ -67,12 +107,24 @@ pub struct ProposedTransaction {
 
     /// The balance of all the spends minus all the receipts. The difference
     /// is the fee paid to the miner for mining the transaction.
-    transaction_fee: i64,
+    value_balance: ValueBalance,
 
     /// This is the sequence in the chain the transaction will expire at and be
     /// removed from the mempool. A value of 0 indicates the transaction will
     /// not expire.
     expiration_sequence: u32,
+
+    /// Running totals of the values spent and received by each source, for
+    /// transactions built from several parties' spends.
+    ledger: SourceLedger,
+
+    /// Whether the same note may be spent or created more than once. This
+    /// is almost always a wallet bug, so it's rejected by default.
+    allow_duplicates: bool,
+
+    /// If set, receipts are encrypted for the sender with the outgoing view
+    /// key for this epoch rather than the account's outgoing view key.
+    outgoing_view_key_epoch: Option<u32>,
     //
     // NOTE: If adding fields here, you may need to add fields to
     // signature hash method, and also to Transaction.
@@ -86,8 +138,11 @@ impl ProposedTransaction {
             binding_verification_key: ExtendedPoint::identity(),
             spends: vec![],
             receipts: vec![],
-            transaction_fee: 0,
+            value_balance: ValueBalance::default(),
             expiration_sequence: 0,
+            ledger: SourceLedger::default(),
+            allow_duplicates: false,
+            outgoing_view_key_epoch: None,
         }
     }
 
@@ -98,21 +153,65 @@ impl ProposedTransaction {
         note: &Note,
         witness: &dyn WitnessTrait,
     ) -> Result<(), SaplingProofError> {
+        self.check_duplicate_spend(&spender_key, note, witness)?;
         let proof = SpendParams::new(self.sapling.clone(), spender_key, note, witness)?;
         self.add_spend_proof(proof, note.value());
         Ok(())
     }
 
+    /// Spend the note owned by spender_key at the given witness location,
+    /// attributing its value to the given source in the accounting summary.
+    pub fn spend_from_source(
+        &mut self,
+        source: SourceId,
+        spender_key: SaplingKey,
+        note: &Note,
+        witness: &dyn WitnessTrait,
+    ) -> Result<(), SaplingProofError> {
+        self.check_duplicate_spend(&spender_key, note, witness)?;
+        let proof = SpendParams::new(self.sapling.clone(), spender_key, note, witness)?;
+        self.add_spend_proof_from_source(source, proof, note.value());
+        Ok(())
+    }
+
+    /// Spend a note at its recorded position, checking that the witness
+    /// is for the same position before creating the proof.
+    pub fn spend_positioned(
+        &mut self,
+        spender_key: SaplingKey,
+        positioned_note: &PositionedNote,
+        witness: &dyn WitnessTrait,
+    ) -> Result<(), SaplingProofError> {
+        positioned_note.verify_witness(witness)?;
+        self.spend(spender_key, &positioned_note.note, witness)
+    }
+
     /// Add a spend proof that was created externally.
     ///
     /// This allows for parallel immutable spends without having to take
     /// a mutable pointer out on self.
     pub fn add_spend_proof(&mut self, spend: SpendParams, note_value: u64) {
+        self.push_spend(None, spend, note_value);
+    }
+
+    /// Add a spend proof that was created externally, attributing its value
+    /// to the given source.
+    pub fn add_spend_proof_from_source(
+        &mut self,
+        source: SourceId,
+        spend: SpendParams,
+        note_value: u64,
+    ) {
+        self.push_spend(Some(source), spend, note_value);
+    }
+
+    fn push_spend(&mut self, source: Option<SourceId>, spend: SpendParams, note_value: u64) {
         self.increment_binding_signature_key(&spend.value_commitment.randomness, false);
         self.increment_binding_verification_key(&spend.value_commitment(), false);
 
         self.spends.push(spend);
-        self.transaction_fee += note_value as i64;
+        self.value_balance.spend(note_value);
+        self.ledger.record_spend(source, note_value);
     }
 
     /// Create a proof of a new note owned by the recipient in this
@@ -122,13 +221,170 @@ impl ProposedTransaction {
         spender_key: &SaplingKey,
         note: &Note,
     ) -> Result<(), SaplingProofError> {
-        let proof = ReceiptParams::new(self.sapling.clone(), spender_key, note)?;
+        self.check_duplicate_receipt(note)?;
+        let proof = ReceiptParams::for_outgoing_view_key(
+            self.sapling.clone(),
+            &self.outgoing_view_key(spender_key),
+            note,
+        )?;
+        self.add_receipt_proof(proof, note.value);
+
+        Ok(())
+    }
+
+    /// Encrypt the notes created by receive and post for the sender with
+    /// the outgoing view key of the given epoch, derived with
+    /// OutgoingViewKey::for_epoch. None uses the account's outgoing view key.
+    ///
+    /// Wallets that rotate epochs limit what a leaked epoch key reveals to
+    /// the transactions sent during that epoch.
+    pub fn set_outgoing_view_key_epoch(&mut self, epoch: Option<u32>) {
+        self.outgoing_view_key_epoch = epoch;
+    }
+
+    fn outgoing_view_key(&self, spender_key: &SaplingKey) -> OutgoingViewKey {
+        match self.outgoing_view_key_epoch {
+            Some(epoch) => spender_key.outgoing_view_key().for_epoch(epoch),
+            None => spender_key.outgoing_view_key().clone(),
+        }
+    }
+
+    /// Add a receipt proof that was created externally, such as with
+    /// ReceiptParams::with_diffie_hellman_keys.
+    pub fn add_receipt_proof(&mut self, receipt: ReceiptParams, note_value: u64) {
+        self.push_receipt(None, receipt, note_value);
+    }
+
+    /// Create a proof of a new note owned by the recipient, attributing it
+    /// to the given source. This is usually used for a party's change, so
+    /// the accounting summary shows their net contribution.
+    pub fn receive_for_source(
+        &mut self,
+        source: SourceId,
+        spender_key: &SaplingKey,
+        note: &Note,
+    ) -> Result<(), SaplingProofError> {
+        self.check_duplicate_receipt(note)?;
+        let proof = ReceiptParams::for_outgoing_view_key(
+            self.sapling.clone(),
+            &self.outgoing_view_key(spender_key),
+            note,
+        )?;
+        self.add_receipt_proof_for_source(source, proof, note.value);
+
+        Ok(())
+    }
+
+    /// Add a receipt proof that was created externally, attributing it to
+    /// the given source.
+    pub fn add_receipt_proof_for_source(
+        &mut self,
+        source: SourceId,
+        receipt: ReceiptParams,
+        note_value: u64,
+    ) {
+        self.push_receipt(Some(source), receipt, note_value);
+    }
+
+    fn push_receipt(&mut self, source: Option<SourceId>, receipt: ReceiptParams, note_value: u64) {
+        self.increment_binding_signature_key(&receipt.value_commitment_randomness, true);
+        self.increment_binding_verification_key(&receipt.merkle_note.value_commitment, true);
+
+        self.receipts.push(receipt);
+        self.value_balance.receive(note_value);
+        self.ledger.record_receipt(source, note_value);
+    }
+
+    /// Summarize how much each source has spent and received so far, so
+    /// that coordinators of multi-party transactions can show who is paying
+    /// for what.
+    pub fn accounting_summary(&self) -> TransactionAccounting {
+        self.ledger.summary(self.value_balance.value())
+    }
+
+    /// Attach a message that is too long for a single memo to this
+    /// transaction. The message is split with Memo::split_message and each
+    /// part is placed on its own zero-value note owned by the recipient.
+    ///
+    /// The recipient can reassemble the message by decrypting the notes and
+    /// passing their memos to Memo::join_parts.
+    pub fn receive_message(
+        &mut self,
+        spender_key: &SaplingKey,
+        recipient: &PublicAddress,
+        message: &[u8],
+    ) -> Result<(), TransactionError> {
+        let memos =
+            Memo::split_message(message).map_err(|_| TransactionError::IllegalValueError)?;
+
+        for memo in memos {
+            let note = Note::new(recipient.clone(), 0, memo);
+            self.receive(spender_key, &note)?;
+        }
+
+        Ok(())
+    }
+
+    /// Deposit value to an exchange, putting the tag the exchange issued to
+    /// the depositing user in the note's memo so the exchange can credit
+    /// the right account.
+    pub fn receive_deposit(
+        &mut self,
+        spender_key: &SaplingKey,
+        exchange_address: &PublicAddress,
+        value: u64,
+        tag: &DepositTag,
+    ) -> Result<(), SaplingProofError> {
+        let note = Note::new(exchange_address.clone(), value, tag.memo());
+        self.receive(spender_key, &note)
+    }
+
+    /// Pad the transaction with zero-value spends and receipts until it has
+    /// at least the given number of each, so that wallets can make their
+    /// transactions the same shape and avoid leaking information through
+    /// the number of spends and receipts.
+    ///
+    /// The change receipt is added first, the same way post adds it, so it
+    /// counts toward the receipts. The same change_goes_to and
+    /// intended_transaction_fee should then be passed to post, which finds
+    /// no change left to add and keeps the padded shape.
+    ///
+    /// The dummy spends reference the given root hash and tree size, which
+    /// should be a valid anchor on the chain. The dummy notes are owned by
+    /// throwaway keys and don't affect the transaction's balance. They are
+    /// encrypted for the spender's outgoing view key, so the spender's
+    /// wallet can recognize them as padding. Spends and receipts, including
+    /// the change, are shuffled afterward so neither the dummies nor the
+    /// change can be identified by their position.
+    ///
+    /// This should be called after all other spends and receipts are added.
+    #[allow(clippy::too_many_arguments)]
+    pub fn pad_to(
+        &mut self,
+        spender_key: &SaplingKey,
+        spends: usize,
+        receipts: usize,
+        root_hash: Scalar,
+        tree_size: u32,
+        change_goes_to: Option<PublicAddress>,
+        intended_transaction_fee: u64,
+    ) -> Result<(), TransactionError> {
+        self.add_change(spender_key, change_goes_to, intended_transaction_fee)?;
+
+        while self.spends.len() < spends {
+            let spend = SpendParams::new_dummy(self.sapling.clone(), root_hash, tree_size)?;
+            self.add_spend_proof(spend, 0);
+        }
 
-        self.increment_binding_signature_key(&proof.value_commitment_randomness, true);
-        self.increment_binding_verification_key(&proof.merkle_note.value_commitment, true);
+        while self.receipts.len() < receipts {
+            let dummy_key = SaplingKey::generate_key();
+            let note = Note::new(dummy_key.generate_public_address(), 0, Memo::default());
+            self.receive(spender_key, &note)?;
+        }
 
-        self.receipts.push(proof);
-        self.transaction_fee -= note.value as i64;
+        let mut rng = thread_rng();
+        self.spends.shuffle(&mut rng);
+        self.receipts.shuffle(&mut rng);
 
         Ok(())
     }
@@ -142,19 +398,33 @@ impl ProposedTransaction {
     /// wouldn't accept a transaction that takes money away from them.
     ///
     /// sum(spends) - sum(outputs) - intended_transaction_fee - change = 0
-    /// aka: self.transaction_fee - intended_transaction_fee - change = 0
+    /// aka: self.value_balance - intended_transaction_fee - change = 0
     pub fn post(
         &mut self,
         spender_key: &SaplingKey,
         change_goes_to: Option<PublicAddress>,
         intended_transaction_fee: u64,
     ) -> Result<Transaction, TransactionError> {
-        let change_amount = self.transaction_fee - intended_transaction_fee as i64;
+        self.add_change(spender_key, change_goes_to, intended_transaction_fee)?;
+        self._partial_post()
+    }
+
+    /// Add a receipt for whatever the spends exceed the receipts and
+    /// intended fee by, if anything.
+    fn add_change(
+        &mut self,
+        spender_key: &SaplingKey,
+        change_goes_to: Option<PublicAddress>,
+        intended_transaction_fee: u64,
+    ) -> Result<(), TransactionError> {
+        let change_amount = self.value_balance.value() - i128::from(intended_transaction_fee);
 
         if change_amount < 0 {
             return Err(TransactionError::InvalidBalanceError);
         }
         if change_amount > 0 {
+            let change_amount =
+                u64::try_from(change_amount).map_err(|_| TransactionError::IllegalValueError)?;
             // TODO: The public address generated from the spender_key if
             // change_goes_to is None should probably be associated with a
             // known diversifier (eg: that used on other notes?)
@@ -162,14 +432,10 @@ impl ProposedTransaction {
             // would be useful yet.
             let change_address =
                 change_goes_to.unwrap_or_else(|| spender_key.generate_public_address());
-            let change_note = Note::new(
-                change_address,
-                change_amount as u64, // we checked it was positive
-                Memo::default(),
-            );
+            let change_note = Note::new(change_address, change_amount, Memo::default());
             self.receive(spender_key, &change_note)?;
         }
-        self._partial_post()
+        Ok(())
     }
 
     /// Special case for posting a miners fee transaction. Miner fee transactions
@@ -206,14 +472,105 @@ impl ProposedTransaction {
         self.expiration_sequence = expiration_sequence;
     }
 
+    /// The openings of the value commitments of the receipts added so far,
+    /// in the order the receipts will appear in the posted transaction.
+    ///
+    /// Note that posting may add a change receipt at the end, and pad_to
+    /// adds the change and shuffles the receipts, so this should be called
+    /// after either.
+    pub fn value_commitment_openings(&self) -> Vec<ValueCommitmentOpening> {
+        self.receipts
+            .iter()
+            .map(|receipt| receipt.value_commitment_opening())
+            .collect()
+    }
+
+    /// The openings of the value commitments of the receipts, each encrypted
+    /// to the owner of the receipt's note. The owner can decrypt them with
+    /// ValueCommitmentOpening::decrypt_for_owner and hand them to a third
+    /// party to prove the value of a note.
+    ///
+    /// Requires the outgoing view key of the spender_key used to create the
+    /// receipts, to recover the shared secret of each note.
+    pub fn encrypted_value_commitment_openings(
+        &self,
+        outgoing_view_key: &OutgoingViewKey,
+    ) -> Result<Vec<[u8; ENCRYPTED_OPENING_SIZE]>, NoteError> {
+        self.receipts
+            .iter()
+            .map(|receipt| {
+                let shared_secret = receipt
+                    .merkle_note
+                    .note_encryption_key_for_spender(outgoing_view_key)?;
+                Ok(receipt.value_commitment_opening().encrypt(&shared_secret))
+            })
+            .collect()
+    }
+
+    /// Allow or reject spending the same note twice or creating the same
+    /// note twice in this transaction. Duplicates are rejected by default.
+    pub fn set_allow_duplicates(&mut self, allow_duplicates: bool) {
+        self.allow_duplicates = allow_duplicates;
+    }
+
+    /// Check the transaction for problems that would make it invalid or
+    /// that indicate a bug in the caller, before spending time on signing.
+    ///
+    /// Unless duplicates are allowed, this rejects spends with the same
+    /// nullifier and receipts with the same note commitment, including
+    /// proofs that were added with add_spend_proof or add_receipt_proof.
+    /// Also checks that the values are consistent with the proofs.
+    ///
+    /// Called when the transaction is posted, but can be called at any time.
+    pub fn sanity_check(&self) -> Result<(), TransactionError> {
+        if !self.allow_duplicates {
+            let mut nullifiers = HashSet::with_capacity(self.spends.len());
+            if !self
+                .spends
+                .iter()
+                .all(|spend| nullifiers.insert(spend.nullifier.0))
+            {
+                return Err(TransactionError::DuplicateSpendError);
+            }
+
+            let mut commitments = HashSet::with_capacity(self.receipts.len());
+            if !self
+                .receipts
+                .iter()
+                .all(|receipt| commitments.insert(receipt.merkle_note.note_commitment.to_bytes()))
+            {
+                return Err(TransactionError::DuplicateReceiptError);
+            }
+        }
+
+        self.check_value_consistency()
+    }
+
     // post transaction without much validation.
     fn _partial_post(&self) -> Result<Transaction, TransactionError> {
-        self.check_value_consistency()?;
+        let data_to_sign = self.transaction_signature_hash();
+        let mut spend_signatures = Vec::with_capacity(self.spends.len());
+        for spend in &self.spends {
+            spend_signatures.push(spend.sign(&data_to_sign)?);
+        }
+        self.post_with_spend_signatures(&spend_signatures)
+    }
+
+    /// Post the transaction with spend authorizing signatures made by the
+    /// spenders, one for each spend in the order they were added.
+    pub(crate) fn post_with_spend_signatures(
+        &self,
+        spend_signatures: &[Signature],
+    ) -> Result<Transaction, TransactionError> {
+        if spend_signatures.len() != self.spends.len() {
+            return Err(TransactionError::SigningError);
+        }
+        self.sanity_check()?;
         let data_to_sign = self.transaction_signature_hash();
         let binding_signature = self.binding_signature()?;
         let mut spend_proofs = Vec::with_capacity(self.spends.len());
-        for spend in &self.spends {
-            spend_proofs.push(spend.post(&data_to_sign)?);
+        for (spend, signature) in self.spends.iter().zip(spend_signatures) {
+            spend_proofs.push(spend.post_with_signature(&data_to_sign, *signature)?);
         }
         let mut receipt_proofs = Vec::with_capacity(self.receipts.len());
         for receipt in &self.receipts {
@@ -222,7 +579,7 @@ impl ProposedTransaction {
         Ok(Transaction {
             sapling: self.sapling.clone(),
             expiration_sequence: self.expiration_sequence,
-            transaction_fee: self.transaction_fee,
+            transaction_fee: self.value_balance.transaction_fee()?,
             spends: spend_proofs,
             receipts: receipt_proofs,
             binding_signature,
@@ -234,29 +591,47 @@ impl ProposedTransaction {
     ///
     /// This is called during final posting of the transaction
     ///
-    fn transaction_signature_hash(&self) -> [u8; 32] {
-        let mut hasher = Blake2b::new()
-            .hash_length(32)
-            .personal(SIGNATURE_HASH_PERSONALIZATION)
-            .to_state();
-
-        hasher.update(TRANSACTION_SIGNATURE_VERSION);
-        hasher
+    pub(crate) fn transaction_signature_hash(&self) -> [u8; 32] {
+        let mut hasher = SignatureHasher::new(
+            TransactionVersion::CURRENT,
+            self.expiration_sequence,
+            self.value_balance.value() as i64,
+        );
+        for spend in self.spends.iter() {
+            hasher
+                .add_spend(spend)
+                .expect("spends are added before receipts");
+        }
+        for receipt in self.receipts.iter() {
+            hasher
+                .add_receipt(receipt)
+                .expect("should be able to hash receipt");
+        }
+        hasher.finalize()
+    }
+
+    /// The exact bytes that are hashed to calculate the signature hash. This
+    /// is the same data as Transaction::signature_hash_preimage will return
+    /// once the transaction is posted.
+    pub fn signature_hash_preimage(&self) -> Vec<u8> {
+        let mut preimage = vec![];
+        preimage.extend_from_slice(TransactionVersion::CURRENT.signature_version_bytes());
+        preimage
             .write_u32::<LittleEndian>(self.expiration_sequence)
             .unwrap();
-        hasher
-            .write_i64::<LittleEndian>(self.transaction_fee)
+        // A balance that doesn't fit is rejected by sanity_check before
+        // anything is signed, so truncating it here can't affect a posted
+        // transaction.
+        preimage
+            .write_i64::<LittleEndian>(self.value_balance.value() as i64)
             .unwrap();
         for spend in self.spends.iter() {
-            spend.serialize_signature_fields(&mut hasher).unwrap();
+            spend.serialize_signature_fields(&mut preimage).unwrap();
         }
         for receipt in self.receipts.iter() {
-            receipt.serialize_signature_fields(&mut hasher).unwrap();
+            receipt.serialize_signature_fields(&mut preimage).unwrap();
         }
-
-        let mut hash_result = [0; 32];
-        hash_result[..].clone_from_slice(hasher.finalize().as_ref());
-        hash_result
+        preimage
     }
 
     /// Confirm that balance of input and receipt values is consistent with
@@ -274,7 +649,8 @@ impl ProposedTransaction {
         let private_key = PrivateKey(self.binding_signature_key);
         let public_key =
             PublicKey::from_private(&private_key, VALUE_COMMITMENT_RANDOMNESS_GENERATOR);
-        let mut value_balance_point = value_balance_to_point(self.transaction_fee as i64)?;
+        let mut value_balance_point =
+            value_balance_to_point(self.value_balance.transaction_fee()?)?;
 
         value_balance_point = -value_balance_point;
         let mut calculated_public_key = self.binding_verification_key;
@@ -307,6 +683,44 @@ impl ProposedTransaction {
         ))
     }
 
+    /// Reject spending a note that is already spent in this transaction,
+    /// before spending time on the proof.
+    fn check_duplicate_spend(
+        &self,
+        spender_key: &SaplingKey,
+        note: &Note,
+        witness: &dyn WitnessTrait,
+    ) -> Result<(), SaplingProofError> {
+        if self.allow_duplicates {
+            return Ok(());
+        }
+
+        let nullifier = note.nullifier(spender_key, witness_position(witness));
+        if self.spends.iter().any(|spend| spend.nullifier == nullifier) {
+            return Err(SaplingProofError::DuplicateSpend);
+        }
+
+        Ok(())
+    }
+
+    /// Reject creating a note that is already created in this transaction.
+    fn check_duplicate_receipt(&self, note: &Note) -> Result<(), SaplingProofError> {
+        if self.allow_duplicates {
+            return Ok(());
+        }
+
+        let commitment = note.commitment_point();
+        if self
+            .receipts
+            .iter()
+            .any(|receipt| receipt.merkle_note.note_commitment == commitment)
+        {
+            return Err(SaplingProofError::DuplicateReceipt);
+        }
+
+        Ok(())
+    }
+
     /// Helper method to encapsulate the verbose way incrementing the signature
     /// key works
     fn increment_binding_signature_key(&mut self, value: &jubjub::Fr, negate: bool) {
@@ -364,23 +778,51 @@ impl Transaction {
     /// Load a Transaction from a Read implementation (e.g: socket, file)
     /// This is the main entry-point when reconstructing a serialized transaction
     /// for verifying.
-    pub fn read<R: io::Read>(
+    pub fn read<R: io::Read>(sapling: Arc<Sapling>, reader: R) -> Result<Self, TransactionError> {
+        Transaction::read_with_context(sapling, reader, &VerificationContext::default())
+    }
+
+    /// Load a Transaction, failing with TooManySpendsError or
+    /// TooManyReceiptsError before reading any descriptions if it has more
+    /// than the context allows. Use this for transactions from untrusted
+    /// sources so a forged count can't trigger a huge allocation.
+    pub fn read_with_context<R: io::Read>(
         sapling: Arc<Sapling>,
         mut reader: R,
+        context: &VerificationContext,
     ) -> Result<Self, TransactionError> {
         let num_spends = reader.read_u64::<LittleEndian>()?;
         let num_receipts = reader.read_u64::<LittleEndian>()?;
+        if context
+            .max_spends
+            .map_or(false, |max| num_spends > max as u64)
+        {
+            return Err(TransactionError::TooManySpendsError);
+        }
+        if context
+            .max_receipts
+            .map_or(false, |max| num_receipts > max as u64)
+        {
+            return Err(TransactionError::TooManyReceiptsError);
+        }
         let transaction_fee = reader.read_i64::<LittleEndian>()?;
         let expiration_sequence = reader.read_u32::<LittleEndian>()?;
-        let mut spends = Vec::with_capacity(num_spends as usize);
-        let mut receipts = Vec::with_capacity(num_receipts as usize);
+        // Without limits in the context, the counts are only trusted as far
+        // as what could fit in a network message
+        let mut spends = Vec::with_capacity(
+            num_spends.min(u64::from(MAX_MESSAGE_SIZE) / SPEND_SIZE as u64) as usize,
+        );
+        let mut receipts = Vec::with_capacity(
+            num_receipts.min(u64::from(MAX_MESSAGE_SIZE) / RECEIPT_SIZE as u64) as usize,
+        );
         for _ in 0..num_spends {
             spends.push(SpendProof::read(&mut reader)?);
         }
         for _ in 0..num_receipts {
             receipts.push(ReceiptProof::read(&mut reader)?);
         }
-        let binding_signature = Signature::read(&mut reader)?;
+        let binding_signature =
+            read_canonical_signature(&mut reader).map_err(SaplingProofError::from)?;
 
         Ok(Transaction {
             sapling,
@@ -410,6 +852,15 @@ impl Transaction {
         Ok(())
     }
 
+    /// Hash of the serialized transaction, including its signatures. This is
+    /// the hash nodes use to identify the transaction.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut bytes = vec![];
+        self.write(&mut bytes)
+            .expect("should be able to serialize transaction");
+        *blake3::hash(&bytes).as_bytes()
+    }
+
     /// Validate the transaction. Confirms that:
     ///  *  Each of the spend proofs has the inputs it says it does
     ///  *  Each of the receipt proofs has the inputs it says it has
@@ -418,34 +869,89 @@ impl Transaction {
     ///     containing those proofs (and only those proofs)
     ///
     pub fn verify(&self) -> Result<(), TransactionError> {
+        self.verify_with_context(&VerificationContext::default())
+    }
+
+    /// Validate the transaction as in verify, additionally enforcing the
+    /// consensus rules in the provided context.
+    pub fn verify_with_context(
+        &self,
+        context: &VerificationContext,
+    ) -> Result<(), TransactionError> {
+        context.check(self)?;
+
+        let binding_verification_key = self.verify_proofs(context)?;
+
+        let hash_to_verify_signature = self.transaction_signature_hash();
+
+        for spend in self.spends.iter() {
+            spend.verify_signature(&hash_to_verify_signature)?;
+        }
+
+        self.verify_binding_signature(&binding_verification_key)?;
+
+        Ok(())
+    }
+
+    /// Check the root hash of every spend against the chain's note tree, as
+    /// in SpendProof::verify_against_tree. This isn't part of verify, since
+    /// the history of the note tree lives outside of this crate.
+    pub fn verify_against_tree<F: Fn(u32) -> Option<Scalar>>(
+        &self,
+        tree_root_provider: F,
+    ) -> Result<(), TransactionError> {
+        for spend in self.spends.iter() {
+            spend.verify_against_tree(&tree_root_provider)?;
+        }
+
+        Ok(())
+    }
+
+    /// Verify the bellman proofs on each of the spends and receipts, and
+    /// accumulate their value commitments into the key that the binding
+    /// signature will be checked against.
+    ///
+    /// Signatures are not checked here; see verify and
+    /// batch_verify_transactions. Proofs found in the context's
+    /// spend_proof_cache or receipt_proof_cache are skipped.
+    fn verify_proofs(
+        &self,
+        context: &VerificationContext,
+    ) -> Result<ExtendedPoint, TransactionError> {
         // Context to accumulate a signature of all the spends and outputs and
         // guarantee they are part of this transaction, unmodified.
         let mut binding_verification_key = ExtendedPoint::identity();
 
         for spend in self.spends.iter() {
-            spend.verify_proof(&self.sapling)?;
+            match &context.spend_proof_cache {
+                Some(cache) if cache.contains(spend) => {}
+                Some(cache) => {
+                    spend.verify_proof(&self.sapling)?;
+                    cache.insert(spend);
+                }
+                None => spend.verify_proof(&self.sapling)?,
+            }
             let mut tmp = spend.value_commitment;
             tmp += binding_verification_key;
             binding_verification_key = tmp;
         }
 
         for receipt in self.receipts.iter() {
-            receipt.verify_proof(&self.sapling)?;
+            match &context.receipt_proof_cache {
+                Some(cache) if cache.contains(receipt) => {}
+                Some(cache) => {
+                    receipt.verify_proof(&self.sapling)?;
+                    cache.insert(receipt);
+                }
+                None => receipt.verify_proof(&self.sapling)?,
+            }
             let mut tmp = receipt.merkle_note.value_commitment;
             tmp = -tmp;
             tmp += binding_verification_key;
             binding_verification_key = tmp;
         }
 
-        let hash_to_verify_signature = self.transaction_signature_hash();
-
-        for spend in self.spends.iter() {
-            spend.verify_signature(&hash_to_verify_signature)?;
-        }
-
-        self.verify_binding_signature(&binding_verification_key)?;
-
-        Ok(())
+        Ok(binding_verification_key)
     }
 
     /// Get an iterator over the spends in this transaction. Each spend
@@ -493,27 +999,51 @@ impl Transaction {
     /// private keys when the transaction was constructed, and will now be
     /// reconstructed to verify the signature.
     pub fn transaction_signature_hash(&self) -> [u8; 32] {
-        let mut hasher = Blake2b::new()
-            .hash_length(32)
-            .personal(SIGNATURE_HASH_PERSONALIZATION)
-            .to_state();
-        hasher.update(TRANSACTION_SIGNATURE_VERSION);
-        hasher
+        let mut hasher = SignatureHasher::new(
+            self.version(),
+            self.expiration_sequence,
+            self.transaction_fee,
+        );
+        for spend in self.spends.iter() {
+            hasher
+                .add_spend_proof(spend)
+                .expect("spends are added before receipts");
+        }
+        for receipt in self.receipts.iter() {
+            hasher
+                .add_receipt_proof(receipt)
+                .expect("should be able to hash receipt");
+        }
+        hasher.finalize()
+    }
+
+    /// The exact bytes that are hashed to calculate the signature hash, so
+    /// that the hash can be reproduced and audited outside of this library.
+    /// Hash them with blake2b-256, personalized with
+    /// TransactionVersion::signature_hash_personalization for the
+    /// transaction's version.
+    pub fn signature_hash_preimage(&self) -> Vec<u8> {
+        let mut preimage = vec![];
+        preimage.extend_from_slice(self.version().signature_version_bytes());
+        preimage
             .write_u32::<LittleEndian>(self.expiration_sequence)
             .unwrap();
-        hasher
+        preimage
             .write_i64::<LittleEndian>(self.transaction_fee)
             .unwrap();
         for spend in self.spends.iter() {
-            spend.serialize_signature_fields(&mut hasher).unwrap();
+            spend.serialize_signature_fields(&mut preimage).unwrap();
         }
         for receipt in self.receipts.iter() {
-            receipt.serialize_signature_fields(&mut hasher).unwrap();
+            receipt.serialize_signature_fields(&mut preimage).unwrap();
         }
+        preimage
+    }
 
-        let mut hash_result = [0; 32];
-        hash_result[..].clone_from_slice(hasher.finalize().as_ref());
-        hash_result
+    /// The version of the transaction format. Only one version exists so
+    /// far, and it isn't stored in the serialized transaction.
+    pub fn version(&self) -> TransactionVersion {
+        TransactionVersion::V0
     }
 
     /// Confirm that this transaction was signed by the values it contains.
@@ -522,6 +1052,28 @@ impl Transaction {
         &self,
         binding_verification_key: &ExtendedPoint,
     ) -> Result<(), TransactionError> {
+        let (public_key, data_to_verify_signature) = self
+            .binding_signature_data(binding_verification_key, &self.transaction_signature_hash())?;
+
+        if !public_key.verify(
+            &data_to_verify_signature,
+            &self.binding_signature,
+            VALUE_COMMITMENT_RANDOMNESS_GENERATOR,
+        ) {
+            Err(TransactionError::VerificationFailed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calculate the public key and message the binding signature should
+    /// verify against, given the binding verification key accumulated from
+    /// the spend and receipt value commitments.
+    fn binding_signature_data(
+        &self,
+        binding_verification_key: &ExtendedPoint,
+        signature_hash: &[u8; 32],
+    ) -> Result<(PublicKey, [u8; 64]), TransactionError> {
         let mut value_balance_point = value_balance_to_point(self.transaction_fee)?;
         value_balance_point = -value_balance_point;
 
@@ -531,18 +1083,83 @@ impl Transaction {
 
         let mut data_to_verify_signature = [0; 64];
         data_to_verify_signature[..32].copy_from_slice(&public_key.0.to_bytes());
-        (&mut data_to_verify_signature[32..]).copy_from_slice(&self.transaction_signature_hash());
+        (&mut data_to_verify_signature[32..]).copy_from_slice(signature_hash);
 
-        if !public_key.verify(
-            &data_to_verify_signature,
-            &self.binding_signature,
-            VALUE_COMMITMENT_RANDOMNESS_GENERATOR,
-        ) {
-            Err(TransactionError::VerificationFailed)
-        } else {
-            Ok(())
+        Ok((public_key, data_to_verify_signature))
+    }
+}
+
+/// Validate a group of transactions, such as the transactions in a block.
+///
+/// This confirms the same things as Transaction::verify, but rather than
+/// checking each redjubjub signature one at a time, the spend authorizing
+/// signatures and the binding signatures are each checked with a single
+/// random linear combination. This is significantly cheaper when verifying
+/// many spends at once.
+///
+/// The consensus rules in the context are applied to every transaction.
+///
+/// If the batch fails, it is not possible to tell which transaction was at
+/// fault. Callers that need to know should fall back to
+/// Transaction::verify_with_context.
+pub fn batch_verify_transactions<'a>(
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+    context: &VerificationContext,
+) -> Result<(), TransactionError> {
+    let mut spend_signatures = vec![];
+    let mut binding_signatures = vec![];
+
+    for transaction in transactions {
+        context.check(transaction)?;
+
+        let binding_verification_key = transaction.verify_proofs(context)?;
+        let hash_to_verify_signature = transaction.transaction_signature_hash();
+
+        for spend in transaction.spends.iter() {
+            if spend.randomized_public_key.0.is_small_order().into() {
+                return Err(TransactionError::VerificationFailed);
+            }
+            spend_signatures.push((
+                PublicKey(spend.randomized_public_key.0),
+                spend.signature_data(&hash_to_verify_signature),
+                spend.authorizing_signature,
+            ));
         }
+
+        let (public_key, data_to_verify_signature) = transaction
+            .binding_signature_data(&binding_verification_key, &hash_to_verify_signature)?;
+        binding_signatures.push((
+            public_key,
+            data_to_verify_signature,
+            transaction.binding_signature,
+        ));
     }
+
+    if !batch_verify_signatures(&spend_signatures, SPENDING_KEY_GENERATOR)
+        || !batch_verify_signatures(&binding_signatures, VALUE_COMMITMENT_RANDOMNESS_GENERATOR)
+    {
+        return Err(TransactionError::VerificationFailed);
+    }
+
+    Ok(())
+}
+
+/// Helper to run redjubjub batch verification over (public key, message,
+/// signature) tuples that all share the same generator.
+fn batch_verify_signatures(
+    signatures: &[(PublicKey, [u8; 64], Signature)],
+    generator: SubgroupPoint,
+) -> bool {
+    let batch: Vec<BatchEntry> = signatures
+        .iter()
+        .map(|(public_key, data, signature)| BatchEntry {
+            vk: PublicKey(public_key.0),
+            msg: &data[..],
+            sig: *signature,
+        })
+        .collect();
+
+    redjubjub::batch_verify(&mut OsRng, &batch, generator)
 }
 
 // Convert the integer value to a point on the Jubjub curve, accounting for
@@ -0,0 +1,95 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::Transaction;
+
+use std::collections::HashSet;
+
+/// Rules for when a transaction in the mempool may be replaced by another
+/// transaction spending some of the same notes, usually to bump its fee.
+#[derive(Clone, Debug)]
+pub struct RbfPolicy {
+    /// How much the fee rate (fee per serialized byte) of the replacement
+    /// must exceed the fee rate of the original, in percent.
+    pub min_fee_rate_increase_percent: u64,
+
+    /// How much the fee of the replacement must exceed the fee of the
+    /// original, so replacements pay for the bandwidth they use.
+    pub min_fee_increase: u64,
+
+    /// The sequence of the chain head. If set, replacements that expire
+    /// within min_blocks_until_expiration of it are rejected, since they
+    /// would likely expire before being mined.
+    pub sequence: Option<u32>,
+
+    pub min_blocks_until_expiration: u32,
+}
+
+impl Default for RbfPolicy {
+    fn default() -> Self {
+        RbfPolicy {
+            min_fee_rate_increase_percent: 10,
+            min_fee_increase: 1,
+            sequence: None,
+            min_blocks_until_expiration: 0,
+        }
+    }
+}
+
+/// Check whether new may replace old in the mempool.
+///
+/// The transactions must spend at least one of the same notes, otherwise new
+/// isn't a replacement and both can be in the mempool. The replacement must
+/// pay a higher fee and fee rate as set by the policy, and must not expire
+/// too soon. Neither transaction's proofs or signatures are verified.
+pub fn replacement_allowed(old: &Transaction, new: &Transaction, policy: &RbfPolicy) -> bool {
+    let old_nullifiers: HashSet<[u8; 32]> = old.iter_spends().map(|s| s.nullifier().0).collect();
+    if !new
+        .iter_spends()
+        .any(|s| old_nullifiers.contains(&s.nullifier().0))
+    {
+        return false;
+    }
+
+    // Miner's fee transactions are never in the mempool
+    if old.transaction_fee() < 0 || new.transaction_fee() < 0 {
+        return false;
+    }
+    let old_fee = old.transaction_fee() as u64;
+    let new_fee = new.transaction_fee() as u64;
+
+    match old_fee.checked_add(policy.min_fee_increase) {
+        Some(required_fee) if new_fee >= required_fee => {}
+        _ => return false,
+    }
+
+    // Compare new_fee / new_size >= old_fee / old_size * (100 + increase) / 100
+    // without division
+    let old_size = serialized_size(old) as u128;
+    let new_size = serialized_size(new) as u128;
+    let increase = policy.min_fee_rate_increase_percent as u128;
+    if (new_fee as u128) * old_size * 100 < (old_fee as u128) * new_size * (100 + increase) {
+        return false;
+    }
+
+    if let Some(sequence) = policy.sequence {
+        let expiration_sequence = new.expiration_sequence();
+        if expiration_sequence != 0
+            && (expiration_sequence as u64)
+                <= sequence as u64 + policy.min_blocks_until_expiration as u64
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn serialized_size(transaction: &Transaction) -> usize {
+    let mut bytes = vec![];
+    transaction
+        .write(&mut bytes)
+        .expect("should be able to serialize transaction");
+    bytes.len()
+}
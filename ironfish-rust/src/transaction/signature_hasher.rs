@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::TransactionVersion;
+use crate::{
+    errors::TransactionError,
+    receiving::{ReceiptParams, ReceiptProof},
+    spending::{SpendParams, SpendProof},
+};
+use blake2b_simd::{Params as Blake2b, State};
+
+/// Calculates a transaction's signature hash as its descriptions are
+/// finished, rather than from the whole signature hash preimage at once.
+///
+/// A consolidation transaction with thousands of spends can start hashing
+/// while later spends are still being proven, without holding the preimage
+/// in memory. The result is the same hash as
+/// Transaction::transaction_signature_hash.
+///
+/// Every spend has to be added before any receipt, in the order they appear
+/// in the transaction.
+pub struct SignatureHasher {
+    state: State,
+    adding_receipts: bool,
+}
+
+impl SignatureHasher {
+    /// Start hashing a transaction with the given version, expiration
+    /// sequence, and fee, which come before the descriptions in the
+    /// preimage.
+    pub fn new(
+        version: TransactionVersion,
+        expiration_sequence: u32,
+        transaction_fee: i64,
+    ) -> SignatureHasher {
+        let mut state = Blake2b::new()
+            .hash_length(32)
+            .personal(version.signature_hash_personalization())
+            .to_state();
+        state.update(version.signature_version_bytes());
+        state.update(&expiration_sequence.to_le_bytes());
+        state.update(&transaction_fee.to_le_bytes());
+
+        SignatureHasher {
+            state,
+            adding_receipts: false,
+        }
+    }
+
+    /// Add a spend that has been proven but not yet signed. Fails with
+    /// IllegalValueError if a receipt has already been added.
+    pub fn add_spend(&mut self, spend: &SpendParams) -> Result<(), TransactionError> {
+        if self.adding_receipts {
+            return Err(TransactionError::IllegalValueError);
+        }
+        spend.serialize_signature_fields(&mut self.state)?;
+        Ok(())
+    }
+
+    /// Add a spend from a posted transaction. Fails with IllegalValueError
+    /// if a receipt has already been added.
+    pub fn add_spend_proof(&mut self, spend: &SpendProof) -> Result<(), TransactionError> {
+        if self.adding_receipts {
+            return Err(TransactionError::IllegalValueError);
+        }
+        spend.serialize_signature_fields(&mut self.state)?;
+        Ok(())
+    }
+
+    /// Add a receipt that has been proven.
+    pub fn add_receipt(&mut self, receipt: &ReceiptParams) -> Result<(), TransactionError> {
+        self.adding_receipts = true;
+        receipt.serialize_signature_fields(&mut self.state)?;
+        Ok(())
+    }
+
+    /// Add a receipt from a posted transaction.
+    pub fn add_receipt_proof(&mut self, receipt: &ReceiptProof) -> Result<(), TransactionError> {
+        self.adding_receipts = true;
+        receipt.serialize_signature_fields(&mut self.state)?;
+        Ok(())
+    }
+
+    /// The signature hash of everything that was added.
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut hash_result = [0; 32];
+        hash_result[..].clone_from_slice(self.state.finalize().as_ref());
+        hash_result
+    }
+}
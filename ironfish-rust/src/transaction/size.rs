@@ -0,0 +1,44 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::{merkle_note::ENCRYPTED_SHARED_KEY_SIZE, note::ENCRYPTED_NOTE_SIZE, serializing::aead};
+
+/// Serialized size of a groth16 proof
+const PROOF_SIZE: usize = 192;
+
+/// Serialized size of the counts of spends and receipts, the fee and the
+/// expiration sequence at the start of a transaction
+pub const TRANSACTION_HEADER_SIZE: usize = 8 + 8 + 8 + 4;
+
+/// Serialized size of a spend: the proof, value commitment, randomized
+/// public key, root hash, tree size, nullifier and authorizing signature
+pub const SPEND_SIZE: usize = PROOF_SIZE + 32 + 32 + 32 + 4 + 32 + 64;
+
+/// Serialized size of a receipt: the proof, value commitment, note
+/// commitment, ephemeral public key, encrypted note and encrypted note
+/// encryption keys
+pub const RECEIPT_SIZE: usize = PROOF_SIZE
+    + 32
+    + 32
+    + 32
+    + (ENCRYPTED_NOTE_SIZE + aead::MAC_SIZE)
+    + (ENCRYPTED_SHARED_KEY_SIZE + aead::MAC_SIZE);
+
+/// Serialized size of the binding signature at the end of a transaction
+pub const BINDING_SIGNATURE_SIZE: usize = 64;
+
+/// The serialized size of a transaction with the given number of spends and
+/// receipts. Every spend and receipt has a fixed size, so this is exact and
+/// doesn't require building any proofs.
+///
+/// Remember to count the change receipt, if the transaction will have one.
+pub fn estimate_transaction_size(spends: usize, receipts: usize) -> usize {
+    TRANSACTION_HEADER_SIZE + spends * SPEND_SIZE + receipts * RECEIPT_SIZE + BINDING_SIGNATURE_SIZE
+}
+
+/// The fee for a transaction with the given number of spends and receipts
+/// at a fee rate in ore per serialized byte, as used by RbfPolicy.
+pub fn estimate_fee(spends: usize, receipts: usize, fee_rate: u64) -> u64 {
+    (estimate_transaction_size(spends, receipts) as u64).saturating_mul(fee_rate)
+}
@@ -0,0 +1,227 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::{receiving::ReceiptProof, spending::SpendProof};
+use blake2b_simd::Params as Blake2b;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryInto,
+    sync::Mutex,
+};
+
+const SPEND_PROOF_CACHE_PERSONALIZATION: &[u8; 16] = b"Bnsp proof cache";
+const RECEIPT_PROOF_CACHE_PERSONALIZATION: &[u8; 16] = b"Bnrp proof cache";
+
+/// Counters describing how a SpendProofCache or ReceiptProofCache has been
+/// used
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProofCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// Maps the hash of a verified proof to the nullifier it spends, if any
+    entries: HashMap<[u8; 32], Option<[u8; 32]>>,
+    /// Hashes in the order they were inserted, oldest first
+    order: VecDeque<[u8; 32]>,
+    stats: ProofCacheStats,
+}
+
+/// Bounded set of hashes of verified proofs, shared by the spend and
+/// receipt caches.
+#[derive(Debug)]
+struct ProofCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl ProofCache {
+    fn new(capacity: usize) -> Self {
+        ProofCache {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    fn contains(&self, key: &[u8; 32]) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let found = state.entries.contains_key(key);
+        if found {
+            state.stats.hits += 1;
+        } else {
+            state.stats.misses += 1;
+        }
+        found
+    }
+
+    fn insert(&self, key: [u8; 32], nullifier: Option<[u8; 32]>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.entries.contains_key(&key) {
+            return;
+        }
+
+        while state.entries.len() >= self.capacity {
+            match state.order.pop_front() {
+                Some(oldest) => {
+                    if state.entries.remove(&oldest).is_some() {
+                        state.stats.evictions += 1;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        state.entries.insert(key, nullifier);
+        state.order.push_back(key);
+    }
+
+    fn invalidate(&self, nullifier: &[u8; 32]) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .entries
+            .retain(|_, cached_nullifier| cached_nullifier.as_ref() != Some(nullifier));
+
+        let CacheState { entries, order, .. } = &mut *state;
+        order.retain(|key| entries.contains_key(key));
+    }
+
+    fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.order.clear();
+    }
+
+    fn stats(&self) -> ProofCacheStats {
+        let state = self.state.lock().unwrap();
+        ProofCacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+}
+
+/// A bounded cache of spend proofs that have already been verified.
+///
+/// During a reorg the same spends are verified again as blocks are
+/// reconnected, and proof verification is by far the most expensive part of
+/// verifying a transaction. Entries are keyed by a hash of the proof and all
+/// of its public inputs, including the nullifier and randomized public key,
+/// so a cached result only applies to an identical spend.
+///
+/// Only successful verifications are cached. The oldest entries are evicted
+/// once the cache is full.
+#[derive(Debug)]
+pub struct SpendProofCache {
+    cache: ProofCache,
+}
+
+impl SpendProofCache {
+    pub fn new(capacity: usize) -> Self {
+        SpendProofCache {
+            cache: ProofCache::new(capacity),
+        }
+    }
+
+    /// Whether the spend's proof is known to be valid
+    pub(crate) fn contains(&self, spend: &SpendProof) -> bool {
+        self.cache.contains(&spend_cache_key(spend))
+    }
+
+    /// Record that the spend's proof is valid
+    pub(crate) fn insert(&self, spend: &SpendProof) {
+        self.cache
+            .insert(spend_cache_key(spend), Some(spend.nullifier().0));
+    }
+
+    /// Forget every cached spend of the given nullifier
+    pub fn invalidate(&self, nullifier: &[u8; 32]) {
+        self.cache.invalidate(nullifier);
+    }
+
+    /// Forget every cached spend
+    pub fn clear(&self) {
+        self.cache.clear();
+    }
+
+    pub fn stats(&self) -> ProofCacheStats {
+        self.cache.stats()
+    }
+}
+
+/// A bounded cache of receipt proofs that have already been verified, so
+/// that a transaction seen first in the mempool and then in a block, or
+/// reconnected during a reorg, doesn't have its receipts proven again.
+///
+/// Entries are keyed by a hash of the proof and the whole merkle note,
+/// which holds every public input of the proof. Receipts have no signature
+/// of their own, so the cached result doesn't depend on the rest of the
+/// transaction.
+#[derive(Debug)]
+pub struct ReceiptProofCache {
+    cache: ProofCache,
+}
+
+impl ReceiptProofCache {
+    pub fn new(capacity: usize) -> Self {
+        ReceiptProofCache {
+            cache: ProofCache::new(capacity),
+        }
+    }
+
+    /// Whether the receipt's proof is known to be valid
+    pub(crate) fn contains(&self, receipt: &ReceiptProof) -> bool {
+        self.cache.contains(&receipt_cache_key(receipt))
+    }
+
+    /// Record that the receipt's proof is valid
+    pub(crate) fn insert(&self, receipt: &ReceiptProof) {
+        self.cache.insert(receipt_cache_key(receipt), None);
+    }
+
+    /// Forget every cached receipt
+    pub fn clear(&self) {
+        self.cache.clear();
+    }
+
+    pub fn stats(&self) -> ProofCacheStats {
+        self.cache.stats()
+    }
+}
+
+fn spend_cache_key(spend: &SpendProof) -> [u8; 32] {
+    let mut bytes = vec![];
+    spend
+        .serialize_signature_fields(&mut bytes)
+        .expect("should be able to serialize spend");
+
+    hash_key(SPEND_PROOF_CACHE_PERSONALIZATION, &bytes)
+}
+
+fn receipt_cache_key(receipt: &ReceiptProof) -> [u8; 32] {
+    let mut bytes = vec![];
+    receipt
+        .write(&mut bytes)
+        .expect("should be able to serialize receipt");
+
+    hash_key(RECEIPT_PROOF_CACHE_PERSONALIZATION, &bytes)
+}
+
+fn hash_key(personalization: &[u8; 16], bytes: &[u8]) -> [u8; 32] {
+    Blake2b::new()
+        .hash_length(32)
+        .personal(personalization)
+        .hash(bytes)
+        .as_bytes()
+        .try_into()
+        .expect("hash has incorrect length")
+}
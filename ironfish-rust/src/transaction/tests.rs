@@ -3,16 +3,35 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 #[cfg(test)]
-use super::{ProposedTransaction, Transaction};
+use super::{
+    batch_verify_transactions, diff, estimate_fee, estimate_transaction_size, extract_events,
+    replacement_allowed, BlockVerifier, ChainEvent, MovedNoteCommitment, ProposedTransaction,
+    RbfPolicy, ReceiptProofCache, SignatureHasher, SourceId, SpendProofCache, Transaction,
+    TransactionAssembler, TransactionVersion, VerificationContext,
+};
+use crate::errors::{SaplingProofError, TransactionError};
 use crate::{
     keys::SaplingKey,
     merkle_note::NOTE_ENCRYPTION_MINER_KEYS,
     note::{Memo, Note},
+    receiving::ReceiptParams,
     sapling_bls12,
+    spending::SpendParams,
     test_util::make_fake_witness,
+    tree_snapshot::TreeSnapshot,
+    value_commitment_opening::ValueCommitmentOpening,
+    witness::WitnessTrait,
+    MerkleNoteHash,
+};
+
+use bls12_381::Scalar;
+use byteorder::{LittleEndian, WriteBytesExt};
+use zcash_primitives::{
+    constants::SPENDING_KEY_GENERATOR,
+    redjubjub::{PublicKey, Signature},
 };
 
-use zcash_primitives::redjubjub::Signature;
+use std::sync::Arc;
 
 #[test]
 fn test_transaction() {
@@ -148,3 +167,1336 @@ fn test_transaction_signature() {
     Signature::read(&mut serialized_signature[..].as_ref())
         .expect("Can deserialize back into a valid Signature");
 }
+
+#[test]
+fn test_batch_verify() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let transaction1 = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let mut miners_fee = ProposedTransaction::new(sapling);
+    let fee_note = Note::new(receiver_key.generate_public_address(), 5, Memo::default());
+    miners_fee
+        .receive(&receiver_key, &fee_note)
+        .expect("should be able to receive note");
+    let transaction2 = miners_fee
+        .post_miners_fee()
+        .expect("should be able to post miners fee");
+
+    let context = VerificationContext::default();
+    batch_verify_transactions(&[transaction1.clone(), transaction2.clone()], &context)
+        .expect("should be able to verify batch");
+    batch_verify_transactions(Vec::<Transaction>::new().iter(), &context)
+        .expect("empty batch should verify");
+
+    // Swapping the binding signatures invalidates both transactions
+    let mut bad_transaction1 = transaction1.clone();
+    let mut bad_transaction2 = transaction2.clone();
+    bad_transaction1.binding_signature = transaction2.binding_signature;
+    bad_transaction2.binding_signature = transaction1.binding_signature;
+    assert!(batch_verify_transactions(&[bad_transaction1, bad_transaction2], &context).is_err());
+
+    // A spend signed for a different transaction fails the batch
+    let mut bad_transaction = transaction1.clone();
+    bad_transaction.expiration_sequence = 1337;
+    assert!(batch_verify_transactions(&[transaction1, bad_transaction], &context).is_err());
+}
+
+#[test]
+fn test_block_verifier() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let transaction1 = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let mut miners_fee = ProposedTransaction::new(sapling.clone());
+    let fee_note = Note::new(receiver_key.generate_public_address(), 5, Memo::default());
+    miners_fee
+        .receive(&receiver_key, &fee_note)
+        .expect("should be able to receive note");
+    let transaction2 = miners_fee
+        .post_miners_fee()
+        .expect("should be able to post miners fee");
+
+    let cache = Arc::new(SpendProofCache::new(10));
+    let context = VerificationContext {
+        spend_proof_cache: Some(cache.clone()),
+        ..VerificationContext::default()
+    };
+
+    let mut verifier = BlockVerifier::new(sapling.clone(), context.clone());
+    verifier
+        .add_transaction(&transaction2)
+        .expect("should accept miners fee");
+    verifier
+        .add_transaction(&transaction1)
+        .expect("should accept transaction");
+    assert_eq!(verifier.transaction_count(), 2);
+
+    // Spending the same note twice in a block is rejected immediately
+    assert!(matches!(
+        verifier.add_transaction(&transaction1),
+        Err(TransactionError::DuplicateSpendError)
+    ));
+    assert_eq!(verifier.transaction_count(), 2);
+    verifier.finalize().expect("block should verify");
+    assert_eq!(cache.stats().entries, 1);
+
+    // Signatures are checked when the transaction is added
+    let mut verifier = BlockVerifier::new(sapling.clone(), context);
+    let mut bad_transaction = transaction1;
+    bad_transaction.expiration_sequence = 1337;
+    assert!(verifier.add_transaction(&bad_transaction).is_err());
+    assert_eq!(verifier.transaction_count(), 0);
+    BlockVerifier::new(sapling.clone(), VerificationContext::default())
+        .finalize()
+        .expect("empty block should verify");
+}
+
+#[test]
+fn test_large_note_values() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    // The balance of a u64::MAX spend and a small receipt doesn't fit in an
+    // i64, but the change brings it back in range
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    let in_note = Note::new(
+        spender_key.generate_public_address(),
+        u64::MAX,
+        Memo::default(),
+    );
+    let out_note = Note::new(receiver_key.generate_public_address(), 1, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    assert_eq!(
+        transaction.accounting_summary().transaction_fee,
+        u64::MAX as i128 - 1
+    );
+    let public_transaction = transaction
+        .post(&spender_key, None, 3)
+        .expect("should be able to post transaction");
+    public_transaction
+        .verify()
+        .expect("should be able to verify transaction");
+    assert_eq!(public_transaction.transaction_fee(), 3);
+
+    // A fee that can't be serialized is rejected
+    let mut transaction = ProposedTransaction::new(sapling);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    assert!(matches!(
+        transaction.post(&spender_key, None, u64::MAX),
+        Err(TransactionError::IllegalValueError)
+    ));
+}
+
+#[test]
+fn test_standalone_spend() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+
+    // The randomized public key can be calculated before the spend is built
+    let public_key_randomness = jubjub::Fr::from(1337u64);
+    let expected_public_key = PublicKey(spender_key.authorizing_key.into())
+        .randomize(public_key_randomness, SPENDING_KEY_GENERATOR);
+
+    let spend = SpendParams::with_public_key_randomness(
+        sapling.clone(),
+        spender_key.clone(),
+        &in_note,
+        &witness,
+        public_key_randomness,
+    )
+    .expect("should be able to create spend");
+    assert_eq!(spend.randomized_public_key.0, expected_public_key.0);
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    transaction.add_spend_proof(spend, in_note.value());
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let public_transaction = transaction
+        .post(&spender_key, None, 2)
+        .expect("should be able to post transaction");
+    public_transaction
+        .verify()
+        .expect("should be able to verify transaction");
+    assert_eq!(
+        public_transaction.spends()[0].randomized_public_key.0,
+        expected_public_key.0
+    );
+
+    // The witness is checked against the note
+    let other_note = Note::new(spender_key.generate_public_address(), 1, Memo::default());
+    assert!(SpendParams::with_public_key_randomness(
+        sapling,
+        spender_key,
+        &other_note,
+        &witness,
+        public_key_randomness,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_transaction_assembler() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let alice_key = SaplingKey::generate_key();
+    let bob_key = SaplingKey::generate_key();
+
+    // Each party builds their own spend and change without the other's key
+    let alice_note = Note::new(alice_key.generate_public_address(), 30, Memo::default());
+    let alice_spend = SpendParams::new(
+        sapling.clone(),
+        alice_key.clone(),
+        &alice_note,
+        &make_fake_witness(&alice_note),
+    )
+    .expect("should be able to create spend");
+    let alice_change = Note::new(alice_key.generate_public_address(), 9, Memo::default());
+    let alice_receipt = ReceiptParams::for_outgoing_view_key(
+        sapling.clone(),
+        alice_key.outgoing_view_key(),
+        &alice_change,
+    )
+    .expect("should be able to create receipt");
+
+    let bob_note = Note::new(bob_key.generate_public_address(), 20, Memo::default());
+    let bob_spend = SpendParams::new(
+        sapling.clone(),
+        bob_key.clone(),
+        &bob_note,
+        &make_fake_witness(&bob_note),
+    )
+    .expect("should be able to create spend");
+    let bob_change = Note::new(bob_key.generate_public_address(), 39, Memo::default());
+    let bob_receipt = ReceiptParams::for_outgoing_view_key(
+        sapling.clone(),
+        bob_key.outgoing_view_key(),
+        &bob_change,
+    )
+    .expect("should be able to create receipt");
+
+    let alice = SourceId::from("alice");
+    let bob = SourceId::from("bob");
+    let mut assembler = TransactionAssembler::new(sapling.clone());
+    assembler.add_spend(alice.clone(), alice_spend.without_spender_key(), 30);
+    assembler.add_receipt(alice.clone(), alice_receipt, 9);
+    assembler.add_spend(bob.clone(), bob_spend.without_spender_key(), 20);
+    assembler.add_receipt(bob.clone(), bob_receipt, 39);
+    assembler.set_expiration_sequence(10);
+
+    let summary = assembler.accounting_summary();
+    assert_eq!(summary.transaction_fee, 2);
+    assert_eq!(summary.source(&alice).unwrap().contribution(), 21);
+    assert_eq!(summary.source(&bob).unwrap().contribution(), -19);
+
+    // Each party signs the hash with the key only they hold
+    let signature_hash = assembler.signature_hash();
+    let alice_signature = alice_spend
+        .sign(&signature_hash)
+        .expect("should be able to sign spend");
+    let bob_signature = bob_spend
+        .sign(&signature_hash)
+        .expect("should be able to sign spend");
+
+    // The coordinator's copies can't sign, or be posted with someone else's
+    // signature
+    assert!(matches!(
+        alice_spend.without_spender_key().sign(&signature_hash),
+        Err(SaplingProofError::SigningError)
+    ));
+    assert!(matches!(
+        alice_spend
+            .without_spender_key()
+            .post_with_signature(&signature_hash, bob_signature),
+        Err(SaplingProofError::VerificationFailed)
+    ));
+
+    let transaction = assembler
+        .assemble(2, &[alice_signature, bob_signature])
+        .expect("should be able to assemble transaction");
+    transaction
+        .verify()
+        .expect("should be able to verify transaction");
+    assert_eq!(transaction.transaction_fee(), 2);
+    assert_eq!(transaction.expiration_sequence(), 10);
+
+    // The parties have to balance out to the fee exactly
+    let mut assembler = TransactionAssembler::new(sapling.clone());
+    let note = Note::new(alice_key.generate_public_address(), 5, Memo::default());
+    let spend = SpendParams::new(sapling, alice_key, &note, &make_fake_witness(&note))
+        .expect("should be able to create spend");
+    let signature = spend.sign(&[0; 32]).expect("should be able to sign spend");
+    assembler.add_spend(alice, spend.without_spender_key(), 5);
+    assert!(matches!(
+        assembler.assemble(1, &[signature]),
+        Err(TransactionError::InvalidBalanceError)
+    ));
+}
+
+#[test]
+fn test_verify_with_context() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    transaction.set_expiration_sequence(10);
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let context = VerificationContext {
+        sequence: Some(9),
+        max_spends: Some(1),
+        max_receipts: Some(2),
+        allow_negative_fee: false,
+        max_miners_fee: None,
+        spend_proof_cache: None,
+        receipt_proof_cache: None,
+    };
+    public_transaction
+        .verify_with_context(&context)
+        .expect("should be valid before expiration");
+
+    let expired = VerificationContext {
+        sequence: Some(10),
+        ..context.clone()
+    };
+    assert!(matches!(
+        public_transaction.verify_with_context(&expired),
+        Err(TransactionError::ExpiredError)
+    ));
+
+    let too_many_receipts = VerificationContext {
+        max_receipts: Some(1),
+        ..context.clone()
+    };
+    assert!(matches!(
+        public_transaction.verify_with_context(&too_many_receipts),
+        Err(TransactionError::TooManyReceiptsError)
+    ));
+
+    // The counts are also checked when reading untrusted transactions
+    let mut serialized = vec![];
+    public_transaction.write(&mut serialized).unwrap();
+    Transaction::read_with_context(sapling.clone(), &serialized[..], &context)
+        .expect("should read transaction within limits");
+    assert!(matches!(
+        Transaction::read_with_context(sapling.clone(), &serialized[..], &too_many_receipts),
+        Err(TransactionError::TooManyReceiptsError)
+    ));
+
+    // Without limits, a forged count fails on the missing descriptions
+    // instead of allocating for them
+    let mut forged = vec![];
+    forged.write_u64::<LittleEndian>(u64::MAX).unwrap();
+    forged.write_u64::<LittleEndian>(u64::MAX).unwrap();
+    forged.extend_from_slice(&serialized[16..28]);
+    assert!(Transaction::read(sapling.clone(), &forged[..]).is_err());
+
+    let mut miners_fee = ProposedTransaction::new(sapling);
+    let fee_note = Note::new(receiver_key.generate_public_address(), 5, Memo::default());
+    miners_fee
+        .receive(&receiver_key, &fee_note)
+        .expect("should be able to receive note");
+    let miners_fee = miners_fee
+        .post_miners_fee()
+        .expect("should be able to post miners fee");
+
+    assert!(matches!(
+        miners_fee.verify_with_context(&context),
+        Err(TransactionError::InvalidFeeError)
+    ));
+    let miners_fee_context = VerificationContext {
+        allow_negative_fee: true,
+        max_miners_fee: Some(4),
+        ..context
+    };
+    assert!(matches!(
+        miners_fee.verify_with_context(&miners_fee_context),
+        Err(TransactionError::InvalidFeeError)
+    ));
+    miners_fee
+        .verify_with_context(&VerificationContext {
+            max_miners_fee: Some(5),
+            ..miners_fee_context
+        })
+        .expect("miners fee within the limit should verify");
+}
+
+#[test]
+fn test_receive_message() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+    let receiver_address = receiver_key.generate_public_address();
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+
+    let message = b"A message that is far too long to fit in a single memo field";
+    transaction
+        .receive_message(&spender_key, &receiver_address, message)
+        .expect("should be able to attach message");
+    assert_eq!(transaction.receipts.len(), 3);
+
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+    public_transaction
+        .verify()
+        .expect("should be able to verify transaction");
+
+    let memos: Vec<Memo> = public_transaction
+        .iter_receipts()
+        .filter_map(|receipt| {
+            receipt
+                .merkle_note()
+                .decrypt_note_for_owner(receiver_key.incoming_view_key())
+                .ok()
+        })
+        .map(|note| note.memo())
+        .collect();
+    assert_eq!(memos.len(), 3);
+    assert_eq!(Memo::join_parts(&memos).unwrap(), message);
+}
+
+#[test]
+fn test_pad_to() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 30, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+
+    // The change counts toward the receipts
+    transaction
+        .pad_to(
+            &spender_key,
+            2,
+            3,
+            witness.root_hash,
+            witness.tree_size as u32,
+            None,
+            2,
+        )
+        .expect("should be able to pad transaction");
+    assert_eq!(transaction.spends.len(), 2);
+    assert_eq!(transaction.receipts.len(), 3);
+    let mut values: Vec<u64> = transaction
+        .receipts
+        .iter()
+        .map(|receipt| receipt.value)
+        .collect();
+    values.sort_unstable();
+    assert_eq!(values, vec![0, 10, 30]);
+
+    // Padding never removes anything
+    transaction
+        .pad_to(
+            &spender_key,
+            1,
+            1,
+            witness.root_hash,
+            witness.tree_size as u32,
+            None,
+            2,
+        )
+        .expect("should be able to pad transaction");
+    assert_eq!(transaction.spends.len(), 2);
+    assert_eq!(transaction.receipts.len(), 3);
+
+    // Posting with the same fee keeps the padded shape
+    let public_transaction = transaction
+        .post(&spender_key, None, 2)
+        .expect("should be able to post transaction");
+    public_transaction
+        .verify()
+        .expect("should be able to verify padded transaction");
+    assert_eq!(public_transaction.transaction_fee(), 2);
+    assert_eq!(public_transaction.spends.len(), 2);
+    assert_eq!(public_transaction.receipts.len(), 3);
+    for spend in public_transaction.iter_spends() {
+        assert_eq!(spend.root_hash(), witness.root_hash);
+    }
+
+    // The fee can't be more than the spends cover
+    let mut transaction = ProposedTransaction::new(sapling_bls12::SAPLING.clone());
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    assert!(matches!(
+        transaction.pad_to(
+            &spender_key,
+            2,
+            2,
+            witness.root_hash,
+            witness.tree_size as u32,
+            None,
+            43,
+        ),
+        Err(TransactionError::InvalidBalanceError)
+    ));
+}
+
+#[test]
+fn test_replacement_allowed() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    let other_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let other_witness = make_fake_witness(&other_note);
+
+    let post = |note: &Note, witness: &dyn WitnessTrait, fee: u64, expiration_sequence: u32| {
+        let mut transaction = ProposedTransaction::new(sapling.clone());
+        transaction
+            .spend(spender_key.clone(), note, witness)
+            .expect("should be able to spend note");
+        transaction
+            .receive(
+                &spender_key,
+                &Note::new(
+                    receiver_key.generate_public_address(),
+                    40 - fee,
+                    Memo::default(),
+                ),
+            )
+            .expect("should be able to receive note");
+        transaction.set_expiration_sequence(expiration_sequence);
+        transaction
+            .post(&spender_key, None, fee)
+            .expect("should be able to post transaction")
+    };
+
+    let original = post(&in_note, &witness, 1, 0);
+    let bumped = post(&in_note, &witness, 2, 0);
+    let same_fee = post(&in_note, &witness, 1, 0);
+    let unrelated = post(&other_note, &other_witness, 10, 0);
+    let expiring = post(&in_note, &witness, 2, 15);
+
+    let policy = RbfPolicy::default();
+    assert!(replacement_allowed(&original, &bumped, &policy));
+    assert!(!replacement_allowed(&bumped, &original, &policy));
+    assert!(!replacement_allowed(&original, &same_fee, &policy));
+    assert!(!replacement_allowed(&original, &unrelated, &policy));
+
+    let high_bump_policy = RbfPolicy {
+        min_fee_rate_increase_percent: 150,
+        ..RbfPolicy::default()
+    };
+    assert!(!replacement_allowed(&original, &bumped, &high_bump_policy));
+
+    let expiration_policy = RbfPolicy {
+        sequence: Some(10),
+        min_blocks_until_expiration: 5,
+        ..RbfPolicy::default()
+    };
+    assert!(replacement_allowed(&original, &bumped, &expiration_policy));
+    assert!(!replacement_allowed(
+        &original,
+        &expiring,
+        &expiration_policy
+    ));
+}
+
+#[test]
+fn test_spend_proof_cache() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+
+    let post = || {
+        let note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+        let witness = make_fake_witness(&note);
+        let mut transaction = ProposedTransaction::new(sapling.clone());
+        transaction
+            .spend(spender_key.clone(), &note, &witness)
+            .expect("should be able to spend note");
+        transaction
+            .post(&spender_key, None, 1)
+            .expect("should be able to post transaction")
+    };
+    let first = post();
+    let second = post();
+
+    let cache = Arc::new(SpendProofCache::new(1));
+    let context = VerificationContext {
+        spend_proof_cache: Some(cache.clone()),
+        ..VerificationContext::default()
+    };
+
+    first.verify_with_context(&context).unwrap();
+    assert_eq!(cache.stats().misses, 1);
+    assert_eq!(cache.stats().entries, 1);
+
+    first.verify_with_context(&context).unwrap();
+    assert_eq!(cache.stats().hits, 1);
+
+    // Verifying a different spend evicts the first from the full cache
+    second.verify_with_context(&context).unwrap();
+    assert_eq!(cache.stats().evictions, 1);
+    assert_eq!(cache.stats().entries, 1);
+
+    cache.invalidate(&second.spends()[0].nullifier().0);
+    assert_eq!(cache.stats().entries, 0);
+
+    first.verify_with_context(&context).unwrap();
+    assert_eq!(cache.stats().misses, 3);
+    cache.clear();
+    assert_eq!(cache.stats().entries, 0);
+}
+
+#[test]
+fn test_accounting_summary() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let mut transaction = ProposedTransaction::new(sapling);
+    let alice_key = SaplingKey::generate_key();
+    let bob_key = SaplingKey::generate_key();
+    let alice = SourceId::from("alice");
+    let bob = SourceId::from("bob");
+
+    let in_note = Note::new(alice_key.generate_public_address(), 42, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend_from_source(alice.clone(), alice_key.clone(), &in_note, &witness)
+        .expect("should be able to prove spend");
+
+    let out_note = Note::new(bob_key.generate_public_address(), 30, Memo::default());
+    transaction
+        .receive_for_source(bob.clone(), &alice_key, &out_note)
+        .expect("should be able to prove receipt");
+
+    let change_note = Note::new(alice_key.generate_public_address(), 10, Memo::default());
+    transaction
+        .receive_for_source(alice.clone(), &alice_key, &change_note)
+        .expect("should be able to prove receipt");
+
+    let summary = transaction.accounting_summary();
+    assert_eq!(summary.transaction_fee, 2);
+    assert_eq!(summary.sources.len(), 2);
+
+    let alice_accounting = summary.source(&alice).expect("alice should be tracked");
+    assert_eq!(alice_accounting.spent, 42);
+    assert_eq!(alice_accounting.received, 10);
+    assert_eq!(alice_accounting.spend_count, 1);
+    assert_eq!(alice_accounting.receipt_count, 1);
+    assert_eq!(alice_accounting.contribution(), 32);
+
+    let bob_accounting = summary.source(&bob).expect("bob should be tracked");
+    assert_eq!(bob_accounting.contribution(), -30);
+
+    assert_eq!(summary.unattributed.spend_count, 0);
+    assert_eq!(summary.unattributed.receipt_count, 0);
+
+    // Contributions add up to the fee
+    let total: i128 = summary.sources.iter().map(|(_, a)| a.contribution()).sum();
+    assert_eq!(total + summary.unattributed.contribution(), 2);
+
+    // Untagged change from post is unattributed
+    transaction
+        .post(&alice_key, None, 1)
+        .expect("should be able to post transaction");
+    let summary = transaction.accounting_summary();
+    assert_eq!(summary.unattributed.received, 1);
+    assert_eq!(summary.transaction_fee, 1);
+}
+
+#[test]
+fn test_duplicate_notes() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(spender_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to prove spend");
+    assert!(transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .is_err());
+    assert_eq!(transaction.spends.len(), 1);
+
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to prove receipt");
+    assert!(matches!(
+        transaction.receive(&spender_key, &out_note),
+        Err(SaplingProofError::DuplicateReceipt)
+    ));
+    assert_eq!(transaction.receipts.len(), 1);
+
+    transaction
+        .sanity_check()
+        .expect("should pass sanity check");
+
+    // Duplicates added while allowed are caught by sanity_check once disallowed
+    let mut transaction = ProposedTransaction::new(sapling);
+    transaction.set_allow_duplicates(true);
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to prove receipt");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to prove duplicate receipt");
+    transaction.sanity_check().expect("duplicates are allowed");
+
+    // Verification rejects them even if the builder allowed them
+    let duplicate_receipts = transaction._partial_post().expect("duplicates are allowed");
+    assert!(matches!(
+        duplicate_receipts.verify(),
+        Err(TransactionError::DuplicateReceiptError)
+    ));
+
+    transaction.set_allow_duplicates(false);
+    assert!(matches!(
+        transaction.sanity_check(),
+        Err(TransactionError::DuplicateReceiptError)
+    ));
+}
+
+#[test]
+fn test_signature_hash_preimage() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let mut transaction = ProposedTransaction::new(sapling);
+    let spender_key = SaplingKey::generate_key();
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to prove spend");
+    transaction.set_expiration_sequence(15);
+
+    let proposed_preimage = transaction.signature_hash_preimage();
+    let public_transaction = transaction
+        .post(&spender_key, None, 42)
+        .expect("should be able to post transaction");
+    let preimage = public_transaction.signature_hash_preimage();
+    assert_eq!(proposed_preimage, preimage);
+
+    // The preimage starts with the version, expiration, and fee
+    assert_eq!(preimage[0], 0);
+    assert_eq!(preimage[1..5], 15u32.to_le_bytes());
+    assert_eq!(preimage[5..13], 42i64.to_le_bytes());
+
+    // Hashing it reproduces the signature hash
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"Bnsighsh")
+        .hash(&preimage);
+    assert_eq!(
+        hash.as_bytes(),
+        &public_transaction.transaction_signature_hash()[..]
+    );
+    assert_eq!(public_transaction.version(), TransactionVersion::V0);
+    assert_eq!(
+        TransactionVersion::V0.signature_hash_personalization(),
+        b"Bnsighsh"
+    );
+}
+
+#[test]
+fn test_value_commitment_openings() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let mut transaction = ProposedTransaction::new(sapling);
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to prove spend");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to prove receipt");
+
+    let openings = transaction.value_commitment_openings();
+    let encrypted_openings = transaction
+        .encrypted_value_commitment_openings(spender_key.outgoing_view_key())
+        .expect("should encrypt openings");
+    let public_transaction = transaction
+        .post(&spender_key, None, 2)
+        .expect("should be able to post transaction");
+
+    let receipt = &public_transaction.receipts[0];
+    assert_eq!(openings[0].value, 40);
+    assert!(openings[0].verify(receipt));
+
+    let opening = ValueCommitmentOpening::decrypt_for_owner(
+        receipt,
+        receiver_key.incoming_view_key(),
+        &encrypted_openings[0],
+    )
+    .expect("owner should decrypt opening");
+    assert_eq!(opening, openings[0]);
+
+    // Other keys can't decrypt it, and a wrong value doesn't verify
+    assert!(ValueCommitmentOpening::decrypt_for_owner(
+        receipt,
+        spender_key.incoming_view_key(),
+        &encrypted_openings[0],
+    )
+    .is_err());
+    assert!(!ValueCommitmentOpening::new(41, opening.randomness).verify(receipt));
+
+    let mut serialized = vec![];
+    opening.write(&mut serialized).unwrap();
+    assert_eq!(
+        ValueCommitmentOpening::read(&serialized[..]).unwrap(),
+        opening
+    );
+}
+
+#[test]
+fn test_diff() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let shared = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let mut miners_fees = vec![];
+    for _ in 0..2 {
+        let mut miners_fee = ProposedTransaction::new(sapling.clone());
+        let fee_note = Note::new(receiver_key.generate_public_address(), 5, Memo::default());
+        miners_fee
+            .receive(&receiver_key, &fee_note)
+            .expect("should be able to receive note");
+        miners_fees.push(
+            miners_fee
+                .post_miners_fee()
+                .expect("should be able to post miners fee"),
+        );
+    }
+    let commitment = |transaction: &Transaction| {
+        transaction.receipts[0]
+            .merkle_note
+            .note_commitment
+            .to_bytes()
+    };
+
+    // Only the miner's fee differs between the blocks
+    let old_block = [miners_fees[0].clone(), shared.clone()];
+    let new_block = [miners_fees[1].clone(), shared.clone()];
+    let block_diff = diff(&old_block, 10, &new_block, 10);
+    assert!(block_diff.added_nullifiers.is_empty());
+    assert!(block_diff.removed_nullifiers.is_empty());
+    assert_eq!(
+        block_diff.removed_note_commitments,
+        vec![commitment(&miners_fees[0])]
+    );
+    assert_eq!(
+        block_diff.added_note_commitments,
+        vec![commitment(&miners_fees[1])]
+    );
+    assert!(block_diff.moved_note_commitments.is_empty());
+
+    // The shared transaction's note moves if it lands at a different index,
+    // or the tree before the block has a different size
+    let reordered_block = [shared.clone(), miners_fees[1].clone()];
+    let block_diff = diff(&old_block, 10, &reordered_block, 10);
+    assert!(block_diff.added_nullifiers.is_empty());
+    assert!(block_diff.removed_nullifiers.is_empty());
+    assert_eq!(
+        block_diff.moved_note_commitments,
+        vec![MovedNoteCommitment {
+            note_commitment: commitment(&shared),
+            old_position: 11,
+            new_position: 10,
+        }]
+    );
+    let block_diff = diff(&old_block, 10, &new_block, 12);
+    assert_eq!(
+        block_diff.moved_note_commitments,
+        vec![MovedNoteCommitment {
+            note_commitment: commitment(&shared),
+            old_position: 11,
+            new_position: 13,
+        }]
+    );
+
+    // Dropping the shared transaction unspends its note
+    let block_diff = diff(&old_block, 10, &miners_fees[..1], 10);
+    assert_eq!(
+        block_diff.removed_nullifiers,
+        vec![shared.spends[0].nullifier.0]
+    );
+    assert_eq!(block_diff.removed_note_commitments.len(), 2);
+    assert!(block_diff.added_note_commitments.is_empty());
+
+    assert_eq!(diff(&old_block, 10, &old_block, 10), Default::default());
+}
+
+#[test]
+fn test_outgoing_view_key_epoch() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    transaction.set_outgoing_view_key_epoch(Some(3));
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let public_transaction = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let outgoing_view_key = spender_key.outgoing_view_key();
+    for receipt in public_transaction.iter_receipts() {
+        assert!(receipt
+            .merkle_note
+            .decrypt_note_for_spender(outgoing_view_key)
+            .is_err());
+        assert!(receipt
+            .merkle_note
+            .decrypt_note_for_spender(&outgoing_view_key.for_epoch(3))
+            .is_ok());
+
+        let (epoch, _) = receipt
+            .merkle_note
+            .decrypt_note_for_spender_in_epochs(outgoing_view_key, 0..5)
+            .expect("should find the epoch within the window");
+        assert_eq!(epoch, 3);
+        assert!(receipt
+            .merkle_note
+            .decrypt_note_for_spender_in_epochs(outgoing_view_key, 4..8)
+            .is_err());
+    }
+
+    // The recipient doesn't need to know about epochs
+    public_transaction.receipts[0]
+        .merkle_note
+        .decrypt_note_for_owner(receiver_key.incoming_view_key())
+        .expect("recipient should decrypt note");
+}
+
+#[test]
+fn test_receipt_proof_cache() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut miners_fee = ProposedTransaction::new(sapling);
+    let fee_note = Note::new(receiver_key.generate_public_address(), 5, Memo::default());
+    miners_fee
+        .receive(&receiver_key, &fee_note)
+        .expect("should be able to receive note");
+    let miners_fee = miners_fee
+        .post_miners_fee()
+        .expect("should be able to post miners fee");
+
+    let cache = Arc::new(ReceiptProofCache::new(10));
+    let context = VerificationContext {
+        receipt_proof_cache: Some(cache.clone()),
+        ..VerificationContext::default()
+    };
+
+    miners_fee.verify_with_context(&context).unwrap();
+    assert_eq!(cache.stats().misses, 1);
+    assert_eq!(cache.stats().entries, 1);
+
+    miners_fee.verify_with_context(&context).unwrap();
+    assert_eq!(cache.stats().hits, 1);
+
+    // The block verifier shares the cache
+    let mut verifier = BlockVerifier::new(sapling_bls12::SAPLING.clone(), context);
+    verifier.add_transaction(&miners_fee).unwrap();
+    verifier.finalize().unwrap();
+    assert_eq!(cache.stats().hits, 2);
+
+    cache.clear();
+    assert_eq!(cache.stats().entries, 0);
+}
+
+#[test]
+fn test_non_canonical_encoding() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling.clone());
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 41, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let posted = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let mut serialized = vec![];
+    posted.write(&mut serialized).unwrap();
+    Transaction::read(sapling.clone(), &serialized[..])
+        .expect("should be able to read canonical transaction");
+
+    // Adding the modulus to s gives another s that works in the signature
+    // equation, so it has to be rejected for not being reduced
+    fn add_modulus(scalar: &mut [u8]) {
+        let modulus_minus_one = (-jubjub::Fr::one()).to_bytes();
+        let mut carry = 1;
+        for (byte, m) in scalar.iter_mut().zip(modulus_minus_one.iter()) {
+            let sum = *byte as u16 + *m as u16 + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+    }
+
+    // The header is 28 bytes, followed by the spend: a 192 byte proof, the
+    // value commitment, the randomized public key, the root hash, the tree
+    // size, the nullifier and the 64 byte signature.
+    let value_commitment = 28 + 192;
+    let spend_signature_s = value_commitment + 32 + 32 + 32 + 4 + 32 + 32;
+    let binding_signature_s = serialized.len() - 32;
+
+    let mut malleated = serialized.clone();
+    add_modulus(&mut malleated[binding_signature_s..]);
+    assert!(matches!(
+        Transaction::read(sapling.clone(), &malleated[..]),
+        Err(TransactionError::NonCanonicalEncoding)
+    ));
+
+    let mut malleated = serialized.clone();
+    add_modulus(&mut malleated[spend_signature_s..spend_signature_s + 32]);
+    assert!(matches!(
+        Transaction::read(sapling.clone(), &malleated[..]),
+        Err(TransactionError::NonCanonicalEncoding)
+    ));
+
+    // The identity, with its x coordinate of zero, encoded with the sign bit set
+    let mut malleated = serialized;
+    let mut identity = [0; 32];
+    identity[0] = 1;
+    identity[31] = 0x80;
+    malleated[value_commitment..value_commitment + 32].copy_from_slice(&identity);
+    assert!(matches!(
+        Transaction::read(sapling, &malleated[..]),
+        Err(TransactionError::NonCanonicalEncoding)
+    ));
+}
+
+#[test]
+fn test_signature_hasher() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    transaction.set_expiration_sequence(15);
+    let posted = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let mut hasher = SignatureHasher::new(posted.version(), 15, 1);
+    for spend in posted.iter_spends() {
+        hasher.add_spend_proof(spend).unwrap();
+    }
+    for receipt in posted.iter_receipts() {
+        hasher.add_receipt_proof(receipt).unwrap();
+    }
+    assert_eq!(hasher.finalize(), posted.transaction_signature_hash());
+    assert_eq!(
+        hasher.finalize(),
+        posted
+            .version()
+            .signature_hash(&posted.signature_hash_preimage())
+    );
+
+    // Spends can't follow receipts
+    assert!(matches!(
+        hasher.add_spend_proof(&posted.spends()[0]),
+        Err(TransactionError::IllegalValueError)
+    ));
+}
+
+#[test]
+fn test_extract_events() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut miners_fee = ProposedTransaction::new(sapling.clone());
+    let fee_note = Note::new(spender_key.generate_public_address(), 5, Memo::default());
+    miners_fee
+        .receive(&spender_key, &fee_note)
+        .expect("should be able to receive note");
+    let miners_fee = miners_fee
+        .post_miners_fee()
+        .expect("should be able to post miners fee");
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let payment = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let events = extract_events(&[miners_fee.clone(), payment.clone()]);
+    assert_eq!(events.len(), 4);
+    assert_eq!(
+        events[0],
+        ChainEvent::NoteCommitmentAdded {
+            transaction_index: 0,
+            transaction_hash: miners_fee.hash(),
+            receipt_index: 0,
+            position: 0,
+            note_commitment: miners_fee.receipts[0]
+                .merkle_note
+                .note_commitment
+                .to_bytes(),
+        }
+    );
+    assert_eq!(
+        events[1],
+        ChainEvent::NullifierSpent {
+            transaction_index: 1,
+            transaction_hash: payment.hash(),
+            spend_index: 0,
+            nullifier: payment.spends[0].nullifier.0,
+        }
+    );
+
+    // The payment and its change follow the miner's fee in the tree
+    for (event, receipt_index) in events[2..].iter().zip(0..) {
+        match event {
+            ChainEvent::NoteCommitmentAdded {
+                transaction_index,
+                receipt_index: index,
+                position,
+                note_commitment,
+                ..
+            } => {
+                assert_eq!(*transaction_index, 1);
+                assert_eq!(*index, receipt_index);
+                assert_eq!(*position, receipt_index as u64 + 1);
+                assert_eq!(
+                    *note_commitment,
+                    payment.receipts[receipt_index]
+                        .merkle_note
+                        .note_commitment
+                        .to_bytes()
+                );
+            }
+            _ => panic!("expected a note commitment"),
+        }
+    }
+}
+
+#[test]
+fn test_verify_against_tree() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let mut tree = TreeSnapshot::default();
+    for value in 0..3 {
+        let note = Note::new(
+            spender_key.generate_public_address(),
+            value,
+            Memo::default(),
+        );
+        tree.push(MerkleNoteHash::new(note.commitment_point()));
+    }
+    tree.push(MerkleNoteHash::new(in_note.commitment_point()));
+    let witness = tree
+        .witnesses_for(&[3])
+        .expect("should calculate witness")
+        .remove(0);
+    let root_hash = tree.root_hash().expect("should calculate root");
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    let posted = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    let tree_size = tree.size() as u32;
+    let roots = |size: u32| {
+        if size == tree_size {
+            Some(root_hash)
+        } else {
+            None
+        }
+    };
+    posted
+        .verify_against_tree(roots)
+        .expect("anchor should be valid");
+
+    // The tree had a different root at that size
+    assert!(matches!(
+        posted.verify_against_tree(|_| Some(Scalar::one())),
+        Err(TransactionError::InvalidAnchor)
+    ));
+
+    // The tree was never that size
+    assert!(matches!(
+        posted.verify_against_tree(|_| None),
+        Err(TransactionError::InvalidAnchor)
+    ));
+}
+
+#[test]
+fn test_dummy_spend() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let key = SaplingKey::generate_key();
+
+    let mut tree = TreeSnapshot::default();
+    let note = Note::new(key.generate_public_address(), 42, Memo::default());
+    tree.push(MerkleNoteHash::new(note.commitment_point()));
+    let root_hash = tree.root_hash().expect("should calculate root");
+
+    assert!(matches!(
+        SpendParams::new_dummy(sapling.clone(), root_hash, 0),
+        Err(SaplingProofError::InvalidAnchor)
+    ));
+
+    let spend = SpendParams::new_dummy(sapling.clone(), root_hash, 1)
+        .expect("should be able to create dummy spend");
+    let mut transaction = ProposedTransaction::new(sapling);
+    transaction.add_spend_proof(spend, 0);
+    let posted = transaction
+        .post(&key, None, 0)
+        .expect("should be able to post transaction");
+
+    posted.verify().expect("dummy spend should verify");
+    assert_eq!(posted.transaction_fee(), 0);
+    posted
+        .verify_against_tree(|size| if size == 1 { Some(root_hash) } else { None })
+        .expect("dummy spend should reference the anchor");
+}
+
+#[test]
+fn test_estimate_transaction_size() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let receiver_key = SaplingKey::generate_key();
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    let in_note = Note::new(spender_key.generate_public_address(), 42, Memo::default());
+    let out_note = Note::new(receiver_key.generate_public_address(), 40, Memo::default());
+    let witness = make_fake_witness(&in_note);
+    transaction
+        .spend(spender_key.clone(), &in_note, &witness)
+        .expect("should be able to spend note");
+    transaction
+        .receive(&spender_key, &out_note)
+        .expect("should be able to receive note");
+    let posted = transaction
+        .post(&spender_key, None, 1)
+        .expect("should be able to post transaction");
+
+    // The payment and the change
+    let mut serialized = vec![];
+    posted
+        .write(&mut serialized)
+        .expect("should be able to serialize transaction");
+    assert_eq!(estimate_transaction_size(1, 2), serialized.len());
+    assert_eq!(estimate_fee(1, 2, 3), serialized.len() as u64 * 3);
+}
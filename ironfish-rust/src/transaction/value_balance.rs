@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::{
+    consensus::constants::{MAX_SUPPLY_IN_IRON, ORE_PER_IRON},
+    errors::TransactionError,
+};
+
+use std::convert::TryFrom;
+
+/// The largest magnitude of value balance a transaction can have: the most
+/// ore that will ever exist, as no transaction can move more than that.
+pub const MAX_VALUE_BALANCE: i64 = (MAX_SUPPLY_IN_IRON * ORE_PER_IRON) as i64;
+
+/// The values of the spends minus the values of the receipts of a proposed
+/// transaction.
+///
+/// Note values are u64, so summing them as i64 can overflow or wrap for
+/// large notes. The balance is kept as an i128 instead, which can't
+/// overflow for any number of notes a transaction could hold, and is only
+/// range checked when it's converted to the transaction fee.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValueBalance(i128);
+
+impl ValueBalance {
+    pub fn spend(&mut self, value: u64) {
+        self.0 += i128::from(value);
+    }
+
+    pub fn receive(&mut self, value: u64) {
+        self.0 -= i128::from(value);
+    }
+
+    pub fn value(&self) -> i128 {
+        self.0
+    }
+
+    /// The balance as a transaction fee, failing with IllegalValueError if
+    /// it's out of range.
+    pub fn transaction_fee(&self) -> Result<i64, TransactionError> {
+        let fee = i64::try_from(self.0).map_err(|_| TransactionError::IllegalValueError)?;
+        check_transaction_fee(fee)?;
+        Ok(fee)
+    }
+}
+
+/// Check that a transaction fee read from a transaction is in range.
+pub fn check_transaction_fee(transaction_fee: i64) -> Result<(), TransactionError> {
+    if !(-MAX_VALUE_BALANCE..=MAX_VALUE_BALANCE).contains(&transaction_fee) {
+        return Err(TransactionError::IllegalValueError);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_transaction_fee, ValueBalance, MAX_VALUE_BALANCE};
+
+    #[test]
+    fn test_value_balance_range() {
+        let mut balance = ValueBalance::default();
+        balance.spend(u64::MAX);
+        balance.spend(u64::MAX);
+        assert_eq!(balance.value(), 2 * u64::MAX as i128);
+        assert!(balance.transaction_fee().is_err());
+
+        balance.receive(u64::MAX);
+        balance.receive(u64::MAX - 5);
+        assert_eq!(balance.transaction_fee().unwrap(), 5);
+
+        let mut balance = ValueBalance::default();
+        balance.spend(MAX_VALUE_BALANCE as u64);
+        assert_eq!(balance.transaction_fee().unwrap(), MAX_VALUE_BALANCE);
+        balance.spend(1);
+        assert!(balance.transaction_fee().is_err());
+
+        let mut balance = ValueBalance::default();
+        balance.receive(MAX_VALUE_BALANCE as u64);
+        assert_eq!(balance.transaction_fee().unwrap(), -MAX_VALUE_BALANCE);
+        balance.receive(1);
+        assert!(balance.transaction_fee().is_err());
+
+        assert!(check_transaction_fee(i64::MIN).is_err());
+        assert!(check_transaction_fee(i64::MAX).is_err());
+        assert!(check_transaction_fee(-MAX_VALUE_BALANCE).is_ok());
+        assert!(check_transaction_fee(MAX_VALUE_BALANCE).is_ok());
+        assert!(check_transaction_fee(-MAX_VALUE_BALANCE - 1).is_err());
+        assert!(check_transaction_fee(MAX_VALUE_BALANCE + 1).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{check_transaction_fee, ReceiptProofCache, SpendProofCache, Transaction};
+use crate::errors::TransactionError;
+
+use std::{collections::HashSet, sync::Arc};
+
+/// Consensus rules that depend on where in the chain a transaction is being
+/// verified, such as the sequence of the block it will be included in.
+///
+/// Passing the rules into verification keeps the checks next to the
+/// cryptographic ones, rather than having callers apply them separately
+/// after the fact. The default context applies no additional restrictions,
+/// which matches the behavior of Transaction::verify.
+#[derive(Clone, Debug)]
+pub struct VerificationContext {
+    /// The sequence of the block the transaction is being verified for. If
+    /// set, transactions with an expiration sequence at or before this
+    /// sequence are rejected. An expiration sequence of 0 never expires.
+    pub sequence: Option<u32>,
+
+    /// The maximum number of spends a single transaction may contain.
+    pub max_spends: Option<usize>,
+
+    /// The maximum number of receipts a single transaction may contain.
+    pub max_receipts: Option<usize>,
+
+    /// Whether transactions with a negative fee are allowed. Only miner's fee
+    /// transactions generate currency, so this should be false everywhere
+    /// except when verifying the miner's fee of a block.
+    pub allow_negative_fee: bool,
+
+    /// The maximum amount of currency a transaction with a negative fee may
+    /// create.
+    pub max_miners_fee: Option<u64>,
+
+    /// Spend proofs that are already known to be valid. If set, cached
+    /// proofs aren't verified again, and newly verified proofs are added.
+    pub spend_proof_cache: Option<Arc<SpendProofCache>>,
+
+    /// Receipt proofs that are already known to be valid, used the same way
+    /// as spend_proof_cache.
+    pub receipt_proof_cache: Option<Arc<ReceiptProofCache>>,
+}
+
+impl Default for VerificationContext {
+    fn default() -> Self {
+        VerificationContext {
+            sequence: None,
+            max_spends: None,
+            max_receipts: None,
+            allow_negative_fee: true,
+            max_miners_fee: None,
+            spend_proof_cache: None,
+            receipt_proof_cache: None,
+        }
+    }
+}
+
+impl VerificationContext {
+    /// Check the rules in this context that can be decided without verifying
+    /// any proofs or signatures. Called before the more expensive checks so
+    /// invalid transactions are rejected early.
+    pub(crate) fn check(&self, transaction: &Transaction) -> Result<(), TransactionError> {
+        if let Some(sequence) = self.sequence {
+            let expiration_sequence = transaction.expiration_sequence();
+            if expiration_sequence != 0 && expiration_sequence <= sequence {
+                return Err(TransactionError::ExpiredError);
+            }
+        }
+
+        if let Some(max_spends) = self.max_spends {
+            if transaction.spends().len() > max_spends {
+                return Err(TransactionError::TooManySpendsError);
+            }
+        }
+
+        if let Some(max_receipts) = self.max_receipts {
+            if transaction.receipts().len() > max_receipts {
+                return Err(TransactionError::TooManyReceiptsError);
+            }
+        }
+
+        // Two receipts with the same commitment would add the same note to
+        // the tree twice, and only one of them could ever be spent
+        let mut commitments = HashSet::with_capacity(transaction.receipts().len());
+        if !transaction
+            .receipts()
+            .iter()
+            .all(|receipt| commitments.insert(receipt.merkle_note.note_commitment.to_bytes()))
+        {
+            return Err(TransactionError::DuplicateReceiptError);
+        }
+
+        let transaction_fee = transaction.transaction_fee();
+        check_transaction_fee(transaction_fee)?;
+        if transaction_fee < 0 {
+            if !self.allow_negative_fee {
+                return Err(TransactionError::InvalidFeeError);
+            }
+            if let Some(max_miners_fee) = self.max_miners_fee {
+                if transaction_fee.unsigned_abs() > max_miners_fee {
+                    return Err(TransactionError::InvalidFeeError);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
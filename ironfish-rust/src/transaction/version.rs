@@ -0,0 +1,47 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use blake2b_simd::Params as Blake2b;
+
+/// Versions of the transaction format that affect what is signed.
+///
+/// Each version hashes its signature data under its own personalization, so
+/// that a signature made for one format can never be valid for another. A new
+/// format must add a version with a new personalization rather than changing
+/// what an existing version signs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionVersion {
+    V0,
+}
+
+impl TransactionVersion {
+    /// The version used for new transactions
+    pub const CURRENT: TransactionVersion = TransactionVersion::V0;
+
+    /// The blake2b personalization used when hashing the signature data.
+    pub fn signature_hash_personalization(&self) -> &'static [u8; 8] {
+        match self {
+            TransactionVersion::V0 => b"Bnsighsh",
+        }
+    }
+
+    /// The bytes at the start of the signature hash preimage.
+    pub fn signature_version_bytes(&self) -> &'static [u8] {
+        match self {
+            TransactionVersion::V0 => &[0],
+        }
+    }
+
+    /// Hash a signature hash preimage for this version.
+    pub fn signature_hash(&self, preimage: &[u8]) -> [u8; 32] {
+        let hash = Blake2b::new()
+            .hash_length(32)
+            .personal(self.signature_hash_personalization())
+            .hash(preimage);
+
+        let mut hash_result = [0; 32];
+        hash_result[..].clone_from_slice(hash.as_ref());
+        hash_result
+    }
+}
@@ -0,0 +1,193 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{
+    errors,
+    serializing::read_scalar,
+    witness::{Witness, WitnessNode},
+    MerkleNoteHash,
+};
+use bls12_381::Scalar;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ff::PrimeField;
+use zcash_proofs::circuit::sapling::TREE_DEPTH;
+
+use std::io;
+
+/// The leaves of the note commitment tree at some size, from which witnesses
+/// for any of its notes can be calculated.
+///
+/// The tree is built the same way as the merkle tree in the node: a node
+/// without a right sibling is hashed with itself, all the way up to a root
+/// at depth TREE_DEPTH.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeSnapshot {
+    leaves: Vec<Scalar>,
+}
+
+impl TreeSnapshot {
+    pub fn new(leaves: Vec<MerkleNoteHash>) -> Self {
+        TreeSnapshot {
+            leaves: leaves.into_iter().map(|leaf| leaf.0).collect(),
+        }
+    }
+
+    /// Read a snapshot: the number of leaves as a u32 followed by the hash
+    /// of each leaf.
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::TreeSnapshotError> {
+        let size = reader.read_u32::<LittleEndian>()?;
+        let mut leaves = vec![];
+        for _ in 0..size {
+            leaves.push(read_scalar(&mut reader)?);
+        }
+
+        Ok(TreeSnapshot { leaves })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(self.leaves.len() as u32)?;
+        for leaf in self.leaves.iter() {
+            writer.write_all(leaf.to_repr().as_ref())?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, leaf: MerkleNoteHash) {
+        self.leaves.push(leaf.0);
+    }
+
+    pub fn size(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Calculate the root hash of the tree.
+    pub fn root_hash(&self) -> Result<Scalar, errors::TreeSnapshotError> {
+        Ok(self.levels()?[TREE_DEPTH][0])
+    }
+
+    /// Calculate witnesses for the notes at the given positions against the
+    /// root of this snapshot.
+    ///
+    /// The tree is hashed once and every witness reads its auth path from
+    /// the shared levels, so this is much cheaper than calculating each
+    /// witness separately when spending several notes.
+    pub fn witnesses_for(
+        &self,
+        positions: &[u64],
+    ) -> Result<Vec<Witness>, errors::TreeSnapshotError> {
+        if let Some(position) = positions
+            .iter()
+            .find(|position| **position >= self.leaves.len() as u64)
+        {
+            return Err(errors::TreeSnapshotError::PositionOutOfRange(*position));
+        }
+
+        let levels = self.levels()?;
+        let root_hash = levels[TREE_DEPTH][0];
+
+        let witnesses = positions
+            .iter()
+            .map(|position| {
+                let mut auth_path = Vec::with_capacity(TREE_DEPTH);
+                for (depth, level) in levels.iter().take(TREE_DEPTH).enumerate() {
+                    let index = (*position >> depth) as usize;
+                    if index % 2 == 1 {
+                        auth_path.push(WitnessNode::Right(level[index - 1]));
+                    } else {
+                        let sibling = level.get(index + 1).unwrap_or(&level[index]);
+                        auth_path.push(WitnessNode::Left(*sibling));
+                    }
+                }
+
+                Witness {
+                    tree_size: self.leaves.len(),
+                    root_hash,
+                    auth_path,
+                }
+            })
+            .collect();
+
+        Ok(witnesses)
+    }
+
+    /// Hash the tree, returning the hashes at each depth from the leaves up
+    /// to the root.
+    fn levels(&self) -> Result<Vec<Vec<Scalar>>, errors::TreeSnapshotError> {
+        if self.leaves.is_empty() {
+            return Err(errors::TreeSnapshotError::EmptyTree);
+        }
+
+        let mut levels = Vec::with_capacity(TREE_DEPTH + 1);
+        levels.push(self.leaves.clone());
+        for depth in 0..TREE_DEPTH {
+            let level = &levels[depth];
+            let parents = level
+                .chunks(2)
+                .map(|pair| {
+                    let right = pair.get(1).unwrap_or(&pair[0]);
+                    MerkleNoteHash::combine_hash(depth, &pair[0], right)
+                })
+                .collect();
+            levels.push(parents);
+        }
+
+        Ok(levels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TreeSnapshot;
+    use crate::{
+        merkle_note_hash::MerkleNoteHash,
+        witness::{WitnessNode, WitnessTrait},
+    };
+    use bls12_381::Scalar;
+
+    fn make_snapshot(size: u64) -> TreeSnapshot {
+        TreeSnapshot::new(
+            (0..size)
+                .map(|i| MerkleNoteHash::new(Scalar::from(i + 1)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_witnesses_for() {
+        let snapshot = make_snapshot(5);
+        let root_hash = snapshot.root_hash().unwrap();
+
+        let witnesses = snapshot.witnesses_for(&[0, 3, 4]).unwrap();
+        assert_eq!(witnesses.len(), 3);
+        for (witness, position) in witnesses.iter().zip([0u64, 3, 4].iter()) {
+            assert_eq!(witness.root_hash(), root_hash);
+            assert_eq!(witness.tree_size(), 5);
+            assert!(witness.verify(&MerkleNoteHash::new(Scalar::from(position + 1))));
+            assert!(!witness.verify(&MerkleNoteHash::new(Scalar::from(100))));
+        }
+
+        // The last leaf has no sibling, so it's hashed with itself
+        assert_eq!(
+            witnesses[2].auth_path[0],
+            WitnessNode::Left(Scalar::from(5))
+        );
+
+        assert!(snapshot.witnesses_for(&[5]).is_err());
+        assert!(make_snapshot(0).root_hash().is_err());
+    }
+
+    #[test]
+    fn test_snapshot_serialization() {
+        let snapshot = make_snapshot(3);
+
+        let mut serialized = vec![];
+        snapshot.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), 4 + 3 * 32);
+
+        let read_back = TreeSnapshot::read(&serialized[..]).unwrap();
+        assert_eq!(read_back, snapshot);
+
+        assert!(TreeSnapshot::read(&serialized[..serialized.len() - 1]).is_err());
+    }
+}
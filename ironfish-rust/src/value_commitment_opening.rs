@@ -0,0 +1,121 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{
+    errors,
+    keys::IncomingViewKey,
+    receiving::ReceiptProof,
+    serializing::{aead, read_scalar},
+};
+use blake2b_simd::Params as Blake2b;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use jubjub::ExtendedPoint;
+use zcash_primitives::primitives::ValueCommitment;
+
+use std::io;
+
+const OPENING_KEY_PERSONALIZATION: &[u8; 16] = b"Bnvcopening_key_";
+
+/// Size of a serialized opening: the value and the randomness
+pub const OPENING_SIZE: usize = 8 + 32;
+
+/// Size of an opening encrypted with encrypt
+pub const ENCRYPTED_OPENING_SIZE: usize = OPENING_SIZE + aead::MAC_SIZE;
+
+/// The value and randomness that a receipt's value commitment was calculated
+/// from.
+///
+/// Anyone holding the opening can check that a receipt commits to the value
+/// without being able to decrypt the note. This lets the owner of a note
+/// prove the amount of a deposit to a bridge or an exchange.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueCommitmentOpening {
+    pub value: u64,
+    pub randomness: jubjub::Fr,
+}
+
+impl ValueCommitmentOpening {
+    pub fn new(value: u64, randomness: jubjub::Fr) -> Self {
+        ValueCommitmentOpening { value, randomness }
+    }
+
+    /// The value commitment point this opens.
+    pub fn commitment(&self) -> ExtendedPoint {
+        ValueCommitment {
+            value: self.value,
+            randomness: self.randomness,
+        }
+        .commitment()
+        .into()
+    }
+
+    /// Check that this opens the value commitment of the receipt.
+    pub fn verify(&self, receipt: &ReceiptProof) -> bool {
+        self.commitment() == receipt.merkle_note.value_commitment
+    }
+
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, errors::NoteError> {
+        let value = reader.read_u64::<LittleEndian>()?;
+        let randomness = read_scalar(&mut reader)?;
+        Ok(ValueCommitmentOpening { value, randomness })
+    }
+
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.value)?;
+        writer.write_all(&self.randomness.to_bytes())
+    }
+
+    /// Encrypt the opening with the shared secret of the note it belongs to,
+    /// so that only the owner and sender of the note can read it.
+    ///
+    /// The shared secret is never used as the key directly, because the note
+    /// itself is encrypted with it.
+    pub fn encrypt(&self, shared_secret: &[u8; 32]) -> [u8; ENCRYPTED_OPENING_SIZE] {
+        let mut plaintext = [0; OPENING_SIZE];
+        self.write(&mut plaintext[..]).unwrap();
+
+        let mut encrypted = [0; ENCRYPTED_OPENING_SIZE];
+        aead::encrypt(&opening_key(shared_secret), &plaintext, &mut encrypted);
+        encrypted
+    }
+
+    /// Decrypt an opening encrypted with encrypt.
+    pub fn decrypt(
+        shared_secret: &[u8; 32],
+        encrypted: &[u8; ENCRYPTED_OPENING_SIZE],
+    ) -> Result<Self, errors::NoteError> {
+        let mut plaintext = [0; OPENING_SIZE];
+        aead::decrypt(&opening_key(shared_secret), encrypted, &mut plaintext)?;
+        ValueCommitmentOpening::read(&plaintext[..])
+    }
+
+    /// Decrypt an opening for a receipt owned by the given incoming view key,
+    /// and check that it opens the receipt's value commitment.
+    pub fn decrypt_for_owner(
+        receipt: &ReceiptProof,
+        owner_view_key: &IncomingViewKey,
+        encrypted: &[u8; ENCRYPTED_OPENING_SIZE],
+    ) -> Result<Self, errors::NoteError> {
+        let shared_secret = receipt
+            .merkle_note
+            .note_encryption_key_for_owner(owner_view_key);
+        let opening = ValueCommitmentOpening::decrypt(&shared_secret, encrypted)?;
+        if !opening.verify(receipt) {
+            return Err(errors::NoteError::InvalidCommitment);
+        }
+
+        Ok(opening)
+    }
+}
+
+fn opening_key(shared_secret: &[u8; 32]) -> [u8; 32] {
+    let hash = Blake2b::new()
+        .hash_length(32)
+        .personal(OPENING_KEY_PERSONALIZATION)
+        .hash(shared_secret);
+
+    let mut key = [0; 32];
+    key.copy_from_slice(hash.as_bytes());
+    key
+}
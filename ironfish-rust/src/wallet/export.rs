@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::WalletNote;
+use crate::{errors::WalletError, keys::OutgoingViewKey, serializing::aead};
+use blake2b_simd::Params as Blake2b;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand::{thread_rng, Rng};
+
+const EXPORT_KEY_PERSONALIZATION: &[u8; 16] = b"Bnwallet_export_";
+
+/// Size of the random salt at the start of an export
+const SALT_SIZE: usize = 32;
+
+/// Encrypt the notes of an account so they can be moved to another wallet
+/// database without rescanning the chain.
+///
+/// The notes are encrypted under a key derived from the account's outgoing
+/// view key and a random salt, so every export uses a different key even
+/// though the nonce is fixed. The salt is stored in the clear at the start
+/// of the export.
+pub fn encrypt_notes(outgoing_view_key: &OutgoingViewKey, notes: &[WalletNote]) -> Vec<u8> {
+    let mut plaintext = vec![];
+    plaintext
+        .write_u32::<LittleEndian>(notes.len() as u32)
+        .unwrap();
+    for note in notes {
+        note.write(&mut plaintext).unwrap();
+    }
+
+    let mut salt = [0; SALT_SIZE];
+    thread_rng().fill(&mut salt[..]);
+
+    let mut exported = vec![0; SALT_SIZE + plaintext.len() + aead::MAC_SIZE];
+    exported[..SALT_SIZE].copy_from_slice(&salt);
+    aead::encrypt(
+        &export_key(outgoing_view_key, &salt),
+        &plaintext,
+        &mut exported[SALT_SIZE..],
+    );
+    exported
+}
+
+/// Decrypt notes encrypted with encrypt_notes.
+///
+/// This only checks that the export was made with the same outgoing view
+/// key. Wallet::import_notes also checks the notes against the chain.
+pub fn decrypt_notes(
+    outgoing_view_key: &OutgoingViewKey,
+    exported: &[u8],
+) -> Result<Vec<WalletNote>, WalletError> {
+    if exported.len() < SALT_SIZE + aead::MAC_SIZE {
+        return Err(WalletError::InvalidExport);
+    }
+
+    let (salt, ciphertext) = exported.split_at(SALT_SIZE);
+    let mut plaintext = vec![0; ciphertext.len() - aead::MAC_SIZE];
+    aead::decrypt(
+        &export_key(outgoing_view_key, salt),
+        ciphertext,
+        &mut plaintext,
+    )
+    .map_err(|_| WalletError::InvalidExport)?;
+
+    let mut reader = &plaintext[..];
+    let count = reader.read_u32::<LittleEndian>()?;
+    let mut notes = vec![];
+    for _ in 0..count {
+        notes.push(WalletNote::read(&mut reader)?);
+    }
+    if !reader.is_empty() {
+        return Err(WalletError::InvalidExport);
+    }
+
+    Ok(notes)
+}
+
+fn export_key(outgoing_view_key: &OutgoingViewKey, salt: &[u8]) -> [u8; 32] {
+    let hash = Blake2b::new()
+        .hash_length(32)
+        .personal(EXPORT_KEY_PERSONALIZATION)
+        .to_state()
+        .update(&outgoing_view_key.view_key)
+        .update(salt)
+        .finalize();
+
+    let mut key = [0; 32];
+    key.copy_from_slice(hash.as_bytes());
+    key
+}
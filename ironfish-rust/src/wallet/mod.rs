@@ -0,0 +1,282 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Account state for a single spending key: which notes it owns, which of
+//! those have been spent, and how to build transactions from them.
+//!
+//! The wallet is driven by connecting and disconnecting transactions as the
+//! chain head moves, so the balance always reflects the transactions that
+//! have been connected and nothing else.
+
+use super::{
+    errors::{TransactionError, WalletError},
+    keys::{PublicAddress, SaplingKey},
+    note::{Memo, Note},
+    positioned_note::PositionedNote,
+    transaction::{ProposedTransaction, Transaction},
+    witness::WitnessTrait,
+    Sapling,
+};
+use bls12_381::Scalar;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use zcash_primitives::primitives::Nullifier;
+
+use std::{io, sync::Arc};
+
+mod export;
+pub use export::*;
+
+mod send;
+pub use send::*;
+
+mod store;
+pub use store::*;
+
+#[cfg(test)]
+mod tests;
+
+/// A note owned by the wallet's account.
+#[derive(Clone)]
+pub struct WalletNote {
+    /// The note and its position in the note commitment tree
+    pub note: PositionedNote,
+
+    /// Nullifier that will be revealed when the note is spent
+    pub nullifier: Nullifier,
+
+    /// Whether a connected transaction has spent this note
+    pub spent: bool,
+}
+
+impl WalletNote {
+    /// Read a wallet note from the given stream IN PLAINTEXT.
+    pub fn read<R: io::Read>(mut reader: R) -> Result<Self, WalletError> {
+        let note = PositionedNote::read(&mut reader).map_err(|_| WalletError::StorageError)?;
+        let mut nullifier = [0; 32];
+        reader.read_exact(&mut nullifier)?;
+        let spent = reader.read_u8()? != 0;
+
+        Ok(WalletNote {
+            note,
+            nullifier: Nullifier(nullifier),
+            spent,
+        })
+    }
+
+    /// Write the wallet note to the given stream IN PLAINTEXT. This contains
+    /// everything needed to spend the note except the spending key, so the
+    /// storage backend must be kept as private as the key itself.
+    pub fn write<W: io::Write>(&self, mut writer: &mut W) -> io::Result<()> {
+        self.note.write(&mut writer)?;
+        writer.write_all(&self.nullifier.0)?;
+        writer.write_u8(self.spent as u8)?;
+        Ok(())
+    }
+
+    /// Value of the note
+    pub fn value(&self) -> u64 {
+        self.note.note.value()
+    }
+}
+
+/// The state of a single account, stored in a pluggable backend.
+pub struct Wallet<S: WalletStore> {
+    key: SaplingKey,
+    store: S,
+}
+
+impl<S: WalletStore> Wallet<S> {
+    pub fn new(key: SaplingKey, store: S) -> Self {
+        Wallet { key, store }
+    }
+
+    /// The storage backend the wallet writes to
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Update the wallet with a transaction that was added to the chain.
+    ///
+    /// first_position is the size of the note commitment tree before this
+    /// transaction's receipts were added, so the receipt at index `i` is at
+    /// position `first_position + i`.
+    ///
+    /// Connecting the same transaction twice is harmless.
+    pub fn connect_transaction(
+        &mut self,
+        transaction: &Transaction,
+        first_position: u64,
+    ) -> Result<(), WalletError> {
+        for spend in transaction.iter_spends() {
+            if let Some(mut note) = self.store.get_note(&spend.nullifier())? {
+                note.spent = true;
+                self.store.put_note(note)?;
+            }
+        }
+
+        for note in self.decrypt_receipts(transaction, first_position) {
+            if self.store.get_note(&note.nullifier)?.is_none() {
+                self.store.put_note(note)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Undo connect_transaction for a transaction that was removed from the
+    /// chain, such as during a reorg. The notes it created are forgotten and
+    /// the notes it spent become spendable again.
+    pub fn disconnect_transaction(
+        &mut self,
+        transaction: &Transaction,
+        first_position: u64,
+    ) -> Result<(), WalletError> {
+        for note in self.decrypt_receipts(transaction, first_position) {
+            self.store.delete_note(&note.nullifier)?;
+        }
+
+        for spend in transaction.iter_spends() {
+            if let Some(mut note) = self.store.get_note(&spend.nullifier())? {
+                note.spent = false;
+                self.store.put_note(note)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Notes owned by this account that haven't been spent
+    pub fn unspent_notes(&self) -> Result<Vec<WalletNote>, WalletError> {
+        let mut notes: Vec<WalletNote> = self
+            .store
+            .notes()?
+            .into_iter()
+            .filter(|note| !note.spent)
+            .collect();
+        notes.sort_by_key(|note| note.note.position);
+        Ok(notes)
+    }
+
+    /// Sum of the values of the unspent notes. Fails with IllegalValueError
+    /// if the sum doesn't fit in a u64.
+    pub fn balance(&self) -> Result<u64, WalletError> {
+        self.unspent_notes()?
+            .iter()
+            .try_fold(0u64, |total, note| total.checked_add(note.value()))
+            .ok_or(WalletError::TransactionError(
+                TransactionError::IllegalValueError,
+            ))
+    }
+
+    /// Build and post a transaction sending value to the recipient.
+    ///
+    /// Unspent notes are selected oldest first until they cover the value and
+    /// the fee, and change is returned to this account. witness_for is called
+    /// for each selected note to get its witness from the note commitment
+    /// tree.
+    ///
+    /// The spent notes aren't marked as spent until the transaction is
+    /// connected.
+    pub fn create_transaction<W, F>(
+        &self,
+        sapling: Arc<Sapling>,
+        recipient: PublicAddress,
+        value: u64,
+        memo: Memo,
+        transaction_fee: u64,
+        witness_for: F,
+    ) -> Result<Transaction, WalletError>
+    where
+        W: WitnessTrait,
+        F: Fn(&PositionedNote) -> Option<W>,
+    {
+        let required = value
+            .checked_add(transaction_fee)
+            .ok_or(WalletError::TransactionError(
+                TransactionError::IllegalValueError,
+            ))?;
+
+        let selected = send::select_notes(self.unspent_notes()?, required, WalletNote::value)?;
+
+        let mut transaction = ProposedTransaction::new(sapling);
+        for note in selected {
+            let witness = witness_for(&note.note).ok_or(WalletError::MissingWitness)?;
+            transaction.spend_positioned(self.key.clone(), &note.note, &witness)?;
+        }
+
+        transaction.receive(&self.key, &Note::new(recipient, value, memo))?;
+
+        Ok(transaction.post(&self.key, None, transaction_fee)?)
+    }
+
+    /// Encrypt all of the account's notes, spent or not, so they can be
+    /// imported into another wallet with import_notes.
+    pub fn export_notes(&self) -> Result<Vec<u8>, WalletError> {
+        let mut notes = self.store.notes()?;
+        notes.sort_by_key(|note| note.note.position);
+        Ok(encrypt_notes(self.key.outgoing_view_key(), &notes))
+    }
+
+    /// Add the notes from export_notes to this wallet, returning how many
+    /// weren't already in it.
+    ///
+    /// Every note is checked before any are stored: its nullifier must
+    /// belong to this account, and witness_for must return a witness that
+    /// authenticates the note's commitment at its position against
+    /// root_hash, the root of the note commitment tree the caller trusts.
+    pub fn import_notes<W, F>(
+        &mut self,
+        exported: &[u8],
+        root_hash: Scalar,
+        witness_for: F,
+    ) -> Result<usize, WalletError>
+    where
+        W: WitnessTrait,
+        F: Fn(&PositionedNote) -> Option<W>,
+    {
+        let notes = decrypt_notes(self.key.outgoing_view_key(), exported)?;
+
+        for note in notes.iter() {
+            if note.note.nullifier(&self.key) != note.nullifier {
+                return Err(WalletError::InvalidExport);
+            }
+
+            let witness = witness_for(&note.note).ok_or(WalletError::MissingWitness)?;
+            if witness.root_hash() != root_hash || note.note.verify_witness(&witness).is_err() {
+                return Err(WalletError::InvalidExport);
+            }
+        }
+
+        let mut imported = 0;
+        for note in notes {
+            if self.store.get_note(&note.nullifier)?.is_none() {
+                self.store.put_note(note)?;
+                imported += 1;
+            }
+        }
+
+        Ok(imported)
+    }
+
+    fn decrypt_receipts(&self, transaction: &Transaction, first_position: u64) -> Vec<WalletNote> {
+        transaction
+            .iter_receipts()
+            .enumerate()
+            .filter_map(|(index, receipt)| {
+                receipt
+                    .merkle_note()
+                    .decrypt_positioned_note_for_owner(
+                        self.key.incoming_view_key(),
+                        first_position + index as u64,
+                    )
+                    .ok()
+            })
+            .map(|note| WalletNote {
+                nullifier: note.nullifier(&self.key),
+                note,
+                spent: false,
+            })
+            .collect()
+    }
+}
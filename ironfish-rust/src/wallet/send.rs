@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::{
+    errors::{TransactionError, WalletError},
+    keys::{PublicAddress, SaplingKey},
+    note::{Memo, Note},
+    transaction::{ProposedTransaction, Transaction},
+    witness::WitnessTrait,
+    Sapling,
+};
+
+use std::sync::Arc;
+
+/// A payment to be made by send
+#[derive(Clone)]
+pub struct Recipient {
+    pub address: PublicAddress,
+    pub value: u64,
+    pub memo: Memo,
+}
+
+/// Build, prove and sign a transaction paying each of the recipients, in a
+/// single call.
+///
+/// Notes are selected in the order given until they cover the payments and
+/// the fee, and any change is returned to the spender's default address.
+/// Each note must come with its witness in the note commitment tree.
+///
+/// This is for callers that track their own notes. Wallet::create_transaction
+/// does the same for the notes in a Wallet.
+pub fn send<W: WitnessTrait>(
+    sapling: Arc<Sapling>,
+    spender_key: &SaplingKey,
+    notes: &[(Note, W)],
+    recipients: &[Recipient],
+    transaction_fee: u64,
+    expiration_sequence: u32,
+) -> Result<Transaction, WalletError> {
+    if recipients.is_empty() {
+        return Err(WalletError::NoRecipients);
+    }
+
+    let required = recipients
+        .iter()
+        .try_fold(transaction_fee, |total, recipient| {
+            total.checked_add(recipient.value)
+        })
+        .ok_or(WalletError::TransactionError(
+            TransactionError::IllegalValueError,
+        ))?;
+
+    let selected = select_notes(notes.iter(), required, |(note, _)| note.value())?;
+
+    let mut transaction = ProposedTransaction::new(sapling);
+    for (note, witness) in selected {
+        transaction.spend(spender_key.clone(), note, witness)?;
+    }
+
+    for recipient in recipients.iter() {
+        let note = Note::new(recipient.address.clone(), recipient.value, recipient.memo);
+        transaction.receive(spender_key, &note)?;
+    }
+
+    transaction.set_expiration_sequence(expiration_sequence);
+
+    Ok(transaction.post(spender_key, None, transaction_fee)?)
+}
+
+/// Take notes in the order given until their values cover required.
+///
+/// Fails with InsufficientFundsError if all of the notes don't cover it, or
+/// IllegalValueError if their values overflow.
+pub(super) fn select_notes<T, I, F>(
+    notes: I,
+    required: u64,
+    value: F,
+) -> Result<Vec<T>, WalletError>
+where
+    I: IntoIterator<Item = T>,
+    F: Fn(&T) -> u64,
+{
+    let mut selected = vec![];
+    let mut selected_value = 0u64;
+    for note in notes {
+        if selected_value >= required {
+            break;
+        }
+        selected_value =
+            selected_value
+                .checked_add(value(&note))
+                .ok_or(WalletError::TransactionError(
+                    TransactionError::IllegalValueError,
+                ))?;
+        selected.push(note);
+    }
+
+    if selected_value < required {
+        return Err(WalletError::InsufficientFundsError);
+    }
+
+    Ok(selected)
+}
@@ -0,0 +1,109 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::WalletNote;
+use crate::{errors::WalletError, storage::Store};
+use zcash_primitives::primitives::Nullifier;
+
+use std::collections::HashMap;
+
+/// Storage backend for the notes tracked by a Wallet.
+///
+/// Notes are keyed by their nullifier, which is unique to each note and is
+/// all that's needed to recognize a spend of the note in a transaction.
+pub trait WalletStore {
+    /// Look up the note with the given nullifier, if this wallet owns it.
+    fn get_note(&self, nullifier: &Nullifier) -> Result<Option<WalletNote>, WalletError>;
+
+    /// Insert the note, replacing any note with the same nullifier.
+    fn put_note(&mut self, note: WalletNote) -> Result<(), WalletError>;
+
+    /// Remove the note with the given nullifier. Removing a note that isn't
+    /// stored is not an error.
+    fn delete_note(&mut self, nullifier: &Nullifier) -> Result<(), WalletError>;
+
+    /// All of the notes in the store, in no particular order.
+    fn notes(&self) -> Result<Vec<WalletNote>, WalletError>;
+}
+
+/// A WalletStore that keeps everything in memory. Useful for tests and
+/// short-lived wallets.
+#[derive(Clone, Default)]
+pub struct MemoryWalletStore {
+    notes: HashMap<[u8; 32], WalletNote>,
+}
+
+impl MemoryWalletStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WalletStore for MemoryWalletStore {
+    fn get_note(&self, nullifier: &Nullifier) -> Result<Option<WalletNote>, WalletError> {
+        Ok(self.notes.get(&nullifier.0).cloned())
+    }
+
+    fn put_note(&mut self, note: WalletNote) -> Result<(), WalletError> {
+        self.notes.insert(note.nullifier.0, note);
+        Ok(())
+    }
+
+    fn delete_note(&mut self, nullifier: &Nullifier) -> Result<(), WalletError> {
+        self.notes.remove(&nullifier.0);
+        Ok(())
+    }
+
+    fn notes(&self) -> Result<Vec<WalletNote>, WalletError> {
+        Ok(self.notes.values().cloned().collect())
+    }
+}
+
+/// Prefix for the keys of notes in a KeyValueWalletStore
+const NOTE_KEY_PREFIX: &[u8] = b"notes:";
+
+/// A WalletStore that serializes notes into a generic key-value Store, so
+/// wallets can be persisted with any storage backend.
+pub struct KeyValueWalletStore<T: Store> {
+    store: T,
+}
+
+impl<T: Store> KeyValueWalletStore<T> {
+    pub fn new(store: T) -> Self {
+        KeyValueWalletStore { store }
+    }
+
+    fn note_key(nullifier: &Nullifier) -> Vec<u8> {
+        [NOTE_KEY_PREFIX, &nullifier.0[..]].concat()
+    }
+}
+
+impl<T: Store> WalletStore for KeyValueWalletStore<T> {
+    fn get_note(&self, nullifier: &Nullifier) -> Result<Option<WalletNote>, WalletError> {
+        match self.store.get(&Self::note_key(nullifier))? {
+            Some(bytes) => Ok(Some(WalletNote::read(&bytes[..])?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_note(&mut self, note: WalletNote) -> Result<(), WalletError> {
+        let mut bytes = vec![];
+        note.write(&mut bytes)?;
+        self.store.put(&Self::note_key(&note.nullifier), &bytes)?;
+        Ok(())
+    }
+
+    fn delete_note(&mut self, nullifier: &Nullifier) -> Result<(), WalletError> {
+        self.store.delete(&Self::note_key(nullifier))?;
+        Ok(())
+    }
+
+    fn notes(&self) -> Result<Vec<WalletNote>, WalletError> {
+        self.store
+            .iterate(NOTE_KEY_PREFIX)?
+            .iter()
+            .map(|(_, bytes)| WalletNote::read(&bytes[..]))
+            .collect()
+    }
+}
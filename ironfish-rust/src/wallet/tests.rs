@@ -0,0 +1,314 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use super::{
+    send, KeyValueWalletStore, MemoryWalletStore, Recipient, Wallet, WalletNote, WalletStore,
+};
+use crate::{
+    errors::{TransactionError, WalletError},
+    keys::SaplingKey,
+    merkle_note::position as witness_position,
+    note::{Memo, Note},
+    positioned_note::PositionedNote,
+    sapling_bls12,
+    storage::MemoryStore,
+    test_util::make_fake_witness,
+    transaction::ProposedTransaction,
+    witness::Witness,
+};
+
+#[test]
+fn test_wallet() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let sender_key = SaplingKey::generate_key();
+    let wallet_key = SaplingKey::generate_key();
+    let recipient_key = SaplingKey::generate_key();
+
+    let mut wallet = Wallet::new(wallet_key.clone(), MemoryWalletStore::new());
+    assert_eq!(wallet.balance().unwrap(), 0);
+
+    // Fund the wallet with a note at a position we have a witness for
+    let in_note = Note::new(sender_key.generate_public_address(), 42, Memo::default());
+    let wallet_note = Note::new(wallet_key.generate_public_address(), 42, Memo::default());
+    let wallet_witness = make_fake_witness(&wallet_note);
+    let wallet_position = witness_position(&wallet_witness);
+
+    let mut funding = ProposedTransaction::new(sapling.clone());
+    funding
+        .spend(sender_key.clone(), &in_note, &make_fake_witness(&in_note))
+        .unwrap();
+    funding.receive(&sender_key, &wallet_note).unwrap();
+    let funding = funding.post(&sender_key, None, 0).unwrap();
+
+    wallet
+        .connect_transaction(&funding, wallet_position)
+        .unwrap();
+    wallet
+        .connect_transaction(&funding, wallet_position)
+        .unwrap();
+    assert_eq!(wallet.balance().unwrap(), 42);
+
+    // Can't send more than the balance
+    let result = wallet.create_transaction(
+        sapling.clone(),
+        recipient_key.generate_public_address(),
+        42,
+        Memo::default(),
+        1,
+        |_| None::<Witness>,
+    );
+    assert!(matches!(result, Err(WalletError::InsufficientFundsError)));
+
+    let payment = wallet
+        .create_transaction(
+            sapling,
+            recipient_key.generate_public_address(),
+            40,
+            Memo::default(),
+            1,
+            |note| {
+                assert_eq!(note.position, wallet_position);
+                Some(Witness {
+                    tree_size: wallet_witness.tree_size,
+                    root_hash: wallet_witness.root_hash,
+                    auth_path: wallet_witness.auth_path.clone(),
+                })
+            },
+        )
+        .expect("should be able to create transaction");
+    payment.verify().unwrap();
+
+    // The payment spends the wallet's note and returns 1 as change
+    wallet.connect_transaction(&payment, 100).unwrap();
+    assert_eq!(wallet.balance().unwrap(), 1);
+    let unspent = wallet.unspent_notes().unwrap();
+    assert_eq!(unspent.len(), 1);
+    assert_eq!(unspent[0].note.position, 101);
+
+    // Disconnecting restores the previous state
+    wallet.disconnect_transaction(&payment, 100).unwrap();
+    assert_eq!(wallet.balance().unwrap(), 42);
+    assert_eq!(wallet.store().notes().unwrap().len(), 1);
+}
+
+#[test]
+fn test_wallet_note_serialization() {
+    let key = SaplingKey::generate_key();
+    let note = Note::new(key.generate_public_address(), 7, Memo::default());
+    let positioned_note = PositionedNote::new(note, 3);
+    let wallet_note = WalletNote {
+        nullifier: positioned_note.nullifier(&key),
+        note: positioned_note,
+        spent: true,
+    };
+
+    let mut serialized = vec![];
+    wallet_note.write(&mut serialized).unwrap();
+    let read_back = WalletNote::read(&serialized[..]).unwrap();
+
+    assert_eq!(read_back.nullifier, wallet_note.nullifier);
+    assert_eq!(read_back.note.position, 3);
+    assert_eq!(read_back.value(), 7);
+    assert!(read_back.spent);
+}
+
+#[test]
+fn test_balance_overflow() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let key = SaplingKey::generate_key();
+
+    let mut store = MemoryWalletStore::new();
+    for (position, value) in [u64::MAX - 1, u64::MAX].iter().enumerate() {
+        let note = Note::new(key.generate_public_address(), *value, Memo::default());
+        let positioned_note = PositionedNote::new(note, position as u64);
+        store
+            .put_note(WalletNote {
+                nullifier: positioned_note.nullifier(&key),
+                note: positioned_note,
+                spent: false,
+            })
+            .unwrap();
+    }
+    let wallet = Wallet::new(key.clone(), store);
+
+    assert!(matches!(
+        wallet.balance(),
+        Err(WalletError::TransactionError(
+            TransactionError::IllegalValueError
+        ))
+    ));
+    assert!(matches!(
+        wallet.create_transaction(
+            sapling,
+            key.generate_public_address(),
+            u64::MAX - 1,
+            Memo::default(),
+            1,
+            |_| None::<Witness>,
+        ),
+        Err(WalletError::TransactionError(
+            TransactionError::IllegalValueError
+        ))
+    ));
+}
+
+#[test]
+fn test_key_value_wallet_store() {
+    let key = SaplingKey::generate_key();
+    let mut store = KeyValueWalletStore::new(MemoryStore::new());
+
+    let note = Note::new(key.generate_public_address(), 7, Memo::default());
+    let positioned_note = PositionedNote::new(note, 3);
+    let mut wallet_note = WalletNote {
+        nullifier: positioned_note.nullifier(&key),
+        note: positioned_note,
+        spent: false,
+    };
+
+    assert!(store.get_note(&wallet_note.nullifier).unwrap().is_none());
+    store.put_note(wallet_note.clone()).unwrap();
+    wallet_note.spent = true;
+    store.put_note(wallet_note.clone()).unwrap();
+
+    let notes = store.notes().unwrap();
+    assert_eq!(notes.len(), 1);
+    assert!(notes[0].spent);
+    assert!(store.get_note(&wallet_note.nullifier).unwrap().is_some());
+
+    store.delete_note(&wallet_note.nullifier).unwrap();
+    assert!(store.notes().unwrap().is_empty());
+}
+
+#[test]
+fn test_note_export() {
+    let key = SaplingKey::generate_key();
+    let note = Note::new(key.generate_public_address(), 7, Memo::default());
+    let witness = make_fake_witness(&note);
+    let positioned_note = PositionedNote::new(note, witness_position(&witness));
+    let wallet_note = WalletNote {
+        nullifier: positioned_note.nullifier(&key),
+        note: positioned_note,
+        spent: false,
+    };
+
+    let mut store = MemoryWalletStore::new();
+    store.put_note(wallet_note).unwrap();
+    let wallet = Wallet::new(key.clone(), store);
+    let exported = wallet.export_notes().unwrap();
+
+    // Exports are salted, so the same notes never encrypt the same way
+    assert_ne!(exported, wallet.export_notes().unwrap());
+
+    let witness_for = |_: &PositionedNote| {
+        Some(Witness {
+            tree_size: witness.tree_size,
+            root_hash: witness.root_hash,
+            auth_path: witness.auth_path.clone(),
+        })
+    };
+
+    // Another account can't decrypt the export
+    let mut other_wallet = Wallet::new(SaplingKey::generate_key(), MemoryWalletStore::new());
+    assert!(matches!(
+        other_wallet.import_notes(&exported, witness.root_hash, witness_for),
+        Err(WalletError::InvalidExport)
+    ));
+
+    // Notes that aren't in the trusted tree are rejected
+    let mut new_wallet = Wallet::new(key, MemoryWalletStore::new());
+    assert!(matches!(
+        new_wallet.import_notes(
+            &exported,
+            witness.root_hash + witness.root_hash,
+            witness_for
+        ),
+        Err(WalletError::InvalidExport)
+    ));
+    assert!(matches!(
+        new_wallet.import_notes(&exported, witness.root_hash, |_| None::<Witness>),
+        Err(WalletError::MissingWitness)
+    ));
+    assert_eq!(new_wallet.balance().unwrap(), 0);
+
+    assert_eq!(
+        new_wallet
+            .import_notes(&exported, witness.root_hash, witness_for)
+            .unwrap(),
+        1
+    );
+    assert_eq!(new_wallet.balance().unwrap(), 7);
+    assert_eq!(
+        new_wallet
+            .import_notes(&exported, witness.root_hash, witness_for)
+            .unwrap(),
+        0
+    );
+
+    let mut truncated = exported.clone();
+    truncated.pop();
+    assert!(new_wallet
+        .import_notes(&truncated, witness.root_hash, witness_for)
+        .is_err());
+}
+
+#[test]
+fn test_send() {
+    let sapling = sapling_bls12::SAPLING.clone();
+    let spender_key = SaplingKey::generate_key();
+    let alice_key = SaplingKey::generate_key();
+    let bob_key = SaplingKey::generate_key();
+
+    let notes: Vec<(Note, Witness)> = [30, 20, 50]
+        .iter()
+        .map(|value| {
+            let note = Note::new(
+                spender_key.generate_public_address(),
+                *value,
+                Memo::default(),
+            );
+            let witness = make_fake_witness(&note);
+            (note, witness)
+        })
+        .collect();
+    let recipients = [
+        Recipient {
+            address: alice_key.generate_public_address(),
+            value: 25,
+            memo: Memo::from("alice"),
+        },
+        Recipient {
+            address: bob_key.generate_public_address(),
+            value: 20,
+            memo: Memo::from("bob"),
+        },
+    ];
+
+    // The first two notes cover the payments and fee, with 4 in change
+    let transaction = send(sapling.clone(), &spender_key, &notes, &recipients, 1, 100)
+        .expect("should be able to send");
+    transaction.verify().expect("transaction should verify");
+    assert_eq!(transaction.spends().len(), 2);
+    assert_eq!(transaction.receipts().len(), 3);
+    assert_eq!(transaction.transaction_fee(), 1);
+    assert_eq!(transaction.expiration_sequence(), 100);
+
+    let alice_notes: Vec<Note> = transaction
+        .iter_receipts()
+        .filter_map(|receipt| {
+            receipt
+                .merkle_note()
+                .decrypt_note_for_owner(alice_key.incoming_view_key())
+                .ok()
+        })
+        .collect();
+    assert_eq!(alice_notes.len(), 1);
+    assert_eq!(alice_notes[0].value(), 25);
+    assert_eq!(alice_notes[0].memo(), Memo::from("alice"));
+
+    let result = send(sapling.clone(), &spender_key, &notes, &recipients, 56, 0);
+    assert!(matches!(result, Err(WalletError::InsufficientFundsError)));
+
+    let result = send(sapling, &spender_key, &notes, &[], 1, 0);
+    assert!(matches!(result, Err(WalletError::NoRecipients)));
+}
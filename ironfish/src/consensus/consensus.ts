@@ -1,6 +1,12 @@
 /* This Source Code Form is subject to the terms of the Mozilla Public
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+import * as native from '@ironfish/rust-nodejs'
+
+/*
+ * Constants the network has to agree on are defined in ironfish-rust, so the
+ * node and the Rust code can't disagree about them. Change them there.
+ */
 
 /**
  * The hash used in the "previousHash" field on the initial block in the
@@ -12,18 +18,23 @@ export const GENESIS_BLOCK_PREVIOUS = Buffer.alloc(32)
 /**
  * The sequence of the genesis block starts at 1
  */
-export const GENESIS_BLOCK_SEQUENCE = 1
+export const GENESIS_BLOCK_SEQUENCE = native.GENESIS_BLOCK_SEQUENCE
 
 /**
  * When adding a block, the block can be this amount of seconds into the future
  * without rejecting it
  */
-export const ALLOWED_BLOCK_FUTURE_SECONDS = 15
+export const ALLOWED_BLOCK_FUTURE_SECONDS = native.ALLOWED_BLOCK_FUTURE_SECONDS
 
 /**
  * The amount of coins in the genesis block
  */
-export const GENESIS_SUPPLY_IN_IRON = 42000000
+export const GENESIS_SUPPLY_IN_IRON = native.GENESIS_SUPPLY_IN_IRON
+
+/**
+ * The number of ore, the smallest unit of value in a note, in one iron
+ */
+export const ORE_PER_IRON = native.ORE_PER_IRON
 
 /**
  * The oldest the tip should be before we consider the chain synced
@@ -33,34 +44,40 @@ export const MAX_SYNCED_AGE_MS = 5 * 60 * 60 * 1000
 /**
  * The maximum allowed requested blocks by the network
  */
-export const MAX_REQUESTED_BLOCKS = 50
+export const MAX_REQUESTED_BLOCKS = native.MAX_REQUESTED_BLOCKS
 
 /**
  * Max size for a message, for instance when requesting batches of blocks
  * TODO 256MB is way too big
  */
-export const MAX_MESSAGE_SIZE = 256 * 1024 * 1024
+export const MAX_MESSAGE_SIZE = native.MAX_MESSAGE_SIZE
 
 /**
  * The average time that all blocks should be mined
  *
  * NOTE: This is not used in target calculation, or IRON_FISH_YEAR_IN_BLOCKS.
  */
-export const TARGET_BLOCK_TIME_IN_SECONDS = 60
+export const TARGET_BLOCK_TIME_IN_SECONDS = native.TARGET_BLOCK_TIME_IN_SECONDS
 
 /**
  * The time range when difficulty and target not change
  */
-export const TARGET_BUCKET_TIME_IN_SECONDS = 10
+export const TARGET_BUCKET_TIME_IN_SECONDS = native.TARGET_BUCKET_TIME_IN_SECONDS
 
 /**
  * Graffiti sizes in bytes
  */
-export const GRAFFITI_SIZE = 32
+export const GRAFFITI_SIZE = native.GRAFFITI_SIZE
 
 /*
  * A ratio of blocks per year that represents an approximation of how many blocks are considered a "year".
  * It's generally an approximation based on TARGET_BLOCK_TIME_IN_SECONDS second block times.
  * It's used in calculating how much a miner should get in rewards.
  */
-export const IRON_FISH_YEAR_IN_BLOCKS = (365 * 24 * 60 * 60) / TARGET_BLOCK_TIME_IN_SECONDS
+export const IRON_FISH_YEAR_IN_BLOCKS = native.IRON_FISH_YEAR_IN_BLOCKS
+
+/**
+ * The most transactions a miner puts in a block template, including the
+ * miners fee
+ */
+export const MAX_TRANSACTIONS_PER_BLOCK = native.MAX_TRANSACTIONS_PER_BLOCK
@@ -0,0 +1,30 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+import { GetCompactBlockRequest, GetCompactBlockResponse } from './getCompactBlock'
+
+describe('GetCompactBlockRequest', () => {
+  it('serializes the object into a buffer and deserializes to the original object', () => {
+    const rpcId = 0
+    const blockHash = Buffer.alloc(32, 1)
+
+    const message = new GetCompactBlockRequest(blockHash, rpcId)
+    const buffer = message.serialize()
+    const deserializedMessage = GetCompactBlockRequest.deserialize(buffer, rpcId)
+
+    expect(deserializedMessage).toEqual(message)
+  })
+})
+
+describe('GetCompactBlockResponse', () => {
+  it('serializes the object into a buffer and deserializes to the original object', () => {
+    const rpcId = 0
+    const compactBlock = Buffer.alloc(100, 2)
+
+    const message = new GetCompactBlockResponse(compactBlock, rpcId)
+    const buffer = message.serialize()
+    const deserializedMessage = GetCompactBlockResponse.deserialize(buffer, rpcId)
+
+    expect(deserializedMessage).toEqual(message)
+  })
+})
@@ -0,0 +1,60 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+import bufio from 'bufio'
+import { NetworkMessageType } from '../types'
+import { Direction, RpcNetworkMessage } from './rpcNetworkMessage'
+
+export class GetCompactBlockRequest extends RpcNetworkMessage {
+  readonly blockHash: Buffer
+
+  constructor(blockHash: Buffer, rpcId?: number) {
+    super(NetworkMessageType.GetCompactBlockRequest, Direction.Request, rpcId)
+    this.blockHash = blockHash
+  }
+
+  serialize(): Buffer {
+    const bw = bufio.write(this.getSize())
+    bw.writeHash(this.blockHash)
+    return bw.render()
+  }
+
+  static deserialize(buffer: Buffer, rpcId: number): GetCompactBlockRequest {
+    const reader = bufio.read(buffer, true)
+    const blockHash = reader.readHash()
+    return new GetCompactBlockRequest(blockHash, rpcId)
+  }
+
+  getSize(): number {
+    return 32
+  }
+}
+
+/**
+ * The block in the compact form used by light clients, as serialized by
+ * CompactBlock in ironfish-rust. It takes up the rest of the message.
+ */
+export class GetCompactBlockResponse extends RpcNetworkMessage {
+  readonly compactBlock: Buffer
+
+  constructor(compactBlock: Buffer, rpcId: number) {
+    super(NetworkMessageType.GetCompactBlockResponse, Direction.Response, rpcId)
+    this.compactBlock = compactBlock
+  }
+
+  serialize(): Buffer {
+    const bw = bufio.write(this.getSize())
+    bw.writeBytes(this.compactBlock)
+    return bw.render()
+  }
+
+  static deserialize(buffer: Buffer, rpcId: number): GetCompactBlockResponse {
+    const reader = bufio.read(buffer, true)
+    const compactBlock = reader.readBytes(reader.left())
+    return new GetCompactBlockResponse(compactBlock, rpcId)
+  }
+
+  getSize(): number {
+    return this.compactBlock.length
+  }
+}
@@ -30,6 +30,7 @@ import {
   GetBlockTransactionsRequest,
   GetBlockTransactionsResponse,
 } from './messages/getBlockTransactions'
+import { GetCompactBlockRequest } from './messages/getCompactBlock'
 import { GossipNetworkMessage } from './messages/gossipNetworkMessage'
 import {
   displayNetworkMessageType,
@@ -565,6 +566,8 @@ export class PeerNetwork {
           responseMessage = this.onPooledTransactionsRequest(rpcMessage, rpcId)
         } else if (rpcMessage instanceof GetBlockTransactionsRequest) {
           responseMessage = await this.onGetBlockTransactionsRequest(peer, rpcMessage)
+        } else if (rpcMessage instanceof GetCompactBlockRequest) {
+          throw new CannotSatisfyRequestError('Compact blocks are not served by this node')
         } else {
           throw new Error(`Invalid rpc message type: '${rpcMessage.type}'`)
         }
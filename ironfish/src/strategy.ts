@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-import { GENESIS_SUPPLY_IN_IRON, IRON_FISH_YEAR_IN_BLOCKS } from './consensus'
+import { GENESIS_SUPPLY_IN_IRON, IRON_FISH_YEAR_IN_BLOCKS, ORE_PER_IRON } from './consensus'
 import { NoteHasher } from './merkletree/hasher'
 import { BlockSerde } from './primitives/block'
 import { BlockHash, BlockHeaderSerde, hashBlockHeader } from './primitives/blockheader'
@@ -85,7 +85,7 @@ export class Strategy {
   }
 
   convertIronToOre(iron: number): number {
-    return Math.round(iron * 10 ** 8)
+    return Math.round(iron * ORE_PER_IRON)
   }
 
   /**
@@ -2,9 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+import { ORE_PER_IRON } from '../consensus/consensus'
+
 const ORE_TICKER = '$ORE'
 const IRON_TICKER = '$IRON'
-const ORE_TO_IRON = 100000000
+const ORE_TO_IRON = ORE_PER_IRON
 export const MINIMUM_IRON_AMOUNT = 1 / ORE_TO_IRON
 export const MAXIMUM_IRON_AMOUNT = 1.8446744e19
 const FLOAT = ORE_TO_IRON.toString().length - 1